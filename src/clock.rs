@@ -0,0 +1,142 @@
+use std::{
+    ffi::CString,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use smithay::{
+    output::Output,
+    reexports::calloop::timer::{TimeoutAction, Timer},
+    utils::{Physical, Point, Size},
+};
+
+use crate::{Backend, Buddaraysh};
+
+/// Distance of the clock from the edges of the output
+const CLOCK_MARGIN: i32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClockConfig {
+    pub corner: ClockCorner,
+    /// strftime format of the clock
+    pub format: String,
+    /// show the clock on every output instead of only the one with the pointer
+    pub all_outputs: bool,
+}
+
+impl ClockConfig {
+    pub fn from_env() -> Self {
+        let corner = match std::env::var("BUD_CLOCK_POSITION").as_deref() {
+            Ok("top-left") => ClockCorner::TopLeft,
+            Ok("bottom-left") => ClockCorner::BottomLeft,
+            Ok("bottom-right") => ClockCorner::BottomRight,
+            _ => ClockCorner::TopRight,
+        };
+
+        let format = std::env::var("BUD_CLOCK_FORMAT")
+            .ok()
+            .filter(|x| !x.is_empty())
+            .unwrap_or_else(|| String::from("%H:%M  %Y-%m-%d"));
+
+        let all_outputs = std::env::var("BUD_CLOCK_OUTPUT")
+            .map(|x| x.to_lowercase() == "all")
+            .unwrap_or(false);
+
+        Self {
+            corner,
+            format,
+            all_outputs,
+        }
+    }
+
+    /// Where a clock of the given size goes on the output
+    pub fn location(
+        &self,
+        output: &Output,
+        clock_size: Size<i32, Physical>,
+    ) -> Point<i32, Physical> {
+        let output_size = output
+            .current_mode()
+            .map(|mode| output.current_transform().transform_size(mode.size))
+            .unwrap_or_default();
+        let margin = (CLOCK_MARGIN as f64 * output.current_scale().fractional_scale()) as i32;
+
+        let left = margin;
+        let right = output_size.w - clock_size.w - margin;
+        let top = margin;
+        let bottom = output_size.h - clock_size.h - margin;
+
+        match self.corner {
+            ClockCorner::TopLeft => (left, top).into(),
+            ClockCorner::TopRight => (right, top).into(),
+            ClockCorner::BottomLeft => (left, bottom).into(),
+            ClockCorner::BottomRight => (right, bottom).into(),
+        }
+    }
+}
+
+/// Formats the current local time with strftime
+fn local_time(format: &str) -> String {
+    let Ok(format) = CString::new(format) else {
+        return String::new();
+    };
+
+    let mut buffer = [0u8; 128];
+    // Safety: tm is only read after localtime_r filled it, and strftime is told the buffer's size
+    let len = unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return String::new();
+        }
+        libc::strftime(
+            buffer.as_mut_ptr() as *mut libc::c_char,
+            buffer.len(),
+            format.as_ptr(),
+            &tm,
+        )
+    };
+
+    String::from_utf8_lossy(&buffer[..len]).into_owned()
+}
+
+/// Time left until the next minute starts
+fn until_next_minute() -> Duration {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Duration::from_secs(60 - now.as_secs() % 60) - Duration::from_nanos(now.subsec_nanos() as u64)
+}
+
+impl<BackendData: Backend> Buddaraysh<BackendData> {
+    /// Shows the clock overlay, or hides it if it's shown
+    pub fn toggle_clock(&mut self) {
+        if let Some(token) = self.clock_timer.take() {
+            self.loop_handle.remove(token);
+            self.clock_text = None;
+            return;
+        }
+
+        let format = self.clock_config.format.clone();
+        self.clock_text = Some(local_time(&format));
+
+        let token = self
+            .loop_handle
+            .insert_source(
+                Timer::from_duration(until_next_minute()),
+                move |_, _, data| {
+                    data.state.clock_text = Some(local_time(&format));
+                    TimeoutAction::ToDuration(until_next_minute())
+                },
+            )
+            .expect("failed to schedule clock timer");
+        self.clock_timer = Some(token);
+    }
+}
@@ -0,0 +1,399 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use serde::Deserialize;
+use smithay::{
+    input::keyboard::{xkb, Keysym, ModifiersState, XkbConfig},
+    utils::Transform,
+};
+use tracing::{info, warn};
+
+use crate::{
+    state::{parse_action, parse_button, SpotlightConfig, SpotlightTarget},
+    workspace::DEFAULT_WORKSPACE_AMOUNT,
+    Action,
+};
+
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    bind: Vec<BindEntry>,
+    #[serde(default)]
+    keyboard: KeyboardEntry,
+    workspaces: Option<usize>,
+    #[serde(default)]
+    output: HashMap<String, OutputEntry>,
+    #[serde(default)]
+    focus_follows_mouse: bool,
+    /// button name to action, like `right = "spawn:fuzzel"`
+    #[serde(default)]
+    desktop: HashMap<String, String>,
+    /// output name to seconds without input before it's turned off, like `DP-1 = 600`
+    #[serde(default)]
+    dpms: HashMap<String, u64>,
+    #[serde(default)]
+    spotlight: SpotlightEntry,
+    move_step: Option<i32>,
+    edge_resistance: Option<i32>,
+}
+
+/// The `[spotlight]` table, unset fields keep their defaults
+#[derive(Debug, Default, Deserialize)]
+struct SpotlightEntry {
+    target: Option<String>,
+    radius: Option<i32>,
+    alpha: Option<f32>,
+}
+
+/// The `[keyboard]` table, unset fields fall back to the `XKB_DEFAULT_*` variables
+#[derive(Debug, Default, Deserialize)]
+struct KeyboardEntry {
+    model: Option<String>,
+    layout: Option<String>,
+    variant: Option<String>,
+    options: Option<String>,
+}
+
+/// An `[output.<name>]` table as it's written in the config file
+#[derive(Debug, Deserialize)]
+struct OutputEntry {
+    mode: Option<String>,
+    position: Option<(i32, i32)>,
+    scale: Option<f64>,
+    transform: Option<String>,
+    enabled: Option<bool>,
+}
+
+/// A `[[bind]]` entry as it's written in the config file
+#[derive(Debug, Deserialize)]
+struct BindEntry {
+    #[serde(default)]
+    modifiers: Vec<String>,
+    key: String,
+    action: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct BindModifiers {
+    logo: bool,
+    alt: bool,
+    shift: bool,
+    ctrl: bool,
+}
+
+impl BindModifiers {
+    fn parse(names: &[String]) -> Option<Self> {
+        let mut modifiers = Self::default();
+        for name in names {
+            match name.to_lowercase().as_str() {
+                "logo" | "super" => modifiers.logo = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "ctrl" | "control" => modifiers.ctrl = true,
+                _ => return None,
+            }
+        }
+
+        Some(modifiers)
+    }
+
+    /// Exactly these modifiers are held, like the built in keybinds
+    fn matches(&self, modifiers: &ModifiersState) -> bool {
+        self.logo == modifiers.logo
+            && self.alt == modifiers.alt
+            && self.shift == modifiers.shift
+            && self.ctrl == modifiers.ctrl
+    }
+}
+
+#[derive(Debug, Clone)]
+struct KeyBind {
+    modifiers: BindModifiers,
+    keysym: Keysym,
+    action: Action,
+}
+
+/// The xkb keymap of the keyboard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardConfig {
+    pub model: String,
+    pub layout: String,
+    pub variant: String,
+    pub options: Option<String>,
+}
+
+impl Default for KeyboardConfig {
+    /// Reads the `XKB_DEFAULT_*` variables
+    fn default() -> Self {
+        Self {
+            model: std::env::var("XKB_DEFAULT_MODEL").unwrap_or_default(),
+            layout: std::env::var("XKB_DEFAULT_LAYOUT").unwrap_or_else(|_| String::from("us,ara")),
+            variant: std::env::var("XKB_DEFAULT_VARIANT").unwrap_or_default(),
+            options: std::env::var("XKB_DEFAULT_OPTIONS")
+                .ok()
+                .or_else(|| Some(String::from("grp:alt_shift_toggle"))),
+        }
+    }
+}
+
+impl KeyboardConfig {
+    pub fn xkb_config(&self) -> XkbConfig<'_> {
+        XkbConfig {
+            model: &self.model,
+            layout: &self.layout,
+            variant: &self.variant,
+            options: self.options.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// How an output is set up when it gets connected, anything left out is picked like for outputs
+/// without a config
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputConfig {
+    /// like "2560x1440@144", the backend looks it up in the output's modes
+    pub mode: Option<String>,
+    pub position: Option<(i32, i32)>,
+    pub scale: Option<f64>,
+    pub transform: Option<Transform>,
+    /// disabled outputs aren't used at all
+    pub enabled: bool,
+}
+
+/// Keybindings, keyboard layout, outputs and workspace amount from `~/.config/buddaraysh/config.toml`,
+/// the keybindings take precedence over the built in ones
+#[derive(Debug)]
+pub struct Config {
+    binds: Vec<KeyBind>,
+    pub keyboard: KeyboardConfig,
+    /// only read at startup, workspaces aren't added or removed while running
+    pub workspaces: usize,
+    /// by output name, like "DP-1", only read when an output gets connected
+    pub outputs: HashMap<String, OutputConfig>,
+    /// hovering a window focuses it, instead of clicking it
+    pub focus_follows_mouse: bool,
+    /// actions of buttons clicked on the desktop, where no window is under the pointer
+    desktop_actions: Vec<(u32, Action)>,
+    /// how long outputs stay on without input, by output name, only used by the udev backend
+    pub dpms_timeouts: HashMap<String, Duration>,
+    pub spotlight: SpotlightConfig,
+    /// pixels a window moves by with Super+Alt+H/J/K/L, while the grid isn't shown
+    pub move_step: i32,
+    /// how far past the seam between two outputs a dragged window sticks to it
+    pub edge_resistance: Option<i32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            binds: Vec::new(),
+            keyboard: KeyboardConfig::default(),
+            workspaces: DEFAULT_WORKSPACE_AMOUNT,
+            outputs: HashMap::new(),
+            focus_follows_mouse: false,
+            desktop_actions: Vec::new(),
+            dpms_timeouts: HashMap::new(),
+            spotlight: SpotlightConfig::default(),
+            move_step: DEFAULT_MOVE_STEP,
+            edge_resistance: None,
+        }
+    }
+}
+
+const DEFAULT_MOVE_STEP: i32 = 32;
+
+fn config_path() -> PathBuf {
+    let home_dir = std::env::var("HOME").expect("HOME should always be set");
+    PathBuf::from(home_dir).join(".config/buddaraysh/config.toml")
+}
+
+impl Config {
+    /// Reads the config file, a missing or broken file leaves only the built in keybinds
+    /// and the keyboard layout of the environment
+    pub fn load() -> Self {
+        let path = config_path();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                warn!(?path, "Failed to read config: {}", err);
+                return Self::default();
+            }
+        };
+
+        let file: ConfigFile = match toml::from_str(&text) {
+            Ok(file) => file,
+            Err(err) => {
+                warn!(?path, "Failed to parse config: {}", err);
+                return Self::default();
+            }
+        };
+
+        let binds: Vec<KeyBind> = file
+            .bind
+            .into_iter()
+            .filter_map(|entry| {
+                let bind = parse_bind(&entry);
+                if bind.is_none() {
+                    warn!(?entry, "Invalid keybind");
+                }
+                bind
+            })
+            .collect();
+        info!(?path, binds = binds.len(), "Loaded config");
+
+        let defaults = KeyboardConfig::default();
+        let keyboard = KeyboardConfig {
+            model: file.keyboard.model.unwrap_or(defaults.model),
+            layout: file.keyboard.layout.unwrap_or(defaults.layout),
+            variant: file.keyboard.variant.unwrap_or(defaults.variant),
+            options: file.keyboard.options.or(defaults.options),
+        };
+
+        let outputs = file
+            .output
+            .into_iter()
+            .map(|(name, entry)| {
+                let output = parse_output(&name, entry);
+                (name, output)
+            })
+            .collect();
+
+        let desktop_actions = file
+            .desktop
+            .iter()
+            .filter_map(|(button, action)| {
+                let parsed = parse_button(button).zip(parse_action(action));
+                if parsed.is_none() {
+                    warn!(button, action, "Invalid desktop action");
+                }
+                parsed
+            })
+            .collect();
+
+        let dpms_timeouts = file
+            .dpms
+            .into_iter()
+            .filter_map(|(name, seconds)| {
+                if seconds == 0 {
+                    warn!(name, "Invalid dpms timeout");
+                    return None;
+                }
+                Some((name, Duration::from_secs(seconds)))
+            })
+            .collect();
+
+        let spotlight = parse_spotlight(file.spotlight);
+
+        Self {
+            binds,
+            keyboard,
+            workspaces: file
+                .workspaces
+                .map(|amount| amount.max(1))
+                .unwrap_or(DEFAULT_WORKSPACE_AMOUNT),
+            outputs,
+            focus_follows_mouse: file.focus_follows_mouse,
+            desktop_actions,
+            dpms_timeouts,
+            spotlight,
+            move_step: file
+                .move_step
+                .map(|step| step.max(1))
+                .unwrap_or(DEFAULT_MOVE_STEP),
+            edge_resistance: file.edge_resistance.filter(|resistance| *resistance > 0),
+        }
+    }
+
+    pub fn action_for(&self, modifiers: &ModifiersState, raw_syms: &[Keysym]) -> Option<Action> {
+        self.binds
+            .iter()
+            .find(|bind| bind.modifiers.matches(modifiers) && raw_syms.contains(&bind.keysym))
+            .map(|bind| bind.action.clone())
+    }
+
+    /// The action of clicking `button` on the desktop, if any
+    pub fn desktop_action_for(&self, button: u32) -> Option<Action> {
+        self.desktop_actions
+            .iter()
+            .find(|(b, _)| *b == button)
+            .map(|(_, action)| action.clone())
+    }
+}
+
+fn parse_bind(entry: &BindEntry) -> Option<KeyBind> {
+    let keysym = xkb::keysym_from_name(&entry.key, xkb::KEYSYM_CASE_INSENSITIVE);
+    if keysym == Keysym::NoSymbol {
+        return None;
+    }
+
+    Some(KeyBind {
+        modifiers: BindModifiers::parse(&entry.modifiers)?,
+        keysym,
+        action: parse_action(&entry.action)?,
+    })
+}
+
+/// Leaves out an invalid scale or transform, the rest of the output's config still applies
+fn parse_output(name: &str, entry: OutputEntry) -> OutputConfig {
+    let scale = entry.scale.filter(|scale| {
+        let valid = *scale > 0.0;
+        if !valid {
+            warn!(name, scale, "Invalid output scale");
+        }
+        valid
+    });
+    let transform = entry.transform.and_then(|transform| {
+        let parsed = parse_transform(&transform);
+        if parsed.is_none() {
+            warn!(name, "Invalid output transform: {}", transform);
+        }
+        parsed
+    });
+
+    OutputConfig {
+        mode: entry.mode,
+        position: entry.position,
+        scale,
+        transform,
+        enabled: entry.enabled.unwrap_or(true),
+    }
+}
+
+/// Leaves out an invalid target, the radius and alpha are clamped into range
+fn parse_spotlight(entry: SpotlightEntry) -> SpotlightConfig {
+    let defaults = SpotlightConfig::default();
+    let target = entry.target.and_then(|target| {
+        let parsed = match target.to_lowercase().as_str() {
+            "pointer" => Some(SpotlightTarget::Pointer),
+            "focus" => Some(SpotlightTarget::Focus),
+            _ => None,
+        };
+        if parsed.is_none() {
+            warn!("Invalid spotlight target: {}", target);
+        }
+        parsed
+    });
+
+    SpotlightConfig {
+        target: target.unwrap_or(defaults.target),
+        radius: entry.radius.map_or(defaults.radius, |radius| radius.max(0)),
+        alpha: entry
+            .alpha
+            .map_or(defaults.alpha, |alpha| alpha.clamp(0.0, 1.0)),
+    }
+}
+
+fn parse_transform(name: &str) -> Option<Transform> {
+    Some(match name {
+        "normal" | "0" => Transform::Normal,
+        "90" => Transform::_90,
+        "180" => Transform::_180,
+        "270" => Transform::_270,
+        "flipped" => Transform::Flipped,
+        "flipped-90" => Transform::Flipped90,
+        "flipped-180" => Transform::Flipped180,
+        "flipped-270" => Transform::Flipped270,
+        _ => return None,
+    })
+}
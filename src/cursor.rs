@@ -10,6 +10,8 @@ static FALLBACK_CURSOR_DATA: &[u8] = include_bytes!("../resources/cursor.rgba");
 
 pub struct Cursor {
     icons: Vec<Image>,
+    /// shown while an app is starting up, falls back to `icons` if the theme has none
+    busy_icons: Vec<Image>,
     size: u32,
 }
 
@@ -24,7 +26,7 @@ impl Cursor {
             .unwrap_or(24);
 
         let theme = CursorTheme::load(&name);
-        let icons = load_icon(&theme)
+        let icons = load_icon(&theme, &["default", "left_ptr"])
             .map_err(|err| warn!("Unable to load xcursor: {}, using fallback cursor", err))
             .unwrap_or_else(|_| {
                 vec![Image {
@@ -39,12 +41,21 @@ impl Cursor {
                 }]
             });
 
-        Cursor { icons, size }
+        let busy_icons = load_icon(&theme, &["progress", "left_ptr_watch", "watch", "wait"])
+            .map_err(|err| warn!("Unable to load busy xcursor: {}, using default cursor", err))
+            .unwrap_or_else(|_| icons.clone());
+
+        Cursor {
+            icons,
+            busy_icons,
+            size,
+        }
     }
 
-    pub fn get_image(&self, scale: u32, time: Duration) -> Image {
+    pub fn get_image(&self, busy: bool, scale: u32, time: Duration) -> Image {
         let size = self.size * scale;
-        frame(time.as_millis() as u32, size, &self.icons)
+        let icons = if busy { &self.busy_icons } else { &self.icons };
+        frame(time.as_millis() as u32, size, icons)
     }
 }
 
@@ -76,19 +87,20 @@ fn frame(mut millis: u32, size: u32, images: &[Image]) -> Image {
 
 #[derive(thiserror::Error, Debug)]
 enum Error {
-    #[error("Theme has no default cursor")]
-    NoDefaultCursor,
+    #[error("Theme has no {0} cursor")]
+    NoCursor(String),
     #[error("Error opening xcursor file: {0}")]
     File(#[from] std::io::Error),
     #[error("Failed to parse XCursor file")]
     Parse,
 }
 
-fn load_icon(theme: &CursorTheme) -> Result<Vec<Image>, Error> {
-    let icon_path = theme
-        .load_icon("default")
-        .or_else(|| theme.load_icon("left_ptr"))
-        .ok_or(Error::NoDefaultCursor)?;
+/// Loads the first of `names` that the theme has
+fn load_icon(theme: &CursorTheme, names: &[&str]) -> Result<Vec<Image>, Error> {
+    let icon_path = names
+        .iter()
+        .find_map(|name| theme.load_icon(name))
+        .ok_or_else(|| Error::NoCursor(names[0].to_string()))?;
     let mut cursor_file = std::fs::File::open(icon_path)?;
     let mut cursor_data = Vec::new();
     cursor_file.read_to_end(&mut cursor_data)?;
@@ -13,7 +13,6 @@ use smithay::{
     render_elements,
     utils::{Physical, Point, Scale},
 };
-#[cfg(feature = "debug")]
 use smithay::{
     backend::renderer::{
         element::{Element, Id, RenderElement},
@@ -25,6 +24,10 @@ use smithay::{
 
 pub static CLEAR_COLOR: [f32; 4] = [0.8, 0.8, 0.9, 1.0];
 pub static CLEAR_COLOR_FULLSCREEN: [f32; 4] = [0.0, 0.0, 0.0, 0.0];
+pub static ACTIVE_OUTPUT_COLOR: [f32; 4] = [0.33, 0.47, 0.85, 1.0];
+pub const ACTIVE_OUTPUT_BORDER_WIDTH: i32 = 2;
+pub static GRID_COLOR: [f32; 4] = [0.25, 0.25, 0.25, 0.4];
+pub const GRID_LINE_WIDTH: i32 = 1;
 
 pub struct PointerElement<T: Texture> {
     texture: Option<TextureBuffer<T>>,
@@ -118,8 +121,33 @@ where
     }
 }
 
-#[cfg(feature = "debug")]
-pub static FPS_NUMBERS_PNG: &[u8] = include_bytes!("../resources/numbers.png");
+pub static NUMBERS_PNG: &[u8] = include_bytes!("../resources/numbers.png");
+
+/// Size of a digit in the numbers texture
+const DIGIT_SIZE: (i32, i32) = (22, 35);
+/// How far apart digits are drawn
+const DIGIT_ADVANCE: i32 = 24;
+/// How far apart the separators of the clock are drawn
+const SEPARATOR_ADVANCE: i32 = 12;
+static CLOCK_OUTLINE_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+static CLOCK_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// Where a digit is in the numbers texture
+fn digit_texture_src(digit: u32) -> Rectangle<i32, Buffer> {
+    match digit {
+        9 => Rectangle::from_loc_and_size((0, 0), DIGIT_SIZE),
+        6 => Rectangle::from_loc_and_size((22, 0), DIGIT_SIZE),
+        3 => Rectangle::from_loc_and_size((44, 0), DIGIT_SIZE),
+        1 => Rectangle::from_loc_and_size((66, 0), DIGIT_SIZE),
+        8 => Rectangle::from_loc_and_size((0, 35), DIGIT_SIZE),
+        0 => Rectangle::from_loc_and_size((22, 35), DIGIT_SIZE),
+        2 => Rectangle::from_loc_and_size((44, 35), DIGIT_SIZE),
+        7 => Rectangle::from_loc_and_size((0, 70), DIGIT_SIZE),
+        4 => Rectangle::from_loc_and_size((22, 70), DIGIT_SIZE),
+        5 => Rectangle::from_loc_and_size((44, 70), DIGIT_SIZE),
+        _ => unreachable!(),
+    }
+}
 
 #[cfg(feature = "debug")]
 #[derive(Debug, Clone)]
@@ -226,19 +254,7 @@ where
                     x
                 })
                 .collect::<Vec<_>>();
-            let texture_src: Rectangle<i32, Buffer> = match digit {
-                9 => Rectangle::from_loc_and_size((0, 0), (22, 35)),
-                6 => Rectangle::from_loc_and_size((22, 0), (22, 35)),
-                3 => Rectangle::from_loc_and_size((44, 0), (22, 35)),
-                1 => Rectangle::from_loc_and_size((66, 0), (22, 35)),
-                8 => Rectangle::from_loc_and_size((0, 35), (22, 35)),
-                0 => Rectangle::from_loc_and_size((22, 35), (22, 35)),
-                2 => Rectangle::from_loc_and_size((44, 35), (22, 35)),
-                7 => Rectangle::from_loc_and_size((0, 70), (22, 35)),
-                4 => Rectangle::from_loc_and_size((22, 70), (22, 35)),
-                5 => Rectangle::from_loc_and_size((44, 70), (22, 35)),
-                _ => unreachable!(),
-            };
+            let texture_src = digit_texture_src(digit);
 
             frame.render_texture_from_to(
                 &self.texture,
@@ -254,3 +270,163 @@ where
         Ok(())
     }
 }
+
+/// Clock drawn with the digits of the numbers texture, other than digits it can only
+/// show `:`, `-`, `.` and spaces, everything else is left out
+#[derive(Debug, Clone)]
+pub struct ClockElement<T: Texture> {
+    id: Id,
+    text: String,
+    location: Point<i32, Physical>,
+    texture: T,
+    commit_counter: CommitCounter,
+}
+
+impl<T: Texture> ClockElement<T> {
+    pub fn new(texture: T) -> Self {
+        ClockElement {
+            id: Id::new(),
+            text: String::new(),
+            location: (0, 0).into(),
+            texture,
+            commit_counter: CommitCounter::default(),
+        }
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        if self.text != text {
+            self.text = text
+                .chars()
+                .filter(|c| clock_char_advance(*c).is_some())
+                .collect();
+            self.commit_counter.increment();
+        }
+    }
+
+    pub fn set_location(&mut self, location: Point<i32, Physical>) {
+        self.location = location;
+    }
+
+    pub fn size(&self) -> Size<i32, Logical> {
+        let width = self.text.chars().filter_map(clock_char_advance).sum();
+        (width, DIGIT_SIZE.1).into()
+    }
+}
+
+fn clock_char_advance(c: char) -> Option<i32> {
+    match c {
+        '0'..='9' => Some(DIGIT_ADVANCE),
+        ':' | '-' | '.' | ' ' => Some(SEPARATOR_ADVANCE),
+        _ => None,
+    }
+}
+
+/// The parts of a separator, relative to where it starts
+fn clock_separator_rects(c: char) -> Vec<Rectangle<i32, Logical>> {
+    match c {
+        ':' => vec![
+            Rectangle::from_loc_and_size((2, 8), (8, 8)),
+            Rectangle::from_loc_and_size((2, 22), (8, 8)),
+        ],
+        '-' => vec![Rectangle::from_loc_and_size((0, 14), (12, 7))],
+        '.' => vec![Rectangle::from_loc_and_size((2, 27), (8, 8))],
+        _ => vec![],
+    }
+}
+
+impl<T> Element for ClockElement<T>
+where
+    T: Texture + 'static,
+{
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn location(&self, _scale: Scale<f64>) -> Point<i32, Physical> {
+        self.location
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        let size = self.size();
+        Rectangle::from_loc_and_size((0, 0), (size.w, size.h)).to_f64()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        Rectangle::from_loc_and_size(self.location, self.size().to_physical_precise_round(scale))
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.commit_counter
+    }
+}
+
+impl<R> RenderElement<R> for ClockElement<<R as Renderer>::TextureId>
+where
+    R: Renderer + ImportAll,
+    <R as Renderer>::TextureId: 'static,
+{
+    fn draw(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        _src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        let scale = dst.size.to_f64() / self.src().size;
+        // damage is relative to the element, but each part is drawn relative to itself
+        let damage_in = |part: Rectangle<i32, Physical>| {
+            damage
+                .iter()
+                .map(|x| Rectangle::from_loc_and_size(x.loc + dst.loc, x.size))
+                .flat_map(|x| x.intersection(part))
+                .map(|mut x| {
+                    x.loc -= part.loc;
+                    x
+                })
+                .collect::<Vec<_>>()
+        };
+        let to_physical = |rect: Rectangle<i32, Logical>, offset: Point<f64, Physical>| {
+            let loc = (rect.loc.to_f64().to_physical(scale) + offset).to_i32_round();
+            let size = rect.size.to_f64().to_physical(scale).to_i32_round();
+            Rectangle::<i32, Physical>::from_loc_and_size(loc, size)
+        };
+
+        let mut offset: Point<f64, Physical> = dst.loc.to_f64();
+        for c in self.text.chars() {
+            let Some(advance) = clock_char_advance(c) else {
+                continue;
+            };
+
+            if let Some(digit) = c.to_digit(10) {
+                let part = to_physical(Rectangle::from_loc_and_size((0, 0), DIGIT_SIZE), offset);
+                frame.render_texture_from_to(
+                    &self.texture,
+                    digit_texture_src(digit).to_f64(),
+                    part,
+                    &damage_in(part),
+                    Transform::Normal,
+                    1.0,
+                )?;
+            } else {
+                // outlined like the digits of the texture
+                for rect in clock_separator_rects(c) {
+                    let outline = to_physical(rect, offset);
+                    frame.draw_solid(outline, &damage_in(outline), CLOCK_OUTLINE_COLOR)?;
+
+                    let inner = to_physical(
+                        Rectangle::from_loc_and_size(
+                            (rect.loc.x + 2, rect.loc.y + 2),
+                            (rect.size.w - 4, rect.size.h - 4),
+                        ),
+                        offset,
+                    );
+                    frame.draw_solid(inner, &damage_in(inner), CLOCK_COLOR)?;
+                }
+            }
+
+            offset += Point::from((advance as f64, 0.0)).to_physical(scale);
+        }
+
+        Ok(())
+    }
+}
@@ -3,3 +3,17 @@ pub use move_grab::MoveSurfaceGrab;
 
 pub mod resize_grab;
 pub use resize_grab::ResizeSurfaceGrab;
+
+/// Rounds `value` to the closest multiple of `grid_size`
+pub fn snap_to_grid(value: i32, grid_size: i32) -> i32 {
+    (value as f64 / grid_size as f64).round() as i32 * grid_size
+}
+
+/// Moves an edge at `value` onto the closest of `seams` within `resistance` pixels
+pub fn stick_to_seams(value: i32, seams: &[i32], resistance: i32) -> Option<i32> {
+    seams
+        .iter()
+        .copied()
+        .filter(|seam| (value - seam).abs() < resistance)
+        .min_by_key(|seam| (value - seam).abs())
+}
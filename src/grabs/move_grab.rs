@@ -1,12 +1,18 @@
-use crate::{focus::FocusTarget, window::WindowElement, Backend, Buddaraysh};
+use crate::{
+    focus::FocusTarget,
+    grabs::{snap_to_grid, stick_to_seams},
+    window::WindowElement,
+    Backend, Buddaraysh,
+};
 use smithay::{
+    desktop::space::SpaceElement,
     input::pointer::{
         AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
         GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent,
         GestureSwipeEndEvent, GestureSwipeUpdateEvent, GrabStartData as PointerGrabStartData,
         MotionEvent, PointerGrab, PointerInnerHandle, RelativeMotionEvent,
     },
-    utils::{Logical, Point},
+    utils::{Logical, Point, Rectangle},
 };
 
 pub struct MoveSurfaceGrab<BackendData: Backend + 'static> {
@@ -29,12 +35,38 @@ impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
         handle.motion(data, None, event);
 
         let delta = event.location - self.start_data.location;
-        let new_location = self.initial_window_location.to_f64() + delta;
-        data.workspaces.current_workspace_mut().map_window(
-            self.window.clone(),
-            new_location.to_i32_round(),
-            true,
-        );
+        let mut new_location = (self.initial_window_location.to_f64() + delta).to_i32_round();
+        if let Some(grid_size) = data.snap_grid() {
+            new_location.x = snap_to_grid(new_location.x, grid_size);
+            new_location.y = snap_to_grid(new_location.y, grid_size);
+        } else if let Some(resistance) = data.config.edge_resistance {
+            let (seams_x, seams_y) = output_seams(data);
+            let size = self.window.geometry().size;
+
+            // either edge of the window can stick, the left and top ones win
+            if let Some(x) = stick_to_seams(new_location.x, &seams_x, resistance) {
+                new_location.x = x;
+            } else if let Some(x) = stick_to_seams(new_location.x + size.w, &seams_x, resistance) {
+                new_location.x = x - size.w;
+            }
+            if let Some(y) = stick_to_seams(new_location.y, &seams_y, resistance) {
+                new_location.y = y;
+            } else if let Some(y) = stick_to_seams(new_location.y + size.h, &seams_y, resistance) {
+                new_location.y = y - size.h;
+            }
+        }
+        let workspace = data.workspaces.current_workspace();
+        let old_location = workspace.window_location(&self.window);
+        let transients = workspace.transients_of(&self.window);
+        data.workspaces
+            .current_workspace_mut()
+            .map_window(self.window.clone(), new_location, true);
+
+        // dialogs come along, staying where they were next to their parent
+        if let Some(old_location) = old_location {
+            let current = data.workspaces.current_workspace_index();
+            data.move_transients(&transients, current, current, new_location - old_location);
+        }
     }
 
     fn relative_motion(
@@ -55,11 +87,7 @@ impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
     ) {
         handle.button(data, event);
 
-        // The button is a button code as defined in the
-        // Linux kernel's linux/input-event-codes.h header file, e.g. BTN_LEFT.
-        const BTN_LEFT: u32 = 0x110;
-
-        if !handle.current_pressed().contains(&BTN_LEFT) {
+        if !handle.current_pressed().contains(&self.start_data.button) {
             // No more buttons are pressed, release the grab.
             handle.unset_grab(data, event.serial, event.time, true);
         }
@@ -158,3 +186,30 @@ impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
         &self.start_data
     }
 }
+
+/// Where two outputs of the current workspace touch, as x coordinates of vertical seams and
+/// y coordinates of horizontal ones
+fn output_seams<BackendData: Backend>(data: &Buddaraysh<BackendData>) -> (Vec<i32>, Vec<i32>) {
+    let workspace = data.workspaces.current_workspace();
+    let geometries: Vec<Rectangle<i32, Logical>> = workspace
+        .outputs()
+        .filter_map(|output| workspace.output_geometry(output))
+        .collect();
+
+    let mut seams_x = Vec::new();
+    let mut seams_y = Vec::new();
+    for a in &geometries {
+        for b in &geometries {
+            let overlaps_y = a.loc.y < b.loc.y + b.size.h && b.loc.y < a.loc.y + a.size.h;
+            let overlaps_x = a.loc.x < b.loc.x + b.size.w && b.loc.x < a.loc.x + a.size.w;
+            if overlaps_y && a.loc.x + a.size.w == b.loc.x {
+                seams_x.push(b.loc.x);
+            }
+            if overlaps_x && a.loc.y + a.size.h == b.loc.y {
+                seams_y.push(b.loc.y);
+            }
+        }
+    }
+
+    (seams_x, seams_y)
+}
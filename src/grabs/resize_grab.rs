@@ -1,4 +1,4 @@
-use crate::{focus::FocusTarget, window::WindowElement, Backend, Buddaraysh, BTN_LEFT, BTN_RIGHT};
+use crate::{focus::FocusTarget, grabs::snap_to_grid, window::WindowElement, Backend, Buddaraysh};
 use smithay::{
     desktop::{space::SpaceElement, Space},
     input::pointer::{
@@ -64,6 +64,8 @@ pub struct ResizeSurfaceGrab<BackendData: Backend + 'static> {
 
     pub initial_rect: Rectangle<i32, Logical>,
     pub last_window_size: Size<i32, Logical>,
+    /// whether the last motion resized from the center of the window
+    pub centered: bool,
 }
 
 impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
@@ -81,6 +83,13 @@ impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
 
         let mut delta = event.location - self.start_data.location;
 
+        let centered = data
+            .mouse_bindings
+            .center_resize_modifier
+            .is_pressed(&data.seat.get_keyboard().unwrap().modifier_state());
+        // the opposite edge moves along when resizing from the center, so the size changes twice as much
+        let factor = if centered { 2.0 } else { 1.0 };
+
         let mut new_window_width = self.initial_rect.size.w;
         let mut new_window_height = self.initial_rect.size.h;
 
@@ -89,7 +98,7 @@ impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
                 delta.x = -delta.x;
             }
 
-            new_window_width = (self.initial_rect.size.w as f64 + delta.x) as i32;
+            new_window_width = (self.initial_rect.size.w as f64 + delta.x * factor) as i32;
         }
 
         if self.edges.intersects(ResizeEdge::TOP | ResizeEdge::BOTTOM) {
@@ -97,7 +106,44 @@ impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
                 delta.y = -delta.y;
             }
 
-            new_window_height = (self.initial_rect.size.h as f64 + delta.y) as i32;
+            new_window_height = (self.initial_rect.size.h as f64 + delta.y * factor) as i32;
+        }
+
+        if let Some(grid_size) = data.snap_grid() {
+            let Rectangle { loc, size } = self.initial_rect;
+            if centered {
+                // snap the dragged edges, the opposite ones mirror them around the center
+                let center: Point<i32, Logical> = (loc.x + size.w / 2, loc.y + size.h / 2).into();
+                if self.edges.intersects(ResizeEdge::RIGHT) {
+                    new_window_width =
+                        2 * (snap_to_grid(center.x + new_window_width / 2, grid_size) - center.x);
+                } else if self.edges.intersects(ResizeEdge::LEFT) {
+                    new_window_width =
+                        2 * (center.x - snap_to_grid(center.x - new_window_width / 2, grid_size));
+                }
+                if self.edges.intersects(ResizeEdge::BOTTOM) {
+                    new_window_height =
+                        2 * (snap_to_grid(center.y + new_window_height / 2, grid_size) - center.y);
+                } else if self.edges.intersects(ResizeEdge::TOP) {
+                    new_window_height =
+                        2 * (center.y - snap_to_grid(center.y - new_window_height / 2, grid_size));
+                }
+            } else {
+                // snap the dragged edges, the opposite ones stay where they are
+                if self.edges.intersects(ResizeEdge::RIGHT) {
+                    new_window_width = snap_to_grid(loc.x + new_window_width, grid_size) - loc.x;
+                } else if self.edges.intersects(ResizeEdge::LEFT) {
+                    let right = loc.x + size.w;
+                    new_window_width = right - snap_to_grid(right - new_window_width, grid_size);
+                }
+                if self.edges.intersects(ResizeEdge::BOTTOM) {
+                    new_window_height = snap_to_grid(loc.y + new_window_height, grid_size) - loc.y;
+                } else if self.edges.intersects(ResizeEdge::TOP) {
+                    let bottom = loc.y + size.h;
+                    new_window_height =
+                        bottom - snap_to_grid(bottom - new_window_height, grid_size);
+                }
+            }
         }
 
         let (min_size, max_size) = if let Some(surface) = self.window.wl_surface() {
@@ -120,6 +166,19 @@ impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
             new_window_height.max(min_height).min(max_height),
         ));
 
+        if centered != self.centered {
+            self.centered = centered;
+            if let Some(surface) = self.window.wl_surface() {
+                ResizeSurfaceState::with(&surface, |state| {
+                    *state = ResizeSurfaceState::Resizing {
+                        edges: self.edges,
+                        initial_rect: self.initial_rect,
+                        centered,
+                    };
+                });
+            }
+        }
+
         match &self.window {
             WindowElement::Wayland(w) => {
                 let xdg = w.toplevel();
@@ -163,9 +222,7 @@ impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
     ) {
         handle.button(data, event);
 
-        if !handle.current_pressed().contains(&BTN_LEFT)
-            && !handle.current_pressed().contains(&BTN_RIGHT)
-        {
+        if !handle.current_pressed().contains(&self.start_data.button) {
             // No more buttons are pressed, release the grab.
             handle.unset_grab(data, event.serial, event.time, true);
 
@@ -188,6 +245,7 @@ impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
                         *state = ResizeSurfaceState::WaitingForLastCommit {
                             edges: self.edges,
                             initial_rect: self.initial_rect,
+                            centered: self.centered,
                         };
                     });
                 }
@@ -212,6 +270,7 @@ impl<BackendData: Backend + 'static> PointerGrab<Buddaraysh<BackendData>>
                         *state = ResizeSurfaceState::WaitingForLastCommit {
                             edges: self.edges,
                             initial_rect: self.initial_rect,
+                            centered: self.centered,
                         };
                     });
                 }
@@ -325,12 +384,16 @@ pub enum ResizeSurfaceState {
         edges: ResizeEdge,
         /// The initial window size and location.
         initial_rect: Rectangle<i32, Logical>,
+        /// Whether the window is resized from its center.
+        centered: bool,
     },
     /// Resize is done, we are now waiting for last commit, to do the final move
     WaitingForLastCommit {
         edges: ResizeEdge,
         /// The initial window size and location.
         initial_rect: Rectangle<i32, Logical>,
+        /// Whether the window is resized from its center.
+        centered: bool,
     },
 }
 
@@ -347,20 +410,22 @@ impl ResizeSurfaceState {
         })
     }
 
-    fn commit(&mut self) -> Option<(ResizeEdge, Rectangle<i32, Logical>)> {
+    fn commit(&mut self) -> Option<(ResizeEdge, Rectangle<i32, Logical>, bool)> {
         match *self {
             Self::Resizing {
                 edges,
                 initial_rect,
-            } => Some((edges, initial_rect)),
+                centered,
+            } => Some((edges, initial_rect, centered)),
             Self::WaitingForLastCommit {
                 edges,
                 initial_rect,
+                centered,
             } => {
                 // The resize is done, let's go back to idle
                 *self = Self::Idle;
 
-                Some((edges, initial_rect))
+                Some((edges, initial_rect, centered))
             }
             Self::Idle => None,
         }
@@ -380,7 +445,24 @@ pub fn handle_commit(space: &mut Space<WindowElement>, surface: &WlSurface) -> O
     let new_loc: Point<Option<i32>, Logical> = ResizeSurfaceState::with(surface, |state| {
         state
             .commit()
-            .and_then(|(edges, initial_rect)| {
+            .and_then(|(edges, initial_rect, centered)| {
+                // When resizing from the center, the window has to stay centered on the same point.
+                if centered {
+                    let new_x = edges
+                        .intersects(ResizeEdge::LEFT | ResizeEdge::RIGHT)
+                        .then_some(
+                            initial_rect.loc.x + (initial_rect.size.w - geometry.size.w) / 2,
+                        );
+
+                    let new_y = edges
+                        .intersects(ResizeEdge::TOP | ResizeEdge::BOTTOM)
+                        .then_some(
+                            initial_rect.loc.y + (initial_rect.size.h - geometry.size.h) / 2,
+                        );
+
+                    return Some((new_x, new_y).into());
+                }
+
                 // If the window is being resized by top or left, its location must be adjusted
                 // accordingly.
                 edges.intersects(ResizeEdge::TOP_LEFT).then(|| {
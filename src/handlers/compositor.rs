@@ -1,11 +1,11 @@
 use crate::{
-    grabs::resize_grab, shell, state::ClientState, window::WindowElement, Backend, Buddaraysh,
-    CalloopData,
+    grabs::resize_grab, protocols::single_pixel_buffer, shell, state::ClientState,
+    window::WindowElement, workspace::Workspaces, Backend, Buddaraysh, CalloopData,
 };
 use smithay::{
-    backend::renderer::utils::on_commit_buffer_handler,
+    backend::renderer::utils::{on_commit_buffer_handler, with_renderer_surface_state},
     delegate_compositor, delegate_shm,
-    desktop::{layer_map_for_output, PopupKind, PopupManager, Space, WindowSurfaceType},
+    desktop::{layer_map_for_output, PopupKind, PopupManager, WindowSurfaceType},
     reexports::{
         calloop::Interest,
         wayland_server::{
@@ -23,7 +23,7 @@ use smithay::{
         dmabuf::get_dmabuf,
         shell::{
             wlr_layer::LayerSurfaceData,
-            xdg::{XdgPopupSurfaceData, XdgToplevelSurfaceData},
+            xdg::{XdgPopupSurfaceData, XdgShellState, XdgToplevelSurfaceData},
         },
         shm::{ShmHandler, ShmState},
     },
@@ -80,6 +80,7 @@ impl<BackendData: Backend + 'static> CompositorHandler for Buddaraysh<BackendDat
         #[cfg(feature = "xwayland")]
         X11Wm::commit_hook::<CalloopData<BackendData>>(surface);
 
+        single_pixel_buffer::handle_commit(surface);
         on_commit_buffer_handler::<Self>(surface);
         self.backend_data.early_import(surface);
 
@@ -89,22 +90,45 @@ impl<BackendData: Backend + 'static> CompositorHandler for Buddaraysh<BackendDat
                 root = parent;
             }
 
-            if let Some(WindowElement::Wayland(w)) = self.window_for_surface(surface) {
+            if let Some(WindowElement::Wayland(w)) = self.workspaces.window_for_surface(surface) {
                 w.on_commit();
+
+                if !w.toplevel().is_initial_configure_sent() {
+                    self.initial_configure_toplevel(w.toplevel());
+                } else if root == *surface && !has_buffer(surface) {
+                    // a null buffer unmaps the toplevel without destroying it
+                    self.unmap_toplevel(&WindowElement::Wayland(w));
+                }
+            } else {
+                self.commit_unmapped(surface, has_buffer(surface));
             }
         };
 
+        // the layers get arranged again below, which may move a bar's exclusive zone
+        let layer_zone = self
+            .workspaces
+            .outputs()
+            .find(|o| {
+                layer_map_for_output(o)
+                    .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                    .is_some()
+            })
+            .map(|o| (o.clone(), layer_map_for_output(o).non_exclusive_zone()));
+
         ensure_initial_configure(
             surface,
-            self.workspaces.current_workspace().space(),
+            &self.workspaces,
+            &self.xdg_shell_state,
             &mut self.popups,
         );
 
-        shell::xdg::handle_commit(
-            &mut self.popups,
-            self.workspaces.current_workspace().space(),
-            surface,
-        );
+        if let Some((output, zone)) = layer_zone {
+            if layer_map_for_output(&output).non_exclusive_zone() != zone {
+                self.remaximize_windows(&output);
+            }
+        }
+
+        shell::xdg::handle_commit(&mut self.popups, &self.workspaces, surface);
 
         resize_grab::handle_commit(self.workspaces.current_workspace_mut().space_mut(), surface);
     }
@@ -129,9 +153,15 @@ delegate_shm!(@<BackendData: Backend + 'static> Buddaraysh<BackendData>);
 //     pub resize_state: ResizeState,
 // }
 
+fn has_buffer(surface: &WlSurface) -> bool {
+    with_renderer_surface_state(surface, |state| state.buffer().is_some()).unwrap_or(false)
+        || single_pixel_buffer::has_single_pixel_buffer(surface)
+}
+
 fn ensure_initial_configure(
     surface: &WlSurface,
-    space: &Space<WindowElement>,
+    workspaces: &Workspaces,
+    xdg_shell_state: &XdgShellState,
     popups: &mut PopupManager,
 ) {
     // TODO:
@@ -147,11 +177,8 @@ fn ensure_initial_configure(
     //     |_, _, _| true,
     // );
 
-    if let Some(window) = space
-        .elements()
-        .find(|window| window.wl_surface().map(|s| s == *surface).unwrap_or(false))
-        .cloned()
-    {
+    // the window might've been placed in a workspace that isn't the current one anymore
+    if let Some(window) = workspaces.window_for_surface(surface) {
         // send the initial configure if relevant
         #[cfg_attr(not(feature = "xwayland"), allow(irrefutable_let_patterns))]
         if let WindowElement::Wayland(ref toplevel) = window {
@@ -185,6 +212,29 @@ fn ensure_initial_configure(
         return;
     }
 
+    // toplevels that haven't been placed in any workspace yet still need their initial configure,
+    // otherwise clients that wait for it before drawing would never show up
+    if let Some(toplevel) = xdg_shell_state
+        .toplevel_surfaces()
+        .iter()
+        .find(|toplevel| toplevel.wl_surface() == surface)
+    {
+        let initial_configure_sent = with_states(surface, |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .initial_configure_sent
+        });
+        if !initial_configure_sent {
+            toplevel.send_configure();
+        }
+
+        return;
+    }
+
     if let Some(popup) = popups.find_popup(surface) {
         let popup = match popup {
             PopupKind::Xdg(ref popup) => popup,
@@ -212,7 +262,7 @@ fn ensure_initial_configure(
         return;
     }
 
-    if let Some(output) = space.outputs().find(|o| {
+    if let Some(output) = workspaces.outputs().find(|o| {
         let map = layer_map_for_output(o);
         map.layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
             .is_some()
@@ -26,10 +26,15 @@ impl<BackendData: Backend + 'static> WlrLayerShellHandler for Buddaraysh<Backend
         layer: WlrLayer,
         namespace: String,
     ) {
-        let output = wl_output
+        let Some(output) = wl_output
             .as_ref()
             .and_then(Output::from_resource)
-            .unwrap_or_else(|| self.workspaces.outputs().next().unwrap().clone());
+            .or_else(|| self.workspaces.outputs().next().cloned())
+        else {
+            // there is no output to put the layer surface on
+            surface.send_close();
+            return;
+        };
         let layer_surface = smithay::desktop::LayerSurface::new(surface, namespace);
 
         {
@@ -38,7 +43,7 @@ impl<BackendData: Backend + 'static> WlrLayerShellHandler for Buddaraysh<Backend
         }
 
         match layer {
-            WlrLayer::Top | WlrLayer::Overlay => {
+            WlrLayer::Top | WlrLayer::Overlay if !self.focus_lock => {
                 if let Some(keyboard) = self.seat.get_keyboard() {
                     keyboard.set_focus(
                         self,
@@ -52,15 +57,19 @@ impl<BackendData: Backend + 'static> WlrLayerShellHandler for Buddaraysh<Backend
     }
 
     fn layer_destroyed(&mut self, surface: LayerSurface) {
-        if let Some((mut map, layer)) = self.workspaces.outputs().find_map(|o| {
-            let map = layer_map_for_output(o);
+        let output = self.workspaces.outputs().find_map(|o| {
+            let mut map = layer_map_for_output(o);
             let layer = map
                 .layers()
                 .find(|&layer| layer.layer_surface() == &surface)
-                .cloned();
-            layer.map(|layer| (map, layer))
-        }) {
+                .cloned()?;
             map.unmap_layer(&layer);
+            Some(o.clone())
+        });
+
+        // the bar's exclusive zone is free again
+        if let Some(output) = output {
+            self.remaximize_windows(&output);
         }
     }
 
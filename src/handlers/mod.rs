@@ -6,6 +6,7 @@ mod pointer_contraints;
 use crate::focus::FocusTarget;
 use crate::{Backend, Buddaraysh};
 
+use smithay::input::pointer::{CursorIcon, CursorImageStatus};
 use smithay::input::{Seat, SeatHandler, SeatState};
 use smithay::reexports::wayland_server::protocol::wl_data_source::WlDataSource;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
@@ -26,11 +27,14 @@ impl<BackendData: Backend + 'static> SeatHandler for Buddaraysh<BackendData> {
         &mut self.seat_state
     }
 
-    fn cursor_image(
-        &mut self,
-        _seat: &Seat<Self>,
-        image: smithay::input::pointer::CursorImageStatus,
-    ) {
+    fn cursor_image(&mut self, _seat: &Seat<Self>, image: CursorImageStatus) {
+        // keep showing the busy cursor while an app is starting up
+        let image = match image {
+            CursorImageStatus::Named(CursorIcon::Default) if self.startup_feedback.is_some() => {
+                CursorImageStatus::Named(CursorIcon::Progress)
+            }
+            image => image,
+        };
         *self.cursor_status.lock().unwrap() = image;
     }
 
@@ -42,6 +46,8 @@ impl<BackendData: Backend + 'static> SeatHandler for Buddaraysh<BackendData> {
         let client = wl_surface.and_then(|s| dh.get_client(s.id()).ok());
         set_data_device_focus(dh, seat, client.clone());
         set_primary_focus(dh, seat, client);
+
+        self.update_focus_dim(focused);
     }
 }
 
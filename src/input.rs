@@ -1,13 +1,13 @@
-use std::{process::Stdio, sync::atomic::Ordering, time::Instant};
+use std::{path::PathBuf, process::Stdio, sync::atomic::Ordering, time::Instant};
 
 use smithay::{
     backend::{
         input::{
-            AbsolutePositionEvent, Axis, AxisSource, ButtonState, Event, GestureBeginEvent as _,
-            GestureEndEvent, GesturePinchUpdateEvent as _, GestureSwipeUpdateEvent as _,
-            InputBackend, InputEvent, KeyState, KeyboardKeyEvent, PointerAxisEvent,
-            PointerButtonEvent, PointerMotionEvent, ProximityState, TabletToolButtonEvent,
-            TabletToolEvent, TabletToolProximityEvent, TouchEvent,
+            AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
+            Event, GestureBeginEvent as _, GestureEndEvent, GesturePinchUpdateEvent as _,
+            GestureSwipeUpdateEvent as _, InputBackend, InputEvent, KeyState, KeyboardKeyEvent,
+            PointerAxisEvent, PointerButtonEvent, PointerMotionEvent, ProximityState,
+            TabletToolButtonEvent, TabletToolEvent, TabletToolProximityEvent, TouchEvent,
         },
         libinput::LibinputInputBackend,
         session::Session,
@@ -21,12 +21,13 @@ use smithay::{
             GestureSwipeUpdateEvent, GrabStartData, MotionEvent, RelativeMotionEvent,
         },
     },
+    output::Output,
     reexports::{
         input::{event::tablet_tool::TipState, DeviceCapability, Led},
         wayland_protocols::xdg::shell::server::xdg_toplevel::ResizeEdge,
         wayland_server::DisplayHandle,
     },
-    utils::{Logical, Point, Serial, SERIAL_COUNTER},
+    utils::{Logical, Point, Rectangle, Serial, Size, SERIAL_COUNTER},
     wayland::{
         input_method::InputMethodSeat,
         keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitorSeat,
@@ -37,27 +38,107 @@ use smithay::{
     },
     xwayland::{xwm::ResizeEdge as X11ResizeEdge, XwmHandler},
 };
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::EnvFilter;
 
 use crate::{
-    focus::FocusTarget, shell::FullscreenSurface, state::Buddaraysh, udev::UdevData,
-    window::WindowElement, winit::WinitData, Action, Backend, BTN_LEFT, BTN_RIGHT,
+    config::{Config, KeyboardConfig},
+    focus::FocusTarget,
+    ipc::Event as IpcEvent,
+    log_filter,
+    shell::FullscreenSurface,
+    state::{Buddaraysh, PendingSpawn, TouchPoint},
+    udev::UdevData,
+    window::WindowElement,
+    winit::WinitData,
+    Action, Backend, Direction,
 };
 
 impl<BackendData: Backend> Buddaraysh<BackendData> {
+    /// A popup menu, an input method or Xwayland grabbed the keyboard, keys go to it instead
+    /// of triggering keybinds. Not callable from a `KeyboardHandle::input` filter, that runs
+    /// with the keyboard locked
+    fn client_keyboard_grab(&self) -> bool {
+        let keyboard = self.seat.get_keyboard().unwrap();
+        keyboard.is_grabbed() || self.seat.input_method().keyboard_grabbed()
+    }
+
+    /// Runs the config's action for clicking `button` on the desktop, returns whether there
+    /// was one, a wallpaper counts as desktop
+    fn desktop_click(&mut self, button: u32) -> bool {
+        let location = self.seat.get_pointer().unwrap().current_location();
+        let on_desktop = match self.surface_under(location) {
+            None => true,
+            Some((FocusTarget::LayerSurface(layer), _)) => layer.layer() == WlrLayer::Background,
+            Some(_) => false,
+        };
+        if !on_desktop {
+            return false;
+        }
+
+        let Some(action) = self.config.desktop_action_for(button) else {
+            return false;
+        };
+        self.process_common_actions(action);
+        true
+    }
+
     fn input_to_action(
         &mut self,
         modifiers: &ModifiersState,
         _keysym: Keysym,
         raw_syms: &[Keysym],
         state: KeyState,
+        client_grab: bool,
     ) -> Option<Action> {
-        if state == KeyState::Pressed && !self.seat.keyboard_shortcuts_inhibited() {
-            if let Some(value) = move_to_workspace(modifiers, raw_syms) {
+        // the only binding left in pass-through mode, so there's always a way back
+        if state == KeyState::Pressed
+            && modifiers.logo
+            && !modifiers.alt
+            && modifiers.shift
+            && modifiers.ctrl
+            && raw_syms.contains(&Keysym::Escape)
+        {
+            return Some(Action::TogglePassthrough);
+        }
+
+        if self.passthrough {
+            return None;
+        }
+
+        if state == KeyState::Pressed && !self.seat.keyboard_shortcuts_inhibited() && !client_grab {
+            if let Some(action) = self.config.action_for(modifiers, raw_syms) {
+                return Some(action);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && modifiers.ctrl
+                && raw_syms.contains(&Keysym::r)
+            {
+                return Some(Action::ReloadConfig);
+            }
+
+            let workspace_amount = self.workspaces.workspaces().len();
+
+            if let Some(value) = move_to_workspace(modifiers, raw_syms, workspace_amount) {
+                return value;
+            }
+
+            if let Some(value) = merge_workspace(modifiers, raw_syms, workspace_amount) {
+                return value;
+            }
+
+            if let Some(value) = switch_workspace(modifiers, raw_syms, workspace_amount) {
+                return value;
+            }
+
+            if let Some(value) = focus_direction(modifiers, raw_syms) {
                 return value;
             }
 
-            if let Some(value) = switch_workspace(modifiers, raw_syms) {
+            if let Some(value) = move_window_direction(modifiers, raw_syms) {
                 return value;
             }
 
@@ -76,7 +157,16 @@ impl<BackendData: Backend> Buddaraysh<BackendData> {
                 && !modifiers.ctrl
                 && raw_syms.contains(&Keysym::q)
             {
-                return Some(Action::Spawn(String::from("kitty")));
+                return Some(Action::Spawn(self.terminal.clone()));
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::q)
+            {
+                return Some(Action::SpawnTerminalHere);
             }
 
             if modifiers.logo
@@ -121,42 +211,465 @@ impl<BackendData: Backend> Buddaraysh<BackendData> {
                 )));
             }
 
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::d)
+            {
+                return Some(Action::ToggleFocusDim);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::r)
+            {
+                return Some(Action::ResetSize);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && !modifiers.shift
+                && modifiers.ctrl
+                && raw_syms.contains(&Keysym::r)
+            {
+                return Some(Action::ResetWindowState);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && modifiers.ctrl
+                && raw_syms.contains(&Keysym::l)
+            {
+                return Some(Action::ToggleDebugLogging);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && modifiers.ctrl
+                && raw_syms.contains(&Keysym::d)
+            {
+                return Some(Action::DumpFrame);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && !modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::o)
+            {
+                return Some(Action::FocusLastOnOtherOutput);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::o)
+            {
+                return Some(Action::SwapOutputs);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::l)
+            {
+                return Some(Action::ToggleFocusLock);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && !modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::Tab)
+            {
+                return Some(Action::FocusLayerOrWindow);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && !modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::f)
+            {
+                return Some(Action::ToggleFullscreen);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && !modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::w)
+            {
+                return Some(Action::ToggleWindowPreview);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::s)
+            {
+                return Some(Action::ToggleStickyAllOutputs);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::c)
+            {
+                return Some(Action::ToggleClock);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && !modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::space)
+            {
+                return Some(Action::NextInputSource);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::f)
+            {
+                return Some(Action::ToggleZen);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && !modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::g)
+            {
+                return Some(Action::ToggleGridOverlay);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && !modifiers.shift
+                && modifiers.ctrl
+                && raw_syms.contains(&Keysym::s)
+            {
+                return Some(Action::ToggleSpotlight);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && !modifiers.shift
+                && modifiers.ctrl
+                && raw_syms.contains(&Keysym::p)
+            {
+                return Some(Action::ToggleSingleOutputPointer);
+            }
+
+            if modifiers.logo
+                && !modifiers.alt
+                && modifiers.shift
+                && !modifiers.ctrl
+                && raw_syms.contains(&Keysym::z)
+            {
+                return Some(Action::NotifyLastClosed);
+            }
+
             if raw_syms.contains(&Keysym::Print) {
-                return Some(Action::Spawn(String::from(
-                    "grimblast --freeze copysave area ~/Pictures/$(date +%Y-%m-%d_%H-%m-%s).png",
-                )));
+                if let Some(command) = self.print_commands.command_for(modifiers) {
+                    return Some(Action::Spawn(command.to_owned()));
+                }
             }
         }
 
         None
     }
-    fn process_common_actions(&mut self, action: Action) {
+    /// Swaps the keymap of the existing keyboard and sends it to clients, the keyboard focus
+    /// and the active layout stay as they were
+    pub fn set_xkb_config(&mut self, keyboard_config: &KeyboardConfig) {
+        let keyboard = self.seat.get_keyboard().unwrap();
+        let layout = keyboard.with_xkb_state(self, |context| context.layout());
+
+        if let Err(err) = keyboard.set_xkb_config(self, keyboard_config.xkb_config()) {
+            error!(?err, "Failed to apply the keyboard layout");
+            return;
+        }
+
+        keyboard.with_xkb_state(self, |mut context| {
+            let layouts = context.xkb().lock().unwrap().layouts().count();
+            if (layout.0 as usize) < layouts {
+                context.set_layout(layout);
+            }
+        });
+        info!(layout = keyboard_config.layout, "Applied keyboard layout");
+    }
+
+    /// Runs `program` through `sh -c`, returning the pid of the shell
+    fn spawn(&mut self, program: String) -> Option<u32> {
+        self.spawn_in(program, None)
+    }
+
+    /// Spawns `program` with `cwd` as its working directory, or the compositor's if it's None
+    fn spawn_in(&mut self, program: String, cwd: Option<PathBuf>) -> Option<u32> {
+        let mut command = std::process::Command::new("sh");
+        if let Some(cwd) = cwd {
+            command.current_dir(cwd);
+        }
+
+        match command
+            .arg("-c")
+            .arg(program)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .envs(
+                [self.socket_name.clone()]
+                    .into_iter()
+                    .map(|v| ("WAYLAND_DISPLAY", v.to_string_lossy().to_string()))
+                    .chain(
+                        #[cfg(feature = "xwayland")]
+                        self.xdisplay.map(|v| ("DISPLAY", format!(":{}", v))),
+                        #[cfg(not(feature = "xwayland"))]
+                        None,
+                    ),
+            )
+            .spawn()
+        {
+            Ok(child) => {
+                // TODO: keep track of child processes
+                self.start_startup_feedback();
+                Some(child.id())
+            }
+            Err(e) => {
+                error!("Failed to run command: {e}");
+                None
+            }
+        }
+        // self.children.insert(child);
+    }
+
+    /// Output a touch screen is mapped to, its entry in `BUD_TOUCH_OUTPUT` or the first output
+    fn touch_output(&self, device_name: &str) -> Option<Output> {
+        self.touch_outputs
+            .output_for(device_name)
+            .and_then(|name| self.workspaces.outputs().find(|o| o.name() == name))
+            .or_else(|| self.workspaces.outputs().next())
+            .cloned()
+    }
+
+    /// Position of a touch point in the global space, the output's logical geometry already
+    /// accounts for its scale
+    fn touch_position<I: InputBackend, E: AbsolutePositionEvent<I>>(
+        &self,
+        event: &E,
+        output: &Output,
+    ) -> Option<Point<f64, Logical>> {
+        let output_geometry = self
+            .workspaces
+            .current_workspace()
+            .output_geometry(output)?;
+        Some(event.position_transformed(output_geometry.size) + output_geometry.loc.to_f64())
+    }
+
+    fn on_touch_down<I: InputBackend>(&mut self, event: I::TouchDownEvent) {
+        let Some(touch) = self.seat.get_touch() else {
+            return;
+        };
+        let Some(output) = self.touch_output(&event.device().name()) else {
+            return;
+        };
+        let Some(position) = self.touch_position::<I, _>(&event, &output) else {
+            return;
+        };
+
+        if let Some((wl_surface, surface_pos)) = self
+            .surface_under(position)
+            .and_then(|(surface, loc)| Some((surface.wl_surface()?, loc)))
+        {
+            touch.down(
+                SERIAL_COUNTER.next_serial(),
+                event.time_msec(),
+                &wl_surface,
+                position - surface_pos.to_f64(),
+                event.slot(),
+            );
+            self.touch_slots.insert(
+                event.slot(),
+                TouchPoint {
+                    output,
+                    surface_location: surface_pos.to_f64(),
+                },
+            );
+        }
+    }
+
+    fn on_touch_up<I: InputBackend>(&mut self, event: I::TouchUpEvent) {
+        if let Some(touch) = self.seat.get_touch() {
+            touch.up(
+                SERIAL_COUNTER.next_serial(),
+                event.time_msec(),
+                event.slot(),
+            );
+        }
+        self.touch_slots.remove(&event.slot());
+    }
+
+    fn on_touch_motion<I: InputBackend>(&mut self, event: I::TouchMotionEvent) {
+        let Some(touch) = self.seat.get_touch() else {
+            return;
+        };
+        let Some(point) = self.touch_slots.get(&event.slot()) else {
+            return;
+        };
+
+        // the point stays with the surface it went down on, even when it's dragged off of it or
+        // onto another output, both locations are global so they stay comparable
+        if let Some(position) = self.touch_position::<I, _>(&event, &point.output) {
+            touch.motion(
+                event.time_msec(),
+                event.slot(),
+                position - point.surface_location,
+            );
+        }
+    }
+
+    fn on_touch_cancel(&mut self) {
+        if let Some(touch) = self.seat.get_touch() {
+            touch.cancel();
+        }
+        self.touch_slots.clear();
+    }
+
+    /// Focus follows mouse, gives the keyboard focus to the window under the pointer once that
+    /// window changes. Unlike a click it doesn't raise the window, and empty desktop keeps the
+    /// focus where it is.
+    fn focus_hovered_window(&mut self, serial: Serial) {
+        if !self.config.focus_follows_mouse || self.focus_lock {
+            return;
+        }
+        let keyboard = self.seat.get_keyboard().unwrap();
+        if self.pointer.is_grabbed()
+            || (keyboard.is_grabbed() && !self.seat.input_method().keyboard_grabbed())
+        {
+            return;
+        }
+
+        let location = self.pointer.current_location();
+        // bars and other surfaces above the windows don't pass the focus to what's below them
+        if let Some(output) = self.workspaces.output_under(location).next() {
+            let layers = layer_map_for_output(output);
+            if layers
+                .layer_under(WlrLayer::Overlay, location)
+                .or_else(|| layers.layer_under(WlrLayer::Top, location))
+                .is_some()
+            {
+                return;
+            }
+        }
+
+        let window = self
+            .workspaces
+            .current_workspace()
+            .window_under(location)
+            .map(|(window, _)| window.clone());
+        if window == self.hovered_window {
+            return;
+        }
+        self.hovered_window = window.clone();
+
+        if let Some(window) = window {
+            keyboard.set_focus(self, Some(window.into()), serial);
+        }
+    }
+
+    fn on_pointer_axis<I: InputBackend>(&mut self, event: I::PointerAxisEvent) {
+        let factor = self.scroll_config.factor;
+        let natural = self.scroll_config.is_natural(&event.device().name());
+        let direction = |axis| {
+            let direction = event.relative_direction(axis);
+            match (natural, direction) {
+                (true, AxisRelativeDirection::Identical) => AxisRelativeDirection::Inverted,
+                (true, AxisRelativeDirection::Inverted) => AxisRelativeDirection::Identical,
+                (false, direction) => direction,
+            }
+        };
+        let sign = if natural { -1.0 } else { 1.0 };
+
+        let horizontal_amount = event
+            .amount(Axis::Horizontal)
+            .unwrap_or_else(|| event.amount_v120(Axis::Horizontal).unwrap_or(0.0) * 3.0 / 120.)
+            * factor
+            * sign;
+        let vertical_amount = event
+            .amount(Axis::Vertical)
+            .unwrap_or_else(|| event.amount_v120(Axis::Vertical).unwrap_or(0.0) * 3.0 / 120.)
+            * factor
+            * sign;
+        let horizontal_amount_discrete = event
+            .amount_v120(Axis::Horizontal)
+            .map(|v| v * factor * sign);
+        let vertical_amount_discrete = event.amount_v120(Axis::Vertical).map(|v| v * factor * sign);
+
+        let mut frame = AxisFrame::new(event.time_msec()).source(event.source());
+        if horizontal_amount != 0.0 {
+            frame = frame.relative_direction(Axis::Horizontal, direction(Axis::Horizontal));
+            frame = frame.value(Axis::Horizontal, horizontal_amount);
+            if let Some(discrete) = horizontal_amount_discrete {
+                frame = frame.v120(Axis::Horizontal, discrete as i32);
+            }
+        }
+        if vertical_amount != 0.0 {
+            frame = frame.relative_direction(Axis::Vertical, direction(Axis::Vertical));
+            frame = frame.value(Axis::Vertical, vertical_amount);
+            if let Some(discrete) = vertical_amount_discrete {
+                frame = frame.v120(Axis::Vertical, discrete as i32);
+            }
+        }
+        if event.source() == AxisSource::Finger {
+            if event.amount(Axis::Horizontal) == Some(0.0) {
+                frame = frame.stop(Axis::Horizontal);
+            }
+            if event.amount(Axis::Vertical) == Some(0.0) {
+                frame = frame.stop(Axis::Vertical);
+            }
+        }
+        let pointer = self.pointer.clone();
+        pointer.axis(self, frame);
+        pointer.frame(self);
+    }
+
+    pub fn process_common_actions(&mut self, action: Action) {
         match action {
             Action::Spawn(program) => {
-                match std::process::Command::new("sh")
-                    .arg("-c")
-                    .arg(program)
-                    .stdin(Stdio::null())
-                    .stdout(Stdio::null())
-                    .envs(
-                        [self.socket_name.clone()]
-                            .into_iter()
-                            .map(|v| ("WAYLAND_DISPLAY", v.to_string_lossy().to_string()))
-                            .chain(
-                                #[cfg(feature = "xwayland")]
-                                self.xdisplay.map(|v| ("DISPLAY", format!(":{}", v))),
-                                #[cfg(not(feature = "xwayland"))]
-                                None,
-                            ),
-                    )
-                    .spawn()
-                {
-                    Ok(_child) => {
-                        // TODO: keep track of child processes
-                    }
-                    Err(e) => error!("Failed to run command: {e}"),
+                self.spawn(program);
+            }
+            Action::SpawnOnWorkspace { cmd, workspace } => {
+                if self.workspaces.get(workspace).is_none() {
+                    error!("workspace index does not exist");
+                    return;
+                }
+
+                if let Some(pid) = self.spawn(cmd) {
+                    self.pending_spawns.push(PendingSpawn::new(pid, workspace));
                 }
-                // self.children.insert(child);
+            }
+            Action::SpawnTerminalHere => {
+                let keyboard = self.seat.get_keyboard().unwrap();
+                let cwd = keyboard
+                    .current_focus()
+                    .and_then(|focused| focused.wl_surface())
+                    .and_then(|surface| self.window_for_surface(&surface))
+                    .and_then(|window| self.window_pid(&window))
+                    .and_then(|pid| std::fs::read_link(format!("/proc/{pid}/cwd")).ok())
+                    .or_else(|| std::env::var_os("HOME").map(PathBuf::from));
+
+                self.spawn_in(self.terminal.clone(), cwd);
             }
             Action::Quit => {
                 info!("Quitting.");
@@ -185,202 +698,780 @@ impl<BackendData: Backend> Buddaraysh<BackendData> {
                             }
                         }
 
-                        window.send_close();
+                        window.send_close();
+                    }
+                }
+            }
+            Action::SwitchToWorkspace(workspace_index) => {
+                if self
+                    .workspaces
+                    .set_current_workspace(workspace_index)
+                    .is_none()
+                {
+                    error!("workspace index does not exist");
+                    return;
+                }
+                self.update_suspended_windows();
+                let output = self
+                    .workspaces
+                    .output_under(self.pointer.current_location())
+                    .next()
+                    .map(|output| output.name());
+                self.send_ipc_event(IpcEvent::Workspace {
+                    output,
+                    workspace: workspace_index + 1,
+                });
+                let pointer = self.pointer.clone();
+                let now = Instant::now();
+                let time = now.duration_since(self.start_time).as_millis() as u32;
+                // this is to fix the button press being sent to the fullscreen
+                // surface when switching to another workspace
+                //
+                // but doesn't fix when switching back to the workspace with the
+                // fullscreen surface
+                pointer.motion(
+                    self,
+                    None,
+                    &MotionEvent {
+                        location: pointer.current_location(),
+                        serial: SERIAL_COUNTER.next_serial(),
+                        time,
+                    },
+                );
+                pointer.frame(self);
+
+                if !self.focus_lock {
+                    let target = self.surface_under(pointer.current_location());
+                    let keyboard = self.seat.get_keyboard().unwrap();
+                    let serial = SERIAL_COUNTER.next_serial();
+                    keyboard.set_focus(self, target.map(|(f, _)| f), serial);
+                }
+            }
+            Action::SwitchToNextWorkspace | Action::SwitchToPreviousWorkspace => {
+                let count = self.workspaces.workspaces().len();
+                let current = self.workspaces.current_workspace_index();
+                let workspace_index = if matches!(action, Action::SwitchToNextWorkspace) {
+                    (current + 1) % count
+                } else {
+                    (current + count - 1) % count
+                };
+                self.process_common_actions(Action::SwitchToWorkspace(workspace_index));
+            }
+            Action::MoveToWorkspace(workspace_index) => {
+                if self.workspaces.current_workspace_index() == workspace_index {
+                    return;
+                }
+
+                let keyboard = self.seat.get_keyboard().unwrap();
+                let pointer = self.pointer.clone();
+
+                // prevent window from duplicating (in the current workspace and the destination workspace)
+                // when it's grabbed
+                if pointer.is_grabbed() {
+                    let time = Instant::now().duration_since(self.start_time);
+                    pointer.unset_grab(self, SERIAL_COUNTER.next_serial(), time.as_millis() as u32);
+                }
+
+                if let Some(window) = keyboard
+                    .current_focus()
+                    .and_then(|t| t.wl_surface())
+                    .and_then(|s| self.window_for_surface(&s))
+                {
+                    let location = self
+                        .workspaces
+                        .current_workspace()
+                        .window_location(&window)
+                        .unwrap();
+                    let transients = self.workspaces.current_workspace().transients_of(&window);
+
+                    self.workspaces
+                        .current_workspace_mut()
+                        .unmap_window(&window);
+
+                    if let Some(workspace) = self.workspaces.get_mut(workspace_index) {
+                        workspace.map_window(window.clone(), location, true);
+                        let current_workspace_index = self.workspaces.current_workspace_index();
+                        self.move_transients(
+                            &transients,
+                            current_workspace_index,
+                            workspace_index,
+                            (0, 0).into(),
+                        );
+
+                        // a fullscreen window stays fullscreen on its new workspace
+                        for output in self.workspaces.outputs() {
+                            if let Some(fullscreen) = output.user_data().get::<FullscreenSurface>()
+                            {
+                                fullscreen.window_moved(&window, workspace_index);
+                            }
+                        }
+                    } else {
+                        self.workspaces
+                            .current_workspace_mut()
+                            .map_window(window, location, false);
+                        error!("invalid workspace index");
+                    }
+                    self.update_suspended_windows();
+                }
+            }
+            Action::MergeWorkspaceInto(workspace_index) => {
+                let current_workspace_index = self.workspaces.current_workspace_index();
+                if current_workspace_index == workspace_index {
+                    return;
+                }
+
+                let pointer = self.pointer.clone();
+
+                // the grabbed window would be left in the removed workspace
+                if pointer.is_grabbed() {
+                    let time = Instant::now().duration_since(self.start_time);
+                    pointer.unset_grab(self, SERIAL_COUNTER.next_serial(), time.as_millis() as u32);
+                }
+
+                let Some(new_index) = self.workspaces.merge_current_into(workspace_index) else {
+                    error!("invalid workspace index");
+                    return;
+                };
+
+                // workspaces after the removed one got shifted down by one
+                for output in self.workspaces.outputs() {
+                    if let Some(fullscreen) = output.user_data().get::<FullscreenSurface>() {
+                        if let (Some(window), Some(index)) = fullscreen.get() {
+                            let index = match index {
+                                i if i == current_workspace_index => new_index,
+                                i if i > current_workspace_index => i - 1,
+                                i => i,
+                            };
+                            fullscreen.set(window, index);
+                        }
+                    }
+                }
+
+                let now = Instant::now();
+                let time = now.duration_since(self.start_time).as_millis() as u32;
+                pointer.motion(
+                    self,
+                    None,
+                    &MotionEvent {
+                        location: pointer.current_location(),
+                        serial: SERIAL_COUNTER.next_serial(),
+                        time,
+                    },
+                );
+                pointer.frame(self);
+
+                if !self.focus_lock {
+                    let target = self.surface_under(pointer.current_location());
+                    let keyboard = self.seat.get_keyboard().unwrap();
+                    let serial = SERIAL_COUNTER.next_serial();
+                    keyboard.set_focus(self, target.map(|(f, _)| f), serial);
+                }
+            }
+            Action::ToggleOutputEnabled(_) | Action::DumpFrame => {
+                if let Some(action) = BackendData::process_backend_action(self, action) {
+                    warn!(?action, "Not supported by this backend");
+                }
+            }
+            Action::FocusLayerOrWindow => {
+                if self.focus_lock {
+                    return;
+                }
+
+                let keyboard = self.seat.get_keyboard().unwrap();
+                let layer_focused =
+                    matches!(keyboard.current_focus(), Some(FocusTarget::LayerSurface(_)));
+
+                let target = if layer_focused {
+                    let window = self
+                        .workspaces
+                        .current_workspace()
+                        .windows()
+                        .next_back()
+                        .cloned();
+                    if let Some(window) = window.as_ref() {
+                        self.workspaces
+                            .current_workspace_mut()
+                            .raise_window(window, true);
+                        #[cfg(feature = "xwayland")]
+                        if let WindowElement::X11(surf) = window {
+                            self.xwm.as_mut().unwrap().raise_window(surf).unwrap();
+                        }
+                    }
+                    window.map(FocusTarget::from)
+                } else {
+                    let output = self
+                        .workspaces
+                        .output_under(self.pointer.current_location())
+                        .next()
+                        .or_else(|| self.workspaces.outputs().next())
+                        .cloned();
+                    output.and_then(|output| {
+                        let layers = layer_map_for_output(&output);
+                        let layer = layers
+                            .layers_on(WlrLayer::Overlay)
+                            .rev()
+                            .chain(layers.layers_on(WlrLayer::Top).rev())
+                            .find(|layer| layer.can_receive_keyboard_focus())
+                            .cloned();
+                        layer.map(FocusTarget::from)
+                    })
+                };
+
+                if let Some(target) = target {
+                    keyboard.set_focus(self, Some(target), SERIAL_COUNTER.next_serial());
+                }
+            }
+            Action::EvacuateOutput(target) => self.move_windows_to_output(&target),
+            Action::SwapOutputs => self.swap_outputs(),
+            Action::SetOutputScale { output, scale } => self.set_output_scale(&output, scale),
+            Action::SaveLayoutPreset(name) => self.save_layout_preset(&name),
+            Action::LoadLayoutPreset(name) => self.load_layout_preset(&name),
+            Action::ToggleFullscreen => {
+                let keyboard = self.seat.get_keyboard().unwrap();
+                let Some(window) = keyboard
+                    .current_focus()
+                    .and_then(|focused| focused.wl_surface())
+                    .and_then(|surface| self.window_for_surface(&surface))
+                else {
+                    return;
+                };
+
+                let is_fullscreen = self.workspaces.outputs().any(|o| {
+                    o.user_data()
+                        .get::<FullscreenSurface>()
+                        .and_then(|f| f.get().0)
+                        .map(|w| w == window)
+                        .unwrap_or(false)
+                });
+
+                match window {
+                    WindowElement::Wayland(ref w) => {
+                        let toplevel = w.toplevel().clone();
+                        if is_fullscreen {
+                            XdgShellHandler::unfullscreen_request(self, toplevel);
+                        } else {
+                            XdgShellHandler::fullscreen_request(self, toplevel, None);
+                        }
+                    }
+                    #[cfg(feature = "xwayland")]
+                    WindowElement::X11(ref w) => {
+                        let w = w.clone();
+                        self.loop_handle.insert_idle(move |data| {
+                            let xwm = data.state.xwm.as_ref().unwrap().id();
+                            if is_fullscreen {
+                                XwmHandler::unfullscreen_request(data, xwm, w);
+                            } else {
+                                XwmHandler::fullscreen_request(data, xwm, w);
+                            }
+                        });
+                    }
+                }
+            }
+            Action::ToggleFocusLock => {
+                self.focus_lock = !self.focus_lock;
+                info!(focus_lock = self.focus_lock, "Toggled focus lock");
+            }
+            Action::ReloadConfig => {
+                let config = Config::load();
+                if config.keyboard != self.config.keyboard {
+                    self.set_xkb_config(&config.keyboard);
+                }
+                self.config = config;
+            }
+            Action::TogglePassthrough => {
+                self.passthrough = !self.passthrough;
+                info!(
+                    passthrough = self.passthrough,
+                    "Toggled keybinding pass-through"
+                );
+            }
+            Action::ToggleFocusDim => {
+                self.focus_dim = !self.focus_dim;
+                self.refresh_focus_dim();
+            }
+            Action::NotifyLastClosed => {
+                let Some(closed) = self.recently_closed.back() else {
+                    info!("No window was closed yet");
+                    return;
+                };
+
+                let app_id = closed.app_id.as_deref().unwrap_or("unknown app");
+                let title = closed.title.as_deref().unwrap_or("");
+                let workspace = closed
+                    .workspace_index
+                    .map(|index| format!("workspace {}", index + 1))
+                    .unwrap_or_else(|| String::from("no workspace"));
+                let body = format!(
+                    "{title}\n{workspace}, {}s ago",
+                    closed.closed_at.elapsed().as_secs()
+                );
+                info!(app_id, %body, "Last closed window");
+                notify(&format!("Closed {app_id}"), &body);
+            }
+            Action::NextInputSource => {
+                let keyboard = self.seat.get_keyboard().unwrap();
+                let name = keyboard.with_xkb_state(self, |mut context| {
+                    context.cycle_next_layout();
+                    let layout = context.layout();
+                    context.xkb().lock().unwrap().layout_name(layout).to_owned()
+                });
+                info!(layout = name, "Switched keyboard layout");
+                notify("Keyboard layout", &name);
+            }
+            Action::ToggleGridOverlay => {
+                self.grid_overlay = !self.grid_overlay;
+            }
+            Action::ToggleSingleOutputPointer => {
+                self.pointer_output_lock = match self.pointer_output_lock {
+                    Some(_) => None,
+                    None => self
+                        .workspaces
+                        .output_under(self.pointer.current_location())
+                        .next()
+                        .map(|output| output.name()),
+                };
+                info!(output = ?self.pointer_output_lock, "Toggled keeping the pointer on one output");
+            }
+            Action::ToggleSpotlight => {
+                self.spotlight = !self.spotlight;
+            }
+            Action::ToggleWindowPreview => {
+                self.show_window_preview = !self.show_window_preview;
+            }
+            Action::ToggleClock => self.toggle_clock(),
+            Action::ToggleZen => self.toggle_zen(),
+            Action::ToggleStickyAllOutputs => {
+                let keyboard = self.seat.get_keyboard().unwrap();
+                let Some(window) = keyboard
+                    .current_focus()
+                    .and_then(|focused| focused.wl_surface())
+                    .and_then(|surface| self.window_for_surface(&surface))
+                else {
+                    return;
+                };
+
+                // only floating windows can be sticky, a fullscreen one already covers its output
+                let is_fullscreen = self.workspaces.outputs().any(|o| {
+                    o.user_data()
+                        .get::<FullscreenSurface>()
+                        .and_then(|f| f.get().0)
+                        .map(|w| w == window)
+                        .unwrap_or(false)
+                });
+                if is_fullscreen {
+                    return;
+                }
+
+                window.set_sticky_all_outputs(!window.is_sticky_all_outputs());
+            }
+            Action::ResetWindowState => {
+                let keyboard = self.seat.get_keyboard().unwrap();
+                let Some(window) = keyboard
+                    .current_focus()
+                    .and_then(|focused| focused.wl_surface())
+                    .and_then(|surface| self.window_for_surface(&surface))
+                else {
+                    return;
+                };
+
+                window.set_sticky_all_outputs(false);
+
+                let is_fullscreen = self.workspaces.outputs().any(|o| {
+                    o.user_data()
+                        .get::<FullscreenSurface>()
+                        .and_then(|f| f.get().0)
+                        .map(|w| w == window)
+                        .unwrap_or(false)
+                });
+
+                match window {
+                    WindowElement::Wayland(ref w) => {
+                        let toplevel = w.toplevel().clone();
+                        if is_fullscreen {
+                            XdgShellHandler::unfullscreen_request(self, toplevel.clone());
+                        }
+                        XdgShellHandler::unmaximize_request(self, toplevel);
+                    }
+                    #[cfg(feature = "xwayland")]
+                    WindowElement::X11(ref w) => {
+                        let w = w.clone();
+                        self.loop_handle.insert_idle(move |data| {
+                            let xwm = data.state.xwm.as_ref().unwrap().id();
+                            if is_fullscreen {
+                                XwmHandler::unfullscreen_request(data, xwm, w.clone());
+                            }
+                            if w.is_maximized() {
+                                XwmHandler::unmaximize_request(data, xwm, w);
+                            }
+                        });
+                    }
+                }
+            }
+            Action::ResetSize => {
+                let keyboard = self.seat.get_keyboard().unwrap();
+                let Some(window) = keyboard
+                    .current_focus()
+                    .and_then(|focused| focused.wl_surface())
+                    .and_then(|surface| self.window_for_surface(&surface))
+                else {
+                    return;
+                };
+
+                // fullscreen windows keep the size of the output
+                let is_fullscreen = self.workspaces.outputs().any(|o| {
+                    o.user_data()
+                        .get::<FullscreenSurface>()
+                        .and_then(|f| f.get().0)
+                        .map(|w| w == window)
+                        .unwrap_or(false)
+                });
+                if is_fullscreen {
+                    return;
+                }
+
+                let Some(size) = window.preferred_size() else {
+                    return;
+                };
+
+                let workspace = self.workspaces.current_workspace_mut();
+                let Some(output_geometry) = workspace
+                    .outputs_for_window(&window)
+                    .first()
+                    .and_then(|o| workspace.output_geometry(o))
+                else {
+                    return;
+                };
+
+                let location = output_geometry.loc
+                    + Point::from((
+                        (output_geometry.size.w - size.w) / 2,
+                        (output_geometry.size.h - size.h) / 2,
+                    ));
+
+                match window {
+                    WindowElement::Wayland(ref w) => {
+                        w.toplevel().with_pending_state(|state| {
+                            state.size = Some(size);
+                        });
+                        w.toplevel().send_pending_configure();
+                    }
+                    WindowElement::X11(ref w) => {
+                        if let Err(err) =
+                            w.configure(Some(Rectangle::from_loc_and_size(location, size)))
+                        {
+                            error!(?err, "Failed to resize X11 window");
+                        }
+                    }
+                }
+
+                workspace.map_window(window, location, true);
+            }
+            Action::ToggleDebugLogging => {
+                self.verbose_logging = !self.verbose_logging;
+                let filter = if self.verbose_logging {
+                    EnvFilter::new("debug,buddaraysh=trace")
+                } else {
+                    log_filter()
+                };
+
+                if let Err(err) = self.log_filter.reload(filter) {
+                    error!("Failed to reload log filter: {err}");
+                } else {
+                    info!(verbose = self.verbose_logging, "Toggled verbose logging");
+                }
+            }
+            Action::FocusLastOnOtherOutput => {
+                if self.focus_lock {
+                    return;
+                }
+
+                let keyboard = self.seat.get_keyboard().unwrap();
+                let workspace = self.workspaces.current_workspace();
+                let outputs: Vec<Output> = workspace.outputs().cloned().collect();
+
+                let focused_output = keyboard
+                    .current_focus()
+                    .and_then(|focused| focused.wl_surface())
+                    .and_then(|surface| self.window_for_surface(&surface))
+                    .and_then(|window| workspace.outputs_for_window(&window).first().cloned())
+                    .or_else(|| {
+                        workspace
+                            .output_under(self.pointer.current_location())
+                            .next()
+                            .cloned()
+                    });
+
+                let Some(current_index) =
+                    focused_output.and_then(|output| outputs.iter().position(|o| *o == output))
+                else {
+                    return;
+                };
+
+                // windows get raised when focused, so the topmost window on an output
+                // is the one that was used most recently there
+                let window = (1..outputs.len())
+                    .map(|offset| &outputs[(current_index + offset) % outputs.len()])
+                    .find_map(|output| {
+                        workspace
+                            .windows()
+                            .rev()
+                            .find(|w| workspace.outputs_for_window(w).contains(output))
+                            .cloned()
+                    });
+
+                if let Some(window) = window {
+                    self.workspaces
+                        .current_workspace_mut()
+                        .raise_window(&window, true);
+                    #[cfg(feature = "xwayland")]
+                    if let WindowElement::X11(surf) = &window {
+                        self.xwm.as_mut().unwrap().raise_window(surf).unwrap();
                     }
+                    keyboard.set_focus(self, Some(window.into()), SERIAL_COUNTER.next_serial());
                 }
             }
-            Action::SwitchToWorkspace(workspace_index) => {
-                if self
-                    .workspaces
-                    .set_current_workspace(workspace_index)
-                    .is_none()
-                {
-                    error!("workspace index does not exist");
+            Action::FocusDirection(direction) => {
+                if self.focus_lock {
+                    return;
                 }
-                let pointer = self.pointer.clone();
-                let now = Instant::now();
-                let time = now.duration_since(self.start_time).as_millis() as u32;
-                // this is to fix the button press being sent to the fullscreen
-                // surface when switching to another workspace
-                //
-                // but doesn't fix when switching back to the workspace with the
-                // fullscreen surface
-                pointer.motion(
-                    self,
-                    None,
-                    &MotionEvent {
-                        location: pointer.current_location(),
-                        serial: SERIAL_COUNTER.next_serial(),
-                        time,
-                    },
-                );
-                pointer.frame(self);
 
-                let target = self.surface_under(pointer.current_location());
                 let keyboard = self.seat.get_keyboard().unwrap();
-                let serial = SERIAL_COUNTER.next_serial();
-                keyboard.set_focus(self, target.map(|(f, _)| f), serial);
-            }
-            Action::MoveToWorkspace(workspace_index) => {
-                if self.workspaces.current_workspace_index() == workspace_index {
+                let Some(focused) = keyboard
+                    .current_focus()
+                    .and_then(|focused| focused.wl_surface())
+                    .and_then(|surface| self.window_for_surface(&surface))
+                else {
+                    return;
+                };
+
+                let workspace = self.workspaces.current_workspace();
+                let Some(bounds) = workspace
+                    .outputs_for_window(&focused)
+                    .first()
+                    .and_then(|output| workspace.output_geometry(output))
+                else {
+                    return;
+                };
+
+                let Some(window) = workspace.window_in_direction(&focused, direction, bounds)
+                else {
+                    debug!(?direction, "No window in that direction");
                     return;
+                };
+
+                self.workspaces
+                    .current_workspace_mut()
+                    .raise_window(&window, true);
+                #[cfg(feature = "xwayland")]
+                if let WindowElement::X11(surf) = &window {
+                    self.xwm.as_mut().unwrap().raise_window(surf).unwrap();
                 }
+                keyboard.set_focus(self, Some(window.into()), SERIAL_COUNTER.next_serial());
+            }
+            Action::MoveWindowDirection(direction) => self.nudge_window(direction),
+            Action::SummonWindow(query) => {
+                let Some(window) = self.find_window(&query) else {
+                    debug!(query, "No window to summon");
+                    return;
+                };
+                let Some(output) = self
+                    .workspaces
+                    .output_under(self.pointer.current_location())
+                    .next()
+                    .or_else(|| self.workspaces.outputs().next())
+                    .cloned()
+                else {
+                    return;
+                };
 
-                let keyboard = self.seat.get_keyboard().unwrap();
+                // the grab would keep moving the window on the workspace it came from
                 let pointer = self.pointer.clone();
-
-                // prevent window from duplicating (in the current workspace and the destination workspace)
-                // when it's grabbed
                 if pointer.is_grabbed() {
                     let time = Instant::now().duration_since(self.start_time);
                     pointer.unset_grab(self, SERIAL_COUNTER.next_serial(), time.as_millis() as u32);
                 }
 
-                if let Some(window) = keyboard
-                    .current_focus()
-                    .and_then(|t| t.wl_surface())
-                    .and_then(|s| self.window_for_surface(&s))
-                {
-                    let location = self
-                        .workspaces
-                        .current_workspace()
-                        .window_location(&window)
-                        .unwrap();
-
-                    self.workspaces
-                        .current_workspace_mut()
-                        .unmap_window(&window);
+                self.summon_window(&window, &output);
 
-                    if let Some(workspace) = self.workspaces.get_mut(workspace_index) {
-                        workspace.map_window(window, location, true);
-                    } else {
-                        self.workspaces
-                            .current_workspace_mut()
-                            .map_window(window, location, false);
-                        error!("invalid workspace index");
-                    }
+                #[cfg(feature = "xwayland")]
+                if let WindowElement::X11(surf) = &window {
+                    self.xwm.as_mut().unwrap().raise_window(surf).unwrap();
                 }
+                let keyboard = self.seat.get_keyboard().unwrap();
+                keyboard.set_focus(self, Some(window.into()), SERIAL_COUNTER.next_serial());
             }
             Action::None => {}
         }
     }
 }
 
-fn switch_workspace(modifiers: &ModifiersState, raw_syms: &[Keysym]) -> Option<Option<Action>> {
-    if modifiers.logo && raw_syms.contains(&Keysym::_1) {
-        return Some(Some(Action::SwitchToWorkspace(0)));
-    }
-    if modifiers.logo && raw_syms.contains(&Keysym::_2) {
-        return Some(Some(Action::SwitchToWorkspace(1)));
-    }
-    if modifiers.logo && raw_syms.contains(&Keysym::_3) {
-        return Some(Some(Action::SwitchToWorkspace(2)));
-    }
-    if modifiers.logo && raw_syms.contains(&Keysym::_4) {
-        return Some(Some(Action::SwitchToWorkspace(3)));
+/// Shows a notification through the notification daemon with `notify-send`. Failures only
+/// end up in the log, there's nowhere else to show them
+fn notify(summary: &str, body: &str) {
+    let child = match std::process::Command::new("notify-send")
+        .arg("--app-name=buddaraysh")
+        .arg(summary)
+        .arg(body)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => {
+            error!(
+                summary,
+                "Failed to run notify-send, is libnotify installed? {err}"
+            );
+            return;
+        }
+    };
+
+    // notify-send fails once it's running when there's no notification daemon, waiting for it
+    // also keeps it from lingering as a zombie
+    let summary = summary.to_owned();
+    std::thread::spawn(move || match child.wait_with_output() {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => error!(
+            summary = %summary,
+            status = %output.status,
+            stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+            "notify-send failed, is a notification daemon running?"
+        ),
+        Err(err) => error!(summary = %summary, "Failed to wait for notify-send: {err}"),
+    });
+}
+
+/// Number keys of the workspaces, in order. Workspaces past the tenth have no keys
+const WORKSPACE_KEYS: [Keysym; 10] = [
+    Keysym::_1,
+    Keysym::_2,
+    Keysym::_3,
+    Keysym::_4,
+    Keysym::_5,
+    Keysym::_6,
+    Keysym::_7,
+    Keysym::_8,
+    Keysym::_9,
+    Keysym::_0,
+];
+
+/// Index of the workspace whose number key is pressed, only counting the `amount` that exist
+fn workspace_key(raw_syms: &[Keysym], amount: usize) -> Option<usize> {
+    WORKSPACE_KEYS
+        .iter()
+        .take(amount)
+        .position(|key| raw_syms.contains(key))
+}
+
+fn switch_workspace(
+    modifiers: &ModifiersState,
+    raw_syms: &[Keysym],
+    amount: usize,
+) -> Option<Option<Action>> {
+    if !modifiers.logo {
+        return None;
     }
-    if modifiers.logo && raw_syms.contains(&Keysym::_5) {
-        return Some(Some(Action::SwitchToWorkspace(4)));
+    workspace_key(raw_syms, amount).map(|index| Some(Action::SwitchToWorkspace(index)))
+}
+
+fn focus_direction(modifiers: &ModifiersState, raw_syms: &[Keysym]) -> Option<Option<Action>> {
+    if !modifiers.logo || modifiers.alt || modifiers.shift || modifiers.ctrl {
+        return None;
     }
-    if modifiers.logo && raw_syms.contains(&Keysym::_6) {
-        return Some(Some(Action::SwitchToWorkspace(5)));
+    if raw_syms.contains(&Keysym::h) {
+        return Some(Some(Action::FocusDirection(Direction::Left)));
     }
-    if modifiers.logo && raw_syms.contains(&Keysym::_7) {
-        return Some(Some(Action::SwitchToWorkspace(6)));
+    if raw_syms.contains(&Keysym::j) {
+        return Some(Some(Action::FocusDirection(Direction::Down)));
     }
-    if modifiers.logo && raw_syms.contains(&Keysym::_8) {
-        return Some(Some(Action::SwitchToWorkspace(7)));
+    if raw_syms.contains(&Keysym::k) {
+        return Some(Some(Action::FocusDirection(Direction::Up)));
     }
-    if modifiers.logo && raw_syms.contains(&Keysym::_9) {
-        return Some(Some(Action::SwitchToWorkspace(8)));
+    if raw_syms.contains(&Keysym::l) {
+        return Some(Some(Action::FocusDirection(Direction::Right)));
     }
     None
 }
 
-fn move_to_workspace(modifiers: &ModifiersState, raw_syms: &[Keysym]) -> Option<Option<Action>> {
-    if modifiers.logo
-        && modifiers.shift
-        && !modifiers.alt
-        && !modifiers.ctrl
-        && raw_syms.contains(&Keysym::_1)
-    {
-        return Some(Some(Action::MoveToWorkspace(0)));
-    }
-    if modifiers.logo
-        && modifiers.shift
-        && !modifiers.alt
-        && !modifiers.ctrl
-        && raw_syms.contains(&Keysym::_2)
-    {
-        return Some(Some(Action::MoveToWorkspace(1)));
-    }
-    if modifiers.logo
-        && modifiers.shift
-        && !modifiers.alt
-        && !modifiers.ctrl
-        && raw_syms.contains(&Keysym::_3)
-    {
-        return Some(Some(Action::MoveToWorkspace(2)));
+fn move_window_direction(
+    modifiers: &ModifiersState,
+    raw_syms: &[Keysym],
+) -> Option<Option<Action>> {
+    if !modifiers.logo || !modifiers.alt || modifiers.shift || modifiers.ctrl {
+        return None;
     }
-    if modifiers.logo
-        && modifiers.shift
-        && !modifiers.alt
-        && !modifiers.ctrl
-        && raw_syms.contains(&Keysym::_4)
-    {
-        return Some(Some(Action::MoveToWorkspace(3)));
+    if raw_syms.contains(&Keysym::h) {
+        return Some(Some(Action::MoveWindowDirection(Direction::Left)));
     }
-    if modifiers.logo
-        && modifiers.shift
-        && !modifiers.alt
-        && !modifiers.ctrl
-        && raw_syms.contains(&Keysym::_5)
-    {
-        return Some(Some(Action::MoveToWorkspace(4)));
+    if raw_syms.contains(&Keysym::j) {
+        return Some(Some(Action::MoveWindowDirection(Direction::Down)));
     }
-    if modifiers.logo
-        && modifiers.shift
-        && !modifiers.alt
-        && !modifiers.ctrl
-        && raw_syms.contains(&Keysym::_6)
-    {
-        return Some(Some(Action::MoveToWorkspace(5)));
+    if raw_syms.contains(&Keysym::k) {
+        return Some(Some(Action::MoveWindowDirection(Direction::Up)));
     }
-    if modifiers.logo
-        && modifiers.shift
-        && !modifiers.alt
-        && !modifiers.ctrl
-        && raw_syms.contains(&Keysym::_7)
-    {
-        return Some(Some(Action::MoveToWorkspace(6)));
+    if raw_syms.contains(&Keysym::l) {
+        return Some(Some(Action::MoveWindowDirection(Direction::Right)));
     }
-    if modifiers.logo
-        && modifiers.shift
-        && !modifiers.alt
-        && !modifiers.ctrl
-        && raw_syms.contains(&Keysym::_8)
-    {
-        return Some(Some(Action::MoveToWorkspace(7)));
+    None
+}
+
+fn move_to_workspace(
+    modifiers: &ModifiersState,
+    raw_syms: &[Keysym],
+    amount: usize,
+) -> Option<Option<Action>> {
+    if !modifiers.logo || !modifiers.shift || modifiers.alt || modifiers.ctrl {
+        return None;
     }
-    if modifiers.logo
-        && modifiers.shift
-        && !modifiers.alt
-        && !modifiers.ctrl
-        && raw_syms.contains(&Keysym::_9)
-    {
-        return Some(Some(Action::MoveToWorkspace(8)));
+    workspace_key(raw_syms, amount).map(|index| Some(Action::MoveToWorkspace(index)))
+}
+
+fn merge_workspace(
+    modifiers: &ModifiersState,
+    raw_syms: &[Keysym],
+    amount: usize,
+) -> Option<Option<Action>> {
+    if !modifiers.logo || !modifiers.ctrl || modifiers.alt || modifiers.shift {
+        return None;
     }
-    None
+    workspace_key(raw_syms, amount).map(|index| Some(Action::MergeWorkspaceInto(index)))
+}
+
+/// Width and height the absolute pointer position of a tablet or touchpad maps to, the
+/// outputs side by side. `None` without any output
+fn absolute_motion_area(
+    output_sizes: impl IntoIterator<Item = Size<i32, Logical>>,
+) -> Option<(i32, i32)> {
+    output_sizes.into_iter().fold(None, |area, size| {
+        let (w, h) = area.unwrap_or((0, 0));
+        Some((w + size.w, h.max(size.h)))
+    })
+}
+
+/// Where the absolute position of a tablet or touchpad puts the pointer. `transform` scales
+/// the position to the width and height of `absolute_motion_area`, like
+/// `AbsolutePositionEvent::x_transformed` does. `None` without any output, the event is
+/// ignored then
+fn absolute_motion_location(
+    output_sizes: impl IntoIterator<Item = Size<i32, Logical>>,
+    transform: impl FnOnce(i32, i32) -> (f64, f64),
+) -> Option<Point<f64, Logical>> {
+    let (max_x, max_y) = absolute_motion_area(output_sizes)?;
+    Some(transform(max_x, max_y).into())
 }
 
 impl Buddaraysh<WinitData> {
     pub fn process_input_event<I: InputBackend>(&mut self, event: InputEvent<I>) {
+        self.last_input = Instant::now();
+
         match event {
             InputEvent::Keyboard { event, .. } => {
                 let serial = SERIAL_COUNTER.next_serial();
                 let time = Event::time_msec(&event);
 
                 let keyboard = self.seat.get_keyboard().unwrap();
+                let client_grab = self.client_keyboard_grab();
 
                 let action = keyboard.input::<Action, _>(
                     self,
@@ -392,10 +1483,16 @@ impl Buddaraysh<WinitData> {
                         let keysym = handle.modified_sym();
                         let raw_syms = handle.raw_syms();
 
-                        data.input_to_action(modifiers, keysym, raw_syms, event.state())
-                            .map_or(FilterResult::Forward, |action| {
-                                FilterResult::Intercept(action)
-                            })
+                        data.input_to_action(
+                            modifiers,
+                            keysym,
+                            raw_syms,
+                            event.state(),
+                            client_grab,
+                        )
+                        .map_or(FilterResult::Forward, |action| {
+                            FilterResult::Intercept(action)
+                        })
                     },
                 );
 
@@ -403,9 +1500,21 @@ impl Buddaraysh<WinitData> {
                     self.process_common_actions(action);
                 }
             }
+            // winit reports touch screens of the host as touch events
+            InputEvent::TouchDown { event } => {
+                if self.seat.get_touch().is_none() {
+                    self.seat.add_touch();
+                }
+                self.on_touch_down::<I>(event)
+            }
+            InputEvent::TouchUp { event } => self.on_touch_up::<I>(event),
+            InputEvent::TouchMotion { event } => self.on_touch_motion::<I>(event),
+            InputEvent::TouchCancel { .. } => self.on_touch_cancel(),
             InputEvent::PointerMotion { .. } => {}
             InputEvent::PointerMotionAbsolute { event, .. } => {
-                let output = self.workspaces.outputs().next().unwrap();
+                let Some(output) = self.workspaces.outputs().next() else {
+                    return;
+                };
 
                 let output_geo = self
                     .workspaces
@@ -431,6 +1540,7 @@ impl Buddaraysh<WinitData> {
                     },
                 );
                 pointer.frame(self);
+                self.focus_hovered_window(serial);
             }
             InputEvent::PointerButton { event, .. } => {
                 let pointer = self.seat.get_pointer().unwrap();
@@ -442,7 +1552,15 @@ impl Buddaraysh<WinitData> {
 
                 let button_state = event.state();
 
-                if ButtonState::Pressed == button_state && !pointer.is_grabbed() {
+                if ButtonState::Pressed == button_state
+                    && !pointer.is_grabbed()
+                    && self.desktop_click(button)
+                {
+                    return;
+                }
+
+                if ButtonState::Pressed == button_state && !pointer.is_grabbed() && !self.focus_lock
+                {
                     if let Some((window, _loc)) = self
                         .workspaces
                         .current_workspace()
@@ -489,51 +1607,7 @@ impl Buddaraysh<WinitData> {
                 );
                 pointer.frame(self);
             }
-            InputEvent::PointerAxis { event, .. } => {
-                let horizontal_amount = event.amount(Axis::Horizontal).unwrap_or_else(|| {
-                    event.amount_v120(Axis::Horizontal).unwrap_or(0.0) * 3.0 / 120.
-                });
-                let vertical_amount = event.amount(Axis::Vertical).unwrap_or_else(|| {
-                    event.amount_v120(Axis::Vertical).unwrap_or(0.0) * 3.0 / 120.
-                });
-                let horizontal_amount_discrete = event.amount_v120(Axis::Horizontal);
-                let vertical_amount_discrete = event.amount_v120(Axis::Vertical);
-
-                {
-                    let mut frame = AxisFrame::new(event.time_msec()).source(event.source());
-                    if horizontal_amount != 0.0 {
-                        frame = frame.relative_direction(
-                            Axis::Horizontal,
-                            event.relative_direction(Axis::Horizontal),
-                        );
-                        frame = frame.value(Axis::Horizontal, horizontal_amount);
-                        if let Some(discrete) = horizontal_amount_discrete {
-                            frame = frame.v120(Axis::Horizontal, discrete as i32);
-                        }
-                    }
-                    if vertical_amount != 0.0 {
-                        frame = frame.relative_direction(
-                            Axis::Vertical,
-                            event.relative_direction(Axis::Vertical),
-                        );
-                        frame = frame.value(Axis::Vertical, vertical_amount);
-                        if let Some(discrete) = vertical_amount_discrete {
-                            frame = frame.v120(Axis::Vertical, discrete as i32);
-                        }
-                    }
-                    if event.source() == AxisSource::Finger {
-                        if event.amount(Axis::Horizontal) == Some(0.0) {
-                            frame = frame.stop(Axis::Horizontal);
-                        }
-                        if event.amount(Axis::Vertical) == Some(0.0) {
-                            frame = frame.stop(Axis::Vertical);
-                        }
-                    }
-                    let pointer = self.pointer.clone();
-                    pointer.axis(self, frame);
-                    pointer.frame(self);
-                }
-            }
+            InputEvent::PointerAxis { event, .. } => self.on_pointer_axis::<I>(event),
             _ => {}
         }
     }
@@ -545,6 +1619,13 @@ impl Buddaraysh<UdevData> {
         _display_handle: &DisplayHandle,
         event: InputEvent<LibinputInputBackend>,
     ) {
+        self.last_input = Instant::now();
+        self.wake_idle_outputs();
+        // anything but relative motion may change what's under the pointer
+        if !matches!(event, InputEvent::PointerMotion { .. }) {
+            self.pointer_under_cache = None;
+        }
+
         match event {
             InputEvent::Keyboard { event, .. } => {
                 let serial = SERIAL_COUNTER.next_serial();
@@ -565,6 +1646,8 @@ impl Buddaraysh<UdevData> {
 
                 event.device().led_update(leds);
 
+                // read before `input`, the filter runs with the keyboard locked
+                let client_grab = self.client_keyboard_grab();
                 let action = keyboard.input::<Action, _>(
                     self,
                     event.key_code(),
@@ -588,10 +1671,16 @@ impl Buddaraysh<UdevData> {
                             return FilterResult::Intercept(Action::None);
                         }
 
-                        data.input_to_action(modifiers, keysym, raw_syms, event.state())
-                            .map_or(FilterResult::Forward, |action| {
-                                FilterResult::Intercept(action)
-                            })
+                        data.input_to_action(
+                            modifiers,
+                            keysym,
+                            raw_syms,
+                            event.state(),
+                            client_grab,
+                        )
+                        .map_or(FilterResult::Forward, |action| {
+                            FilterResult::Intercept(action)
+                        })
                     },
                 );
 
@@ -600,11 +1689,16 @@ impl Buddaraysh<UdevData> {
                 }
             }
             InputEvent::PointerMotion { event, .. } => {
+                // nowhere to move the pointer to until an output comes back
+                if self.workspaces.outputs().next().is_none() {
+                    return;
+                }
+
                 let mut pointer_location = self.pointer.current_location();
                 let serial = SERIAL_COUNTER.next_serial();
 
                 let pointer = self.pointer.clone();
-                let under = self.surface_under(pointer_location);
+                let under = self.pointer_surface_under(pointer_location);
 
                 let mut pointer_locked = false;
                 let mut pointer_confined = false;
@@ -657,6 +1751,12 @@ impl Buddaraysh<UdevData> {
                 // this event is never generated by winit
                 pointer_location = self.clamp_coords(pointer_location);
 
+                if self.pointer_output_lock.is_some() {
+                    if let Some(output) = self.workspaces.output_under(pointer_location).next() {
+                        self.pointer_output_lock = Some(output.name());
+                    }
+                }
+
                 let new_under = self.surface_under(pointer_location);
 
                 // If confined, don't move pointer if it would go outside surface or region
@@ -679,7 +1779,7 @@ impl Buddaraysh<UdevData> {
 
                 pointer.motion(
                     self,
-                    under,
+                    new_under.clone(),
                     &MotionEvent {
                         location: pointer_location,
                         serial,
@@ -687,6 +1787,8 @@ impl Buddaraysh<UdevData> {
                     },
                 );
                 pointer.frame(self);
+                self.pointer_under_cache = Some((pointer_location, new_under.clone()));
+                self.focus_hovered_window(serial);
 
                 // If pointer is now in a constraint region, activate it
                 // TODO Anywhere else pointer is moved needs to do this
@@ -710,39 +1812,19 @@ impl Buddaraysh<UdevData> {
             InputEvent::PointerMotionAbsolute { event, .. } => {
                 let serial = SERIAL_COUNTER.next_serial();
 
-                let max_x = self.workspaces.outputs().fold(0, |acc, o| {
-                    acc + self
-                        .workspaces
-                        .current_workspace()
-                        .output_geometry(o)
-                        .unwrap()
-                        .size
-                        .w
-                });
-
-                let max_h_output = self
+                let output_sizes: Vec<Size<i32, Logical>> = self
                     .workspaces
                     .outputs()
-                    .max_by_key(|o| {
-                        self.workspaces
-                            .current_workspace()
-                            .output_geometry(o)
-                            .unwrap()
-                            .size
-                            .h
-                    })
-                    .unwrap();
-
-                let max_y = self
-                    .workspaces
-                    .current_workspace()
-                    .output_geometry(max_h_output)
-                    .unwrap()
-                    .size
-                    .h;
-
-                let mut pointer_location =
-                    (event.x_transformed(max_x), event.y_transformed(max_y)).into();
+                    .filter_map(|o| self.workspaces.current_workspace().output_geometry(o))
+                    .map(|geometry| geometry.size)
+                    .collect();
+
+                // nowhere to move the pointer to until an output comes back
+                let Some(mut pointer_location) = absolute_motion_location(output_sizes, |w, h| {
+                    (event.x_transformed(w), event.y_transformed(h))
+                }) else {
+                    return;
+                };
 
                 // clamp to screen limits
                 pointer_location = self.clamp_coords(pointer_location);
@@ -760,6 +1842,7 @@ impl Buddaraysh<UdevData> {
                     },
                 );
                 pointer.frame(self);
+                self.focus_hovered_window(serial);
             }
             InputEvent::PointerButton { event, .. } => {
                 let serial = SERIAL_COUNTER.next_serial();
@@ -771,15 +1854,33 @@ impl Buddaraysh<UdevData> {
 
                 let button_state = event.state();
 
+                // buttons bound to an action aren't sent to clients
+                if !self.seat.keyboard_shortcuts_inhibited() {
+                    if let Some(action) = self.mouse_bindings.action_for(button) {
+                        if ButtonState::Pressed == button_state {
+                            self.process_common_actions(action);
+                        }
+                        return;
+                    }
+                }
+
+                if ButtonState::Pressed == button_state
+                    && !pointer.is_grabbed()
+                    && self.desktop_click(button)
+                {
+                    return;
+                }
+
                 if ButtonState::Pressed == button_state {
                     let keyboard = self.seat.get_keyboard().unwrap();
 
                     let modifiers = keyboard.modifier_state();
+                    let bindings = self.mouse_bindings.clone();
+                    let modifier_pressed = bindings.modifier.is_pressed(&modifiers);
 
                     if !self.seat.keyboard_shortcuts_inhibited() {
-                        // TODO: make this better dear mr.future Salman xqcL
-                        if modifiers.logo
-                            && button == BTN_LEFT
+                        if modifier_pressed
+                            && button == bindings.move_button
                             && !keyboard.is_grabbed()
                             && !pointer.is_grabbed()
                         {
@@ -824,8 +1925,8 @@ impl Buddaraysh<UdevData> {
                             }
                         }
 
-                        if modifiers.logo
-                            && button == BTN_RIGHT
+                        if modifier_pressed
+                            && button == bindings.resize_button
                             && !keyboard.is_grabbed()
                             && !pointer.is_grabbed()
                         {
@@ -922,49 +2023,7 @@ impl Buddaraysh<UdevData> {
                 pointer.frame(self);
             }
             InputEvent::PointerAxis { event, .. } => {
-                let horizontal_amount = event.amount(Axis::Horizontal).unwrap_or_else(|| {
-                    event.amount_v120(Axis::Horizontal).unwrap_or(0.0) * 3.0 / 120.
-                });
-                let vertical_amount = event.amount(Axis::Vertical).unwrap_or_else(|| {
-                    event.amount_v120(Axis::Vertical).unwrap_or(0.0) * 3.0 / 120.
-                });
-                let horizontal_amount_discrete = event.amount_v120(Axis::Horizontal);
-                let vertical_amount_discrete = event.amount_v120(Axis::Vertical);
-
-                {
-                    let mut frame = AxisFrame::new(event.time_msec()).source(event.source());
-                    if horizontal_amount != 0.0 {
-                        frame = frame.relative_direction(
-                            Axis::Horizontal,
-                            event.relative_direction(Axis::Horizontal),
-                        );
-                        frame = frame.value(Axis::Horizontal, horizontal_amount);
-                        if let Some(discrete) = horizontal_amount_discrete {
-                            frame = frame.v120(Axis::Horizontal, discrete as i32);
-                        }
-                    }
-                    if vertical_amount != 0.0 {
-                        frame = frame.relative_direction(
-                            Axis::Vertical,
-                            event.relative_direction(Axis::Vertical),
-                        );
-                        frame = frame.value(Axis::Vertical, vertical_amount);
-                        if let Some(discrete) = vertical_amount_discrete {
-                            frame = frame.v120(Axis::Vertical, discrete as i32);
-                        }
-                    }
-                    if event.source() == AxisSource::Finger {
-                        if event.amount(Axis::Horizontal) == Some(0.0) {
-                            frame = frame.stop(Axis::Horizontal);
-                        }
-                        if event.amount(Axis::Vertical) == Some(0.0) {
-                            frame = frame.stop(Axis::Vertical);
-                        }
-                    }
-                    let pointer = self.pointer.clone();
-                    pointer.axis(self, frame);
-                    pointer.frame(self);
-                }
+                self.on_pointer_axis::<LibinputInputBackend>(event)
             }
             InputEvent::GestureSwipeBegin { event } => {
                 let serial = SERIAL_COUNTER.next_serial();
@@ -1178,71 +2237,15 @@ impl Buddaraysh<UdevData> {
                     );
                 }
             }
-            InputEvent::TouchDown { event } => {
-                let touch = self.seat.get_touch();
-                if let Some(touch) = touch {
-                    let serial = SERIAL_COUNTER.next_serial();
-                    let time = event.time_msec();
-                    let output_geometry = self.workspaces.outputs().next().map(|o| {
-                        self.workspaces
-                            .current_workspace()
-                            .output_geometry(o)
-                            .unwrap()
-                    });
-
-                    if let Some(output_geometry) = output_geometry {
-                        let position = event.position_transformed(output_geometry.size)
-                            + output_geometry.loc.to_f64();
-                        if let Some((surface, surface_pos)) = self.surface_under(position) {
-                            let position_inside_surface = position - surface_pos.to_f64();
-                            info!(?position_inside_surface);
-                            touch.down(
-                                serial,
-                                time,
-                                &surface.wl_surface().unwrap(),
-                                position_inside_surface,
-                                event.slot(),
-                            );
-                        }
-                    }
-                }
-            }
-            InputEvent::TouchUp { event } => {
-                let touch = self.seat.get_touch();
-                if let Some(touch) = touch {
-                    touch.up(
-                        SERIAL_COUNTER.next_serial(),
-                        event.time_msec(),
-                        event.slot(),
-                    );
-                }
-            }
+            InputEvent::TouchDown { event } => self.on_touch_down::<LibinputInputBackend>(event),
+            InputEvent::TouchUp { event } => self.on_touch_up::<LibinputInputBackend>(event),
             InputEvent::TouchMotion { event } => {
-                let touch = self.seat.get_touch();
-                if let Some(touch) = touch {
-                    let output_geometry = self.workspaces.outputs().next().map(|o| {
-                        self.workspaces
-                            .current_workspace()
-                            .output_geometry(o)
-                            .unwrap()
-                    });
-
-                    if let Some(output_geometry) = output_geometry {
-                        let position = event.position_transformed(output_geometry.size)
-                            + output_geometry.loc.to_f64();
-                        touch.motion(event.time_msec(), event.slot(), position);
-                    }
-                }
+                self.on_touch_motion::<LibinputInputBackend>(event)
             }
             InputEvent::TouchFrame { event: _ } => {
                 // NOTE: not sure if this needs to be handled
             }
-            InputEvent::TouchCancel { event: _ } => {
-                let touch = self.seat.get_touch();
-                if let Some(touch) = touch {
-                    touch.cancel();
-                }
-            }
+            InputEvent::TouchCancel { event: _ } => self.on_touch_cancel(),
             InputEvent::DeviceAdded { device } => {
                 if device.has_capability(DeviceCapability::TabletTool) {
                     self.seat
@@ -1273,6 +2276,31 @@ impl Buddaraysh<UdevData> {
             return pos;
         }
 
+        // holding Super lets the pointer leave the output it's kept on
+        let override_held = self
+            .seat
+            .get_keyboard()
+            .map_or(false, |keyboard| keyboard.modifier_state().logo);
+        let locked_geometry = self
+            .pointer_output_lock
+            .as_ref()
+            .filter(|_| !override_held)
+            .and_then(|name| self.workspaces.outputs().find(|o| o.name() == *name))
+            .and_then(|o| self.workspaces.current_workspace().output_geometry(o));
+        if let Some(geometry) = locked_geometry {
+            return (
+                pos.x.clamp(
+                    geometry.loc.x as f64,
+                    (geometry.loc.x + geometry.size.w - 1) as f64,
+                ),
+                pos.y.clamp(
+                    geometry.loc.y as f64,
+                    (geometry.loc.y + geometry.size.h - 1) as f64,
+                ),
+            )
+                .into();
+        }
+
         let (pos_x, pos_y) = pos.into();
         let max_x = self.workspaces.outputs().fold(0, |acc, o| {
             acc + self
@@ -1324,6 +2352,10 @@ impl Buddaraysh<UdevData> {
         // subsurface menus (for example firefox-wayland).
         // see here for a discussion about that issue:
         // https://gitlab.freedesktop.org/wayland/wayland/-/issues/294
+        if self.focus_lock {
+            return;
+        }
+
         if !self.pointer.is_grabbed() && (!keyboard.is_grabbed() || input_method.keyboard_grabbed())
         {
             let output = self
@@ -1337,18 +2369,10 @@ impl Buddaraysh<UdevData> {
                     .current_workspace()
                     .output_geometry(output)
                     .unwrap();
-                if let Some(window) =
-                    output
-                        .user_data()
-                        .get::<FullscreenSurface>()
-                        .and_then(|f| match f.get() {
-                            (Some(window), Some(workspace_index))
-                                if workspace_index == self.workspaces.current_workspace_index() =>
-                            {
-                                Some(window)
-                            }
-                            _ => None,
-                        })
+                if let Some(window) = output
+                    .user_data()
+                    .get::<FullscreenSurface>()
+                    .and_then(|f| f.visible_on(self.workspaces.current_workspace_index()))
                 {
                     if let Some((_, _)) = window.surface_under(
                         self.pointer.current_location() - output_geo.loc.to_f64(),
@@ -1427,3 +2451,39 @@ impl Buddaraysh<UdevData> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use smithay::utils::{Logical, Size};
+
+    use super::{absolute_motion_area, absolute_motion_location};
+
+    #[test]
+    fn absolute_motion_area_without_outputs() {
+        assert_eq!(absolute_motion_area(Vec::<Size<i32, Logical>>::new()), None);
+    }
+
+    #[test]
+    fn absolute_motion_area_puts_outputs_side_by_side() {
+        let sizes: Vec<Size<i32, Logical>> = vec![(1920, 1080).into(), (1280, 1440).into()];
+
+        assert_eq!(absolute_motion_area(sizes), Some((3200, 1440)));
+    }
+
+    #[test]
+    fn absolute_motion_without_outputs_is_ignored() {
+        let location = absolute_motion_location(Vec::<Size<i32, Logical>>::new(), |_, _| {
+            panic!("there's no area to scale the position to")
+        });
+
+        assert_eq!(location, None);
+    }
+
+    #[test]
+    fn absolute_motion_spans_all_outputs() {
+        let sizes: Vec<Size<i32, Logical>> = vec![(1920, 1080).into(), (1280, 1440).into()];
+
+        let location = absolute_motion_location(sizes, |w, h| (w as f64 / 2.0, h as f64 / 2.0));
+        assert_eq!(location, Some((1600.0, 720.0).into()));
+    }
+}
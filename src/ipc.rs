@@ -0,0 +1,862 @@
+//! Control socket for scripts and bars, every line sent to it is a JSON request that gets a
+//! JSON response line back.
+//!
+//! Requests are `{"action": "<name>"}`, with the action names of the config file,
+//! `{"query": "workspaces"}`, `{"query": "windows"}` and `{"query": "get_tree"}`, or `{"capture": {"workspace": <n>}}`.
+//! `"subscribe"` keeps sending [`Event`]s on that connection after its response.
+//! Responses are queued and written without blocking, so a client that stops reading can't
+//! hold up the compositor.
+
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    io::{self, ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use smithay::{
+    desktop::space::SpaceElement,
+    reexports::calloop::{generic::Generic, Interest, LoopHandle, Mode, PostAction},
+    utils::{Logical, Physical, Point, Rectangle, Size},
+    wayland::seat::WaylandFocus,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    shell::FullscreenSurface, state::parse_action, window::WindowElement, workspace::WindowChange,
+    Backend, Buddaraysh, CalloopData,
+};
+
+/// Longest request line, a connection sending more without a newline is dropped
+const MAX_REQUEST_LEN: usize = 64 * 1024;
+
+/// How much output may wait for a connection that doesn't read it before it's dropped, a bar
+/// that stopped reading its events can't hold up the compositor
+const MAX_QUEUED_OUTPUT: usize = 1024 * 1024;
+
+/// Captures are at most this many times the size of the largest output on either side, a
+/// typo'd width can't make the compositor allocate a huge buffer
+const MAX_CAPTURE_FACTOR: i32 = 2;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Request {
+    Action(String),
+    Query(Query),
+    Capture(CaptureRequest),
+    Subscribe,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Query {
+    Workspaces,
+    Windows,
+    /// outputs, with the workspaces and the windows on them
+    GetTree,
+}
+
+/// Renders a workspace into a png, whether it's shown or not
+#[derive(Debug, Deserialize)]
+struct CaptureRequest {
+    /// starts at 1, like the keybinds
+    workspace: usize,
+    /// only the part of the workspace on this output, all outputs when left out
+    output: Option<String>,
+    /// the image fits into this size keeping its aspect ratio, the workspace's size when left out
+    width: Option<i32>,
+    height: Option<i32>,
+    /// file name under `~/.cache/buddaraysh/captures/`, picked from the workspace and the time
+    /// when left out
+    name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Response {
+    Ok,
+    Error(String),
+    Workspaces(Vec<WorkspaceInfo>),
+    Windows(Vec<WindowInfo>),
+    Tree(Vec<OutputNode>),
+    /// where the capture was saved
+    Capture(PathBuf),
+    /// events follow on this connection
+    Subscribed,
+}
+
+#[derive(Debug, Serialize)]
+struct WorkspaceInfo {
+    /// starts at 1, like the keybinds
+    index: usize,
+    current: bool,
+    windows: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct WindowInfo {
+    app_id: Option<String>,
+    title: Option<String>,
+    workspace: usize,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    focused: bool,
+}
+
+/// Sent to subscribed connections, one JSON line each
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    /// another workspace is shown, `output` is the one with the pointer
+    Workspace {
+        output: Option<String>,
+        /// starts at 1, like the keybinds
+        workspace: usize,
+    },
+    WindowMapped(WindowEvent),
+    WindowUnmapped(WindowEvent),
+    /// another window got the keyboard focus, or the focused one changed its title or app id
+    Focus(WindowEvent),
+}
+
+#[derive(Debug, Serialize)]
+pub struct WindowEvent {
+    app_id: Option<String>,
+    title: Option<String>,
+    output: Option<String>,
+    /// starts at 1, like the keybinds
+    workspace: usize,
+}
+
+/// An accepted connection, replies and events wait in `outgoing` until the socket takes them
+struct Connection {
+    stream: UnixStream,
+    outgoing: Vec<u8>,
+    /// events are sent to it
+    subscribed: bool,
+    /// the client closed its writing side, unless it's subscribed it's dropped once everything
+    /// is written
+    hung_up: bool,
+}
+
+impl Connection {
+    fn can_be_dropped(&self) -> bool {
+        self.hung_up && !self.subscribed && self.outgoing.is_empty()
+    }
+}
+
+impl Connection {
+    /// Writes as much of the queued output as the socket takes without blocking, fails once
+    /// the connection broke or more than [`MAX_QUEUED_OUTPUT`] is left unread
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match (&self.stream).write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        if self.outgoing.len() > MAX_QUEUED_OUTPUT {
+            return Err(io::Error::other("too much unread output"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OutputNode {
+    name: String,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    scale: f64,
+    workspaces: Vec<WorkspaceNode>,
+}
+
+#[derive(Debug, Serialize)]
+struct WorkspaceNode {
+    /// starts at 1, like the keybinds
+    index: usize,
+    current: bool,
+    windows: Vec<WindowNode>,
+}
+
+/// Windows are listed top to bottom, every window floats as there is no tiling
+#[derive(Debug, Serialize)]
+struct WindowNode {
+    app_id: Option<String>,
+    title: Option<String>,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    focused: bool,
+    fullscreen: bool,
+    maximized: bool,
+    sticky: bool,
+    zen: bool,
+}
+
+/// The listening socket, its file is removed again when this is dropped
+pub struct IpcSocket {
+    path: PathBuf,
+    connections: HashMap<usize, Connection>,
+    /// app id and title of the focused window the last focus event was about
+    last_focus: Option<(Option<String>, Option<String>)>,
+}
+
+impl Drop for IpcSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl IpcSocket {
+    /// Listens on `$XDG_RUNTIME_DIR/buddaraysh-<wayland display>.sock`
+    pub fn new<BackendData: Backend + 'static>(
+        loop_handle: &LoopHandle<'static, CalloopData<BackendData>>,
+        socket_name: &OsStr,
+    ) -> Option<Self> {
+        let runtime_dir = std::env::var("XDG_RUNTIME_DIR").ok()?;
+        let path = PathBuf::from(runtime_dir)
+            .join(format!("buddaraysh-{}.sock", socket_name.to_string_lossy()));
+
+        // left behind by a previous session that didn't exit cleanly
+        let _ = std::fs::remove_file(&path);
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(err) => {
+                warn!(?path, "Failed to bind the IPC socket: {}", err);
+                return None;
+            }
+        };
+        if let Err(err) = listener.set_nonblocking(true) {
+            warn!("Failed to make the IPC socket non-blocking: {}", err);
+            return None;
+        }
+
+        let client_handle = loop_handle.clone();
+        let mut next_client = 0;
+        loop_handle
+            .insert_source(
+                Generic::new(listener, Interest::READ, Mode::Level),
+                move |_, listener, data| {
+                    loop {
+                        match listener.as_ref().accept() {
+                            Ok((stream, _)) => {
+                                // replies are queued, a client that doesn't read them can't
+                                // block the compositor
+                                let writer = stream
+                                    .set_nonblocking(true)
+                                    .and_then(|()| stream.try_clone());
+                                match writer {
+                                    Ok(writer) => {
+                                        data.state.add_ipc_connection(next_client, writer);
+                                        add_client(&client_handle, next_client, stream);
+                                        next_client += 1;
+                                    }
+                                    Err(err) => {
+                                        warn!("Failed to set up an IPC connection: {}", err);
+                                    }
+                                }
+                            }
+                            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                            Err(err) => {
+                                warn!("Failed to accept an IPC connection: {}", err);
+                                break;
+                            }
+                        }
+                    }
+                    Ok(PostAction::Continue)
+                },
+            )
+            .expect("Failed to init the IPC socket source");
+
+        std::env::set_var("BUD_SOCKET", &path);
+        info!(?path, "Listening for IPC connections");
+
+        Some(Self {
+            path,
+            connections: HashMap::new(),
+            last_focus: None,
+        })
+    }
+}
+
+fn captures_dir() -> PathBuf {
+    let home_dir = std::env::var("HOME").expect("HOME should always be set");
+    PathBuf::from(home_dir).join(".cache/buddaraysh/captures/")
+}
+
+/// Reads requests from a connection until it's closed, which drops its event source
+fn add_client<BackendData: Backend + 'static>(
+    loop_handle: &LoopHandle<'static, CalloopData<BackendData>>,
+    client: usize,
+    stream: UnixStream,
+) {
+    let mut buffer = Vec::new();
+    let result = loop_handle.insert_source(
+        Generic::new(stream, Interest::READ, Mode::Level),
+        move |_, stream, data| {
+            let mut stream: &UnixStream = stream.as_ref();
+            let mut chunk = [0; 4096];
+            let read = match stream.read(&mut chunk) {
+                // a subscriber may only close its writing side, its events stop once writing
+                // to it fails
+                Ok(0) => {
+                    data.state.ipc_client_hung_up(client);
+                    return Ok(PostAction::Remove);
+                }
+                Ok(read) => read,
+                Err(err)
+                    if matches!(err.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock) =>
+                {
+                    return Ok(PostAction::Continue)
+                }
+                Err(err) => {
+                    debug!("IPC connection failed: {}", err);
+                    data.state.remove_ipc_connection(client);
+                    return Ok(PostAction::Remove);
+                }
+            };
+            buffer.extend_from_slice(&chunk[..read]);
+
+            while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let response = data.state.handle_ipc_request(line.trim());
+                if !data.state.queue_ipc_response(client, &response) {
+                    return Ok(PostAction::Remove);
+                }
+
+                // only after the response, so it's the first line the subscriber reads
+                if matches!(response, Response::Subscribed) {
+                    data.state.add_ipc_subscriber(client);
+                }
+            }
+
+            if buffer.len() > MAX_REQUEST_LEN {
+                debug!(client, "Dropping IPC connection sending a too long request");
+                let response = Response::Error(String::from("request too long"));
+                data.state.queue_ipc_response(client, &response);
+                data.state.ipc_client_hung_up(client);
+                return Ok(PostAction::Remove);
+            }
+
+            Ok(PostAction::Continue)
+        },
+    );
+
+    if let Err(err) = result {
+        warn!("Failed to add an IPC connection: {}", err);
+    }
+}
+
+impl<BackendData: Backend> Buddaraysh<BackendData> {
+    fn handle_ipc_request(&mut self, line: &str) -> Response {
+        let request = match serde_json::from_str::<Request>(line) {
+            Ok(request) => request,
+            Err(err) => return Response::Error(format!("invalid request: {err}")),
+        };
+
+        match request {
+            Request::Action(name) => {
+                let Some(action) = parse_action(&name) else {
+                    return Response::Error(format!("unknown action: {name}"));
+                };
+                debug!(?action, "IPC action");
+                self.process_common_actions(action);
+                Response::Ok
+            }
+            Request::Query(Query::Workspaces) => {
+                let current = self.workspaces.current_workspace_index();
+                let workspaces = self
+                    .workspaces
+                    .workspaces()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, workspace)| WorkspaceInfo {
+                        index: index + 1,
+                        current: index == current,
+                        windows: workspace.windows().count(),
+                    })
+                    .collect();
+                Response::Workspaces(workspaces)
+            }
+            Request::Query(Query::Windows) => {
+                let focused = self
+                    .seat
+                    .get_keyboard()
+                    .and_then(|keyboard| keyboard.current_focus())
+                    .and_then(|focus| focus.wl_surface());
+
+                let mut windows = Vec::new();
+                for (index, workspace) in self.workspaces.workspaces().into_iter().enumerate() {
+                    for window in workspace.windows() {
+                        let location = workspace
+                            .window_location(window)
+                            .unwrap_or_else(|| (0, 0).into());
+                        let size = window.geometry().size;
+                        windows.push(WindowInfo {
+                            app_id: window.app_id(),
+                            title: window.title(),
+                            workspace: index + 1,
+                            x: location.x,
+                            y: location.y,
+                            width: size.w,
+                            height: size.h,
+                            focused: focused.is_some() && window.wl_surface() == focused,
+                        });
+                    }
+                }
+                Response::Windows(windows)
+            }
+            Request::Query(Query::GetTree) => Response::Tree(self.ipc_tree()),
+            Request::Capture(capture) => match self.capture_workspace(capture) {
+                Ok(path) => Response::Capture(path),
+                Err(err) => Response::Error(err),
+            },
+            Request::Subscribe => Response::Subscribed,
+        }
+    }
+
+    /// Every window shows up under the output its center is on, or the first one it overlaps
+    fn ipc_tree(&self) -> Vec<OutputNode> {
+        let focused = self
+            .seat
+            .get_keyboard()
+            .and_then(|keyboard| keyboard.current_focus())
+            .and_then(|focus| focus.wl_surface());
+        let current = self.workspaces.current_workspace_index();
+        let workspaces = self.workspaces.workspaces();
+
+        let mut outputs: Vec<OutputNode> = self
+            .workspaces
+            .outputs()
+            .filter_map(|output| {
+                let geometry = self
+                    .workspaces
+                    .current_workspace()
+                    .output_geometry(output)?;
+                Some(OutputNode {
+                    name: output.name(),
+                    x: geometry.loc.x,
+                    y: geometry.loc.y,
+                    width: geometry.size.w,
+                    height: geometry.size.h,
+                    scale: output.current_scale().fractional_scale(),
+                    workspaces: (0..workspaces.len())
+                        .map(|index| WorkspaceNode {
+                            index: index + 1,
+                            current: index == current,
+                            windows: Vec::new(),
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+
+        for (index, workspace) in workspaces.iter().enumerate() {
+            for window in workspace.windows().rev() {
+                let Some(location) = workspace.window_location(window) else {
+                    continue;
+                };
+                let size = window.geometry().size;
+                let center = location + Point::from((size.w / 2, size.h / 2));
+                let Some(output) = workspace
+                    .space()
+                    .output_under(center.to_f64())
+                    .next()
+                    .cloned()
+                    .or_else(|| workspace.outputs_for_window(window).into_iter().next())
+                else {
+                    continue;
+                };
+                let Some(node) = outputs.iter_mut().find(|node| node.name == output.name()) else {
+                    continue;
+                };
+
+                let fullscreen = output
+                    .user_data()
+                    .get::<FullscreenSurface>()
+                    .map_or(false, |fullscreen| {
+                        fullscreen.get() == (Some(window.clone()), Some(index))
+                    });
+                node.workspaces[index].windows.push(WindowNode {
+                    app_id: window.app_id(),
+                    title: window.title(),
+                    x: location.x,
+                    y: location.y,
+                    width: size.w,
+                    height: size.h,
+                    focused: focused.is_some() && window.wl_surface() == focused,
+                    fullscreen,
+                    maximized: window.is_maximized(),
+                    sticky: window.is_sticky_all_outputs(),
+                    zen: self
+                        .zen
+                        .as_ref()
+                        .and_then(|zen| zen.window_on(&output, index))
+                        == Some(window),
+                });
+            }
+        }
+
+        outputs
+    }
+
+    fn add_ipc_connection(&mut self, client: usize, stream: UnixStream) {
+        if let Some(ipc) = self.ipc.as_mut() {
+            ipc.connections.insert(
+                client,
+                Connection {
+                    stream,
+                    outgoing: Vec::new(),
+                    subscribed: false,
+                    hung_up: false,
+                },
+            );
+        }
+    }
+
+    /// Drops the connection, its own source notices the shut down socket and removes itself
+    fn remove_ipc_connection(&mut self, client: usize) {
+        if let Some(connection) = self
+            .ipc
+            .as_mut()
+            .and_then(|ipc| ipc.connections.remove(&client))
+        {
+            let _ = connection.stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+
+    /// The client won't send anything anymore, its connection stays until what's queued for it
+    /// is written
+    fn ipc_client_hung_up(&mut self, client: usize) {
+        let Some(connection) = self
+            .ipc
+            .as_mut()
+            .and_then(|ipc| ipc.connections.get_mut(&client))
+        else {
+            return;
+        };
+        connection.hung_up = true;
+        if connection.can_be_dropped() {
+            self.remove_ipc_connection(client);
+        }
+    }
+
+    /// Queues the response for the connection and writes what the socket takes right away,
+    /// false once the connection is gone
+    fn queue_ipc_response(&mut self, client: usize, response: &Response) -> bool {
+        let Some(connection) = self
+            .ipc
+            .as_mut()
+            .and_then(|ipc| ipc.connections.get_mut(&client))
+        else {
+            return false;
+        };
+
+        connection
+            .outgoing
+            .extend(serde_json::to_vec(response).unwrap());
+        connection.outgoing.push(b'\n');
+        if let Err(err) = connection.flush() {
+            debug!(client, "Dropping IPC connection: {}", err);
+            self.remove_ipc_connection(client);
+            return false;
+        }
+        true
+    }
+
+    /// Writes what's left of the queued responses and events, once per event loop iteration
+    pub fn flush_ipc_connections(&mut self) {
+        let Some(ipc) = self.ipc.as_mut() else {
+            return;
+        };
+
+        let mut dropped = Vec::new();
+        for (client, connection) in ipc.connections.iter_mut() {
+            match connection.flush() {
+                Ok(()) if connection.can_be_dropped() => dropped.push(*client),
+                Ok(()) => {}
+                Err(err) => {
+                    debug!(client, "Dropping IPC connection: {}", err);
+                    dropped.push(*client);
+                }
+            }
+        }
+        for client in dropped {
+            self.remove_ipc_connection(client);
+        }
+    }
+
+    fn add_ipc_subscriber(&mut self, client: usize) {
+        if let Some(connection) = self
+            .ipc
+            .as_mut()
+            .and_then(|ipc| ipc.connections.get_mut(&client))
+        {
+            debug!(client, "IPC client subscribed");
+            connection.subscribed = true;
+        }
+    }
+
+    fn has_ipc_subscribers(&self) -> bool {
+        self.ipc.as_ref().map_or(false, |ipc| {
+            ipc.connections
+                .values()
+                .any(|connection| connection.subscribed)
+        })
+    }
+
+    /// Queues the event for every subscriber, the ones with too many unread events are dropped
+    pub fn send_ipc_event(&mut self, event: Event) {
+        let Some(ipc) = self.ipc.as_mut() else {
+            return;
+        };
+
+        let mut line = serde_json::to_vec(&event).unwrap();
+        line.push(b'\n');
+        let mut dropped = Vec::new();
+        for (client, connection) in ipc.connections.iter_mut() {
+            if !connection.subscribed {
+                continue;
+            }
+            connection.outgoing.extend_from_slice(&line);
+            if let Err(err) = connection.flush() {
+                debug!(client, "Dropping IPC subscriber: {}", err);
+                dropped.push(*client);
+            }
+        }
+        for client in dropped {
+            self.remove_ipc_connection(client);
+        }
+    }
+
+    /// Sends the window and focus changes since the last call, once per event loop iteration
+    pub fn send_ipc_window_events(&mut self) {
+        let changes: Vec<(usize, WindowChange)> = self
+            .workspaces
+            .workspaces_mut()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(index, workspace)| {
+                workspace
+                    .take_changes()
+                    .into_iter()
+                    .map(move |change| (index, change))
+            })
+            .collect();
+        if !self.has_ipc_subscribers() {
+            return;
+        }
+
+        for (index, change) in changes {
+            let event = match change {
+                WindowChange::Mapped(window) => {
+                    let output = self
+                        .workspaces
+                        .workspaces()
+                        .get(index)
+                        .and_then(|workspace| {
+                            workspace.outputs_for_window(&window).into_iter().next()
+                        });
+                    Event::WindowMapped(window_event(&window, output.map(|o| o.name()), index))
+                }
+                WindowChange::Unmapped(window, output) => {
+                    Event::WindowUnmapped(window_event(&window, output.map(|o| o.name()), index))
+                }
+            };
+            self.send_ipc_event(event);
+        }
+
+        let focused = self
+            .seat
+            .get_keyboard()
+            .and_then(|keyboard| keyboard.current_focus())
+            .and_then(|focus| focus.wl_surface())
+            .and_then(|surface| self.window_for_surface(&surface));
+        let focus = focused
+            .as_ref()
+            .map(|window| (window.app_id(), window.title()));
+        let changed = self.ipc.as_mut().map_or(false, |ipc| {
+            std::mem::replace(&mut ipc.last_focus, focus.clone()) != focus
+        });
+        if !changed {
+            return;
+        }
+
+        let index = self.workspaces.current_workspace_index();
+        let event = match focused {
+            Some(window) => {
+                let output = self
+                    .workspaces
+                    .current_workspace()
+                    .outputs_for_window(&window)
+                    .into_iter()
+                    .next();
+                window_event(&window, output.map(|o| o.name()), index)
+            }
+            None => WindowEvent {
+                app_id: None,
+                title: None,
+                output: self
+                    .workspaces
+                    .output_under(self.pointer.current_location())
+                    .next()
+                    .map(|o| o.name()),
+                workspace: index + 1,
+            },
+        };
+        self.send_ipc_event(Event::Focus(event));
+    }
+
+    fn capture_workspace(&mut self, capture: CaptureRequest) -> Result<PathBuf, String> {
+        let index = capture.workspace;
+        let workspace = index
+            .checked_sub(1)
+            .and_then(|index| self.workspaces.get(index))
+            .ok_or_else(|| format!("no workspace {index}"))?;
+
+        let region = match capture.output.as_deref() {
+            Some(name) => workspace
+                .outputs()
+                .find(|output| output.name() == name)
+                .and_then(|output| workspace.output_geometry(output))
+                .ok_or_else(|| format!("no output {name}"))?,
+            None => workspace
+                .outputs()
+                .filter_map(|output| workspace.output_geometry(output))
+                .reduce(|region, geometry| region.merge(geometry))
+                .ok_or("no outputs to capture")?,
+        };
+        let max_size: Size<i32, Physical> = self
+            .workspaces
+            .outputs()
+            .filter_map(|output| output.current_mode())
+            .map(|mode| mode.size)
+            .reduce(|max, size| (max.w.max(size.w), max.h.max(size.h)).into())
+            .map(|max| (max.w * MAX_CAPTURE_FACTOR, max.h * MAX_CAPTURE_FACTOR).into())
+            .unwrap_or_else(|| (region.size.w, region.size.h).into());
+        let size = capture_size(region, capture.width, capture.height, max_size);
+
+        let name = capture.name.unwrap_or_else(|| {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            format!("workspace-{index}-{timestamp}.png")
+        });
+        // only into the compositor's own directory, clients can't pick any file to overwrite
+        if name.is_empty() || name.contains('/') || name.starts_with('.') {
+            return Err(format!("invalid capture name: {name}"));
+        }
+        let path = captures_dir().join(name);
+
+        let space = workspace.space();
+        self.backend_data.capture_space(space, region, size, path)
+    }
+}
+
+fn window_event(window: &WindowElement, output: Option<String>, index: usize) -> WindowEvent {
+    WindowEvent {
+        app_id: window.app_id(),
+        title: window.title(),
+        output,
+        workspace: index + 1,
+    }
+}
+
+/// Fits the region into the requested size, a missing side follows the region's aspect ratio.
+/// The result is scaled down to fit into `max` too, and is at least one pixel on each side
+fn capture_size(
+    region: Rectangle<i32, Logical>,
+    width: Option<i32>,
+    height: Option<i32>,
+    max: Size<i32, Physical>,
+) -> Size<i32, Physical> {
+    let (w, h) = (region.size.w.max(1) as f64, region.size.h.max(1) as f64);
+    let scale = match (width, height) {
+        (Some(width), Some(height)) => f64::min(width as f64 / w, height as f64 / h),
+        (Some(width), None) => width as f64 / w,
+        (None, Some(height)) => height as f64 / h,
+        (None, None) => 1.0,
+    };
+    let scale = scale
+        .min(max.w.max(1) as f64 / w)
+        .min(max.h.max(1) as f64 / h);
+
+    Size::from((
+        ((w * scale).round() as i32).max(1),
+        ((h * scale).round() as i32).max(1),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use smithay::utils::{Physical, Rectangle, Size};
+
+    use super::capture_size;
+
+    fn max() -> Size<i32, Physical> {
+        (5120, 2880).into()
+    }
+
+    #[test]
+    fn capture_size_keeps_the_aspect_ratio() {
+        let region = Rectangle::from_loc_and_size((0, 0), (2560, 1440));
+
+        assert_eq!(
+            capture_size(region, Some(320), None, max()),
+            Size::from((320, 180))
+        );
+        assert_eq!(
+            capture_size(region, Some(320), Some(90), max()),
+            Size::from((160, 90))
+        );
+    }
+
+    #[test]
+    fn capture_size_is_clamped_to_the_maximum() {
+        let region = Rectangle::from_loc_and_size((0, 0), (2560, 1440));
+
+        assert_eq!(
+            capture_size(region, Some(1_000_000), None, max()),
+            Size::from((5120, 2880))
+        );
+        assert_eq!(
+            capture_size(region, None, Some(i32::MAX), max()),
+            Size::from((5120, 2880))
+        );
+    }
+
+    #[test]
+    fn capture_size_is_at_least_a_pixel() {
+        let region = Rectangle::from_loc_and_size((0, 0), (2560, 1440));
+
+        assert_eq!(
+            capture_size(region, Some(-20), None, max()),
+            Size::from((1, 1))
+        );
+        assert_eq!(
+            capture_size(region, Some(0), Some(0), max()),
+            Size::from((1, 1))
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use smithay::{
+    output::Output,
+    utils::{Logical, Rectangle},
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{window::WindowElement, Backend, Buddaraysh};
+
+/// A window's place in a saved layout preset
+#[derive(Debug, Clone)]
+struct PresetEntry {
+    app_id: String,
+    geometry: Rectangle<i32, Logical>,
+}
+
+impl PresetEntry {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            self.app_id,
+            self.geometry.loc.x,
+            self.geometry.loc.y,
+            self.geometry.size.w,
+            self.geometry.size.h
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let app_id = fields.next()?.to_string();
+        let mut numbers = fields.map(|f| f.parse::<i32>().ok());
+        let x = numbers.next()??;
+        let y = numbers.next()??;
+        let w = numbers.next()??;
+        let h = numbers.next()??;
+
+        Some(Self {
+            app_id,
+            geometry: Rectangle::from_loc_and_size((x, y), (w, h)),
+        })
+    }
+}
+
+fn presets_dir() -> PathBuf {
+    let home_dir = std::env::var("HOME").expect("HOME should always be set");
+    PathBuf::from(home_dir).join(".cache/buddaraysh/presets/")
+}
+
+/// Where the window positions of each output setup are kept, one file per setup
+fn output_layouts_dir() -> PathBuf {
+    let home_dir = std::env::var("HOME").expect("HOME should always be set");
+    PathBuf::from(home_dir).join(".cache/buddaraysh/output-layouts/")
+}
+
+fn preset_path(name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        return None;
+    }
+
+    Some(presets_dir().join(name))
+}
+
+impl<BackendData: Backend> Buddaraysh<BackendData> {
+    /// Saves the windows of the current workspace as the preset `name`.
+    ///
+    /// Entries are written bottom to top, so the stacking order is kept.
+    pub fn save_layout_preset(&mut self, name: &str) {
+        let Some(path) = preset_path(name) else {
+            error!(name, "Invalid layout preset name");
+            return;
+        };
+
+        let workspace = self.workspaces.current_workspace();
+        let entries = workspace
+            .windows()
+            .filter_map(|window| {
+                let app_id = window.app_id()?;
+                let loc = workspace.window_location(window)?;
+                Some(PresetEntry {
+                    app_id,
+                    geometry: Rectangle::from_loc_and_size(loc, window.geometry().size),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let write = || -> io::Result<()> {
+            fs::create_dir_all(presets_dir())?;
+            let mut file = fs::File::create(&path)?;
+            for entry in &entries {
+                writeln!(file, "{}", entry.to_line())?;
+            }
+            Ok(())
+        };
+
+        match write() {
+            Ok(()) => info!(name, windows = entries.len(), "Saved layout preset"),
+            Err(err) => error!(?err, name, "Failed to save layout preset"),
+        }
+    }
+
+    /// Moves and resizes the windows of the current workspace to match the preset `name`.
+    ///
+    /// Every entry claims the first not yet placed window with the same app id,
+    /// windows without a matching entry are left where they are.
+    pub fn load_layout_preset(&mut self, name: &str) {
+        let Some(path) = preset_path(name) else {
+            error!(name, "Invalid layout preset name");
+            return;
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!(?err, name, "Failed to read layout preset");
+                return;
+            }
+        };
+
+        let entries = contents
+            .lines()
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| {
+                let entry = PresetEntry::from_line(line);
+                if entry.is_none() {
+                    warn!(line, "Skipping malformed layout preset entry");
+                }
+                entry
+            })
+            .collect::<Vec<_>>();
+
+        let workspace = self.workspaces.current_workspace_mut();
+        let mut unplaced = workspace.windows().cloned().collect::<Vec<_>>();
+
+        // entries are bottom to top, mapping raises the window
+        for entry in entries {
+            let Some(idx) = unplaced
+                .iter()
+                .position(|w| w.app_id().as_deref() == Some(entry.app_id.as_str()))
+            else {
+                continue;
+            };
+            let window = unplaced.remove(idx);
+
+            resize_window(&window, entry.geometry);
+            workspace.map_window(window, entry.geometry.loc, false);
+        }
+
+        info!(name, "Loaded layout preset");
+    }
+}
+
+/// Names the current output setup by the outputs' names, resolutions, positions and scales,
+/// like "DP-1@2560x1440:0,0:1+eDP-1@1920x1080:2560,0:1.5", so the same monitors arranged the
+/// same way share a file
+fn output_layout_key(outputs: &[Output]) -> Option<String> {
+    let mut outputs = outputs
+        .iter()
+        .map(|output| {
+            let (w, h) = output
+                .current_mode()
+                .map(|mode| (mode.size.w, mode.size.h))
+                .unwrap_or((0, 0));
+            let location = output.current_location();
+            format!(
+                "{}@{}x{}:{},{}:{}",
+                output.name(),
+                w,
+                h,
+                location.x,
+                location.y,
+                output.current_scale().fractional_scale()
+            )
+        })
+        .collect::<Vec<_>>();
+    if outputs.is_empty() {
+        return None;
+    }
+    outputs.sort();
+
+    Some(outputs.join("+"))
+}
+
+impl<BackendData: Backend> Buddaraysh<BackendData> {
+    /// Saves where the windows of every workspace are for the current output setup,
+    /// called right before an output gets added or removed.
+    ///
+    /// Maximized windows are left out, they follow their output anyway. Nothing is written
+    /// without windows, so outputs found one after another on startup don't wipe the
+    /// positions saved for them.
+    pub fn save_output_layout(&self) {
+        let outputs = self.workspaces.outputs().cloned().collect::<Vec<_>>();
+        let Some(key) = output_layout_key(&outputs) else {
+            return;
+        };
+
+        let lines = self
+            .workspaces
+            .workspaces()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(index, workspace)| {
+                workspace
+                    .windows()
+                    .filter(|window| !window.is_maximized())
+                    .filter_map(move |window| {
+                        let app_id = window.app_id()?;
+                        let loc = workspace.window_location(window)?;
+                        let entry = PresetEntry {
+                            app_id,
+                            geometry: Rectangle::from_loc_and_size(loc, window.geometry().size),
+                        };
+                        Some(format!("{}\t{}", index, entry.to_line()))
+                    })
+            })
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            return;
+        }
+
+        let write = || -> io::Result<()> {
+            fs::create_dir_all(output_layouts_dir())?;
+            let mut file = fs::File::create(output_layouts_dir().join(&key))?;
+            for line in &lines {
+                writeln!(file, "{}", line)?;
+            }
+            Ok(())
+        };
+
+        match write() {
+            Ok(()) => debug!(key, windows = lines.len(), "Saved output layout"),
+            Err(err) => error!(?err, key, "Failed to save output layout"),
+        }
+    }
+
+    /// Puts the windows back where they were the last time this output setup was used,
+    /// called once an output got added or removed and the windows were moved onto the
+    /// remaining outputs.
+    ///
+    /// Windows are matched by app id within their workspace, like with layout presets.
+    pub fn restore_output_layout(&mut self) {
+        let outputs = self.workspaces.outputs().cloned().collect::<Vec<_>>();
+        let Some(key) = output_layout_key(&outputs) else {
+            return;
+        };
+
+        let contents = match fs::read_to_string(output_layouts_dir().join(&key)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+            Err(err) => {
+                error!(?err, key, "Failed to read output layout");
+                return;
+            }
+        };
+
+        let entries = contents
+            .lines()
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| {
+                let entry = line.split_once('\t').and_then(|(index, entry)| {
+                    Some((index.parse::<usize>().ok()?, PresetEntry::from_line(entry)?))
+                });
+                if entry.is_none() {
+                    warn!(line, "Skipping malformed output layout entry");
+                }
+                entry
+            })
+            .collect::<Vec<_>>();
+
+        let mut restored = 0;
+        for (index, workspace) in self.workspaces.workspaces_mut().into_iter().enumerate() {
+            let mut unplaced = workspace
+                .windows()
+                .filter(|window| !window.is_maximized())
+                .cloned()
+                .collect::<Vec<_>>();
+
+            // entries are bottom to top, mapping raises the window
+            for (_, entry) in entries.iter().filter(|(i, _)| *i == index) {
+                // a window is only put back onto an output, never off-screen
+                if workspace
+                    .output_under(entry.geometry.loc.to_f64())
+                    .next()
+                    .is_none()
+                {
+                    continue;
+                }
+                let Some(idx) = unplaced
+                    .iter()
+                    .position(|w| w.app_id().as_deref() == Some(entry.app_id.as_str()))
+                else {
+                    continue;
+                };
+                let window = unplaced.remove(idx);
+
+                resize_window(&window, entry.geometry);
+                workspace.map_window(window, entry.geometry.loc, false);
+                restored += 1;
+            }
+        }
+
+        if restored > 0 {
+            info!(key, windows = restored, "Restored output layout");
+        }
+    }
+}
+
+fn resize_window(window: &WindowElement, geometry: Rectangle<i32, Logical>) {
+    match window {
+        WindowElement::Wayland(w) => {
+            w.toplevel().with_pending_state(|state| {
+                state.size = Some(geometry.size);
+            });
+            w.toplevel().send_pending_configure();
+        }
+        #[cfg(feature = "xwayland")]
+        WindowElement::X11(w) => {
+            if let Err(err) = w.configure(Some(geometry)) {
+                error!(?err, "Failed to resize X11 window");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use smithay::utils::Rectangle;
+
+    use super::{output_layout_key, PresetEntry};
+    use crate::test_util::output;
+
+    #[test]
+    fn preset_entry_round_trips() {
+        let entry = PresetEntry {
+            app_id: String::from("org.gnome.Nautilus"),
+            geometry: Rectangle::from_loc_and_size((-10, 20), (800, 600)),
+        };
+
+        let parsed = PresetEntry::from_line(&entry.to_line()).unwrap();
+        assert_eq!(parsed.app_id, entry.app_id);
+        assert_eq!(parsed.geometry, entry.geometry);
+    }
+
+    #[test]
+    fn preset_entry_rejects_missing_fields() {
+        assert!(PresetEntry::from_line("kitty\t0\t0\t800").is_none());
+        assert!(PresetEntry::from_line("kitty\t0\tzero\t800\t600").is_none());
+        assert!(PresetEntry::from_line("").is_none());
+    }
+
+    #[test]
+    fn output_layout_key_is_sorted() {
+        let outputs = [
+            output("eDP-1", (1920, 1080), (2560, 0), 1.5),
+            output("DP-1", (2560, 1440), (0, 0), 1.0),
+        ];
+
+        assert_eq!(
+            output_layout_key(&outputs).as_deref(),
+            Some("DP-1@2560x1440:0,0:1+eDP-1@1920x1080:2560,0:1.5")
+        );
+    }
+
+    #[test]
+    fn output_layout_key_changes_with_position_and_scale() {
+        let key = |location, scale| {
+            output_layout_key(&[
+                output("DP-1", (2560, 1440), (0, 0), 1.0),
+                output("eDP-1", (1920, 1080), location, scale),
+            ])
+        };
+
+        assert_ne!(key((2560, 0), 1.0), key((0, 1440), 1.0));
+        assert_ne!(key((2560, 0), 1.0), key((2560, 0), 2.0));
+    }
+
+    #[test]
+    fn output_layout_key_without_outputs() {
+        assert_eq!(output_layout_key(&[]), None);
+    }
+}
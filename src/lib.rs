@@ -1,30 +1,58 @@
+mod clock;
+mod config;
 mod cursor;
 mod drawing;
 mod focus;
 mod grabs;
 mod handlers;
 mod input;
+mod ipc;
+mod layout_preset;
 mod protocols;
 mod render;
 mod shell;
 mod ssd;
 mod state;
 mod systemd;
+#[cfg(test)]
+mod test_util;
 pub mod udev;
 mod window;
 pub mod winit;
 mod workspace;
 
+use std::path::PathBuf;
+
 use smithay::{
+    desktop::Space,
     output::Output,
     reexports::wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle},
+    utils::{Logical, Physical, Rectangle, Size},
 };
 pub use state::Buddaraysh;
+use tracing_subscriber::{filter::LevelFilter, reload, EnvFilter, Registry};
+use window::WindowElement;
 
 // The button is a button code as defined in the
 // Linux kernel's linux/input-event-codes.h header file, e.g. BTN_LEFT.
 pub const BTN_LEFT: u32 = 0x110;
 pub const BTN_RIGHT: u32 = 0x111;
+pub const BTN_MIDDLE: u32 = 0x112;
+pub const BTN_SIDE: u32 = 0x113;
+pub const BTN_EXTRA: u32 = 0x114;
+pub const BTN_FORWARD: u32 = 0x115;
+pub const BTN_BACK: u32 = 0x116;
+
+/// Handle used to change the log filter at runtime
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Builds the log filter from `BUD_LOG`
+pub fn log_filter() -> EnvFilter {
+    EnvFilter::builder()
+        .with_env_var("BUD_LOG")
+        .with_default_directive(LevelFilter::ERROR.into())
+        .from_env_lossy()
+}
 
 pub struct CalloopData<BackendData: Backend + 'static> {
     state: Buddaraysh<BackendData>,
@@ -37,14 +65,137 @@ pub trait Backend {
     fn seat_name(&self) -> String;
     fn reset_buffers(&mut self, output: &Output);
     fn early_import(&mut self, surface: &WlSurface);
+    /// Renders the windows of `space` inside `region` into a png of `size` at `path`
+    fn capture_space(
+        &mut self,
+        _space: &Space<WindowElement>,
+        _region: Rectangle<i32, Logical>,
+        _size: Size<i32, Physical>,
+        _path: PathBuf,
+    ) -> Result<PathBuf, String> {
+        Err(String::from("not supported by this backend"))
+    }
+    /// Runs the actions that need this backend, like toggling outputs, and hands back the
+    /// ones it doesn't handle
+    fn process_backend_action(_state: &mut Buddaraysh<Self>, action: Action) -> Option<Action>
+    where
+        Self: Sized + 'static,
+    {
+        Some(action)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Action {
     Spawn(String),
+    /// Spawns `cmd`, putting its first window on `workspace`
+    SpawnOnWorkspace {
+        cmd: String,
+        workspace: usize,
+    },
+    /// Spawns the terminal in the working directory of the focused window's client
+    SpawnTerminalHere,
     Quit,
     SwitchToWorkspace(usize),
+    SwitchToNextWorkspace,
+    SwitchToPreviousWorkspace,
     MoveToWorkspace(usize),
+    MergeWorkspaceInto(usize),
+    ToggleFocusDim,
+    ToggleWindowPreview,
+    /// Shows or hides the clock overlay
+    ToggleClock,
+    /// Shows the focused window on every output, or only on its own again
+    ToggleStickyAllOutputs,
+    /// Shows a grid over the output and snaps moved and resized windows to it
+    ToggleGridOverlay,
+    /// Dims everything but the area around the pointer or the focused window
+    ToggleSpotlight,
+    /// Shows a notification about the most recently closed window
+    NotifyLastClosed,
+    /// Switches to the next keyboard layout and shows its name in a notification
+    NextInputSource,
+    ResetSize,
+    /// Takes the focused window out of fullscreen, maximized and sticky states
+    ResetWindowState,
+    /// Shows the focused window alone over its output without decorations or bars, or restores it
+    ToggleZen,
+    ToggleDebugLogging,
+    FocusLastOnOtherOutput,
+    /// Focuses the nearest window in that direction from the focused one, on its output
+    FocusDirection(Direction),
+    /// Moves the focused window a step in that direction
+    MoveWindowDirection(Direction),
+    ToggleFocusLock,
+    /// Keeps the pointer on the output it's on, or lets it move freely again
+    ToggleSingleOutputPointer,
+    /// Stops handling keybindings other than the one toggling this back, for nested sessions
+    TogglePassthrough,
+    /// Reads the keybinds of the config file again
+    ReloadConfig,
+    /// Fullscreens the focused window on its workspace, or restores it
+    ToggleFullscreen,
+    FocusLayerOrWindow,
+    /// Takes the named output out of the layout and blanks it, or restores it
+    ToggleOutputEnabled(String),
+    /// Writes the next frame of the output under the pointer to a png, for debugging rendering
+    DumpFrame,
+    /// Moves the windows of the output under the pointer onto the named output
+    EvacuateOutput(String),
+    /// Swaps the windows of the output under the pointer with the ones of the next output
+    SwapOutputs,
+    /// Changes the scale of the named output
+    SetOutputScale {
+        output: String,
+        scale: f64,
+    },
+    /// Saves the arrangement of the current workspace under the given name
+    SaveLayoutPreset(String),
+    /// Restores a saved arrangement on the current workspace
+    LoadLayoutPreset(String),
+    /// Brings the window with the given app id or title to the current workspace and focuses it
+    SummonWindow(String),
     None,
     Close,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Whether a `BUD_*` variable is set to `1`, `true`, `yes` or `y`
+pub fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|x| is_truthy(&x))
+}
+
+pub fn is_truthy(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "1" | "true" | "yes" | "y")
+}
+
+/// Parses a `BUD_*` variable, unset and unparsable variables are `None`
+pub fn env_parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok().and_then(|x| x.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_truthy;
+
+    #[test]
+    fn truthy_values() {
+        for value in ["1", "true", "TRUE", "yes", "Yes", "y"] {
+            assert!(is_truthy(value), "{value}");
+        }
+    }
+
+    #[test]
+    fn falsy_values() {
+        for value in ["", "0", "false", "no", "n", "on"] {
+            assert!(!is_truthy(value), "{value}");
+        }
+    }
+}
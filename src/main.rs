@@ -1,11 +1,11 @@
 use std::path::PathBuf;
 
-use buddaraysh::{udev::run_udev, winit::run_winit};
+use buddaraysh::{log_filter, udev::run_udev, winit::run_winit, LogFilterHandle};
 use tracing::Level;
-use tracing_subscriber::{filter::LevelFilter, prelude::*, EnvFilter};
+use tracing_subscriber::{prelude::*, reload};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    logging();
+    let log_filter_handle = logging();
 
     std::env::set_var("XDG_CURRENT_DESKTOP", "Buddaraysh");
 
@@ -13,15 +13,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .unwrap_or(String::from("udev"))
         .as_str()
     {
-        "winit" => run_winit()?,
-        "udev" => run_udev()?,
+        "winit" => run_winit(log_filter_handle)?,
+        "udev" => run_udev(log_filter_handle)?,
         _ => panic!("invalid backend"),
     }
 
     Ok(())
 }
 
-pub fn logging() {
+pub fn logging() -> LogFilterHandle {
     if let Err(_e) = std::env::var("BUD_LOG") {
         tracing::info!(
             "no log level specified, defaulting to debug level for buddaraysh crate only"
@@ -31,8 +31,12 @@ pub fn logging() {
 
     let journald_layer = tracing_journald::layer().expect("journald should be running");
 
-    let home_dir = std::env::var("HOME").expect("HOME should always be set");
-    let logs_dir = PathBuf::from(home_dir).join(".cache/buddaraysh/logs/");
+    let logs_dir = std::env::var("BUD_LOG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            let home_dir = std::env::var("HOME").expect("HOME should always be set");
+            PathBuf::from(home_dir).join(".cache/buddaraysh/logs/")
+        });
 
     // Log all `tracing` events to files prefixed with `debug`. Since these
     // files will be written to very frequently, roll the log file every minute.
@@ -41,16 +45,15 @@ pub fn logging() {
     // to occur less frequently, roll that file on a daily basis instead.
     let warn_file = tracing_appender::rolling::daily(&logs_dir, "warnings");
 
+    // the filter is reloadable so the log level can be raised at runtime, the debug file and
+    // stdout take whatever it lets through, trace too while verbose logging is on
+    let (filter, log_filter_handle) = reload::Layer::new(log_filter());
+
     tracing_subscriber::registry()
-        .with(
-            EnvFilter::builder()
-                .with_env_var("BUD_LOG")
-                .with_default_directive(LevelFilter::ERROR.into())
-                .from_env_lossy(),
-        )
+        .with(filter)
         .with(
             tracing_subscriber::fmt::Layer::default()
-                .with_writer(debug_file.with_max_level(Level::DEBUG))
+                .with_writer(debug_file)
                 .with_ansi(false),
         )
         .with(
@@ -58,10 +61,9 @@ pub fn logging() {
                 .with_writer(warn_file.with_max_level(Level::WARN))
                 .with_ansi(false),
         )
-        .with(
-            tracing_subscriber::fmt::Layer::default()
-                .with_writer(std::io::stdout.with_max_level(Level::DEBUG)),
-        )
+        .with(tracing_subscriber::fmt::Layer::default().with_writer(std::io::stdout))
         .with(journald_layer)
         .init();
+
+    log_filter_handle
 }
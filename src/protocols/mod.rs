@@ -1 +1,3 @@
+pub mod output_power_management;
 pub mod screencopy;
+pub mod single_pixel_buffer;
@@ -0,0 +1,214 @@
+//! wlr-output-power-management protocol.
+
+use _power::zwlr_output_power_manager_v1::{self, ZwlrOutputPowerManagerV1};
+use _power::zwlr_output_power_v1::{self, Mode, ZwlrOutputPowerV1};
+use smithay::output::Output;
+use smithay::reexports::wayland_protocols_wlr::output_power_management::v1::server as _power;
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+
+const MANAGER_VERSION: u32 = 1;
+
+pub struct OutputPowerManagementState {
+    /// the power objects and the outputs they control, only one client controls an output
+    powers: Vec<(ZwlrOutputPowerV1, Output)>,
+}
+
+impl OutputPowerManagementState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<ZwlrOutputPowerManagerV1, ()>,
+        D: Dispatch<ZwlrOutputPowerManagerV1, ()>,
+        D: Dispatch<ZwlrOutputPowerV1, OutputPowerData>,
+        D: OutputPowerHandler,
+        D: 'static,
+    {
+        display.create_global::<D, ZwlrOutputPowerManagerV1, _>(MANAGER_VERSION, ());
+
+        Self { powers: Vec::new() }
+    }
+
+    /// Tells the client controlling `output` about its new power mode
+    pub fn mode_changed(&self, output: &Output, on: bool) {
+        let mode = if on { Mode::On } else { Mode::Off };
+        for (power, _) in self.powers.iter().filter(|(_, o)| o == output) {
+            power.mode(mode);
+        }
+    }
+
+    /// Invalidates the power object of an output that went away
+    pub fn output_removed(&mut self, output: &Output) {
+        self.powers.retain(|(power, o)| {
+            if o == output {
+                power.failed();
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+pub struct OutputPowerData {
+    output: Option<Output>,
+}
+
+impl<D> GlobalDispatch<ZwlrOutputPowerManagerV1, (), D> for OutputPowerManagementState
+where
+    D: GlobalDispatch<ZwlrOutputPowerManagerV1, ()>,
+    D: Dispatch<ZwlrOutputPowerManagerV1, ()>,
+    D: Dispatch<ZwlrOutputPowerV1, OutputPowerData>,
+    D: OutputPowerHandler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        manager: New<ZwlrOutputPowerManagerV1>,
+        _manager_state: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+}
+
+impl<D> Dispatch<ZwlrOutputPowerManagerV1, (), D> for OutputPowerManagementState
+where
+    D: GlobalDispatch<ZwlrOutputPowerManagerV1, ()>,
+    D: Dispatch<ZwlrOutputPowerManagerV1, ()>,
+    D: Dispatch<ZwlrOutputPowerV1, OutputPowerData>,
+    D: OutputPowerHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _manager: &ZwlrOutputPowerManagerV1,
+        request: zwlr_output_power_manager_v1::Request,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_output_power_manager_v1::Request::GetOutputPower { id, output } => {
+                let output = Output::from_resource(&output);
+                let power = data_init.init(
+                    id,
+                    OutputPowerData {
+                        output: output.clone(),
+                    },
+                );
+
+                let Some(output) = output else {
+                    power.failed();
+                    return;
+                };
+                let taken = state
+                    .output_power_state()
+                    .powers
+                    .iter()
+                    .any(|(_, o)| *o == output);
+                if taken {
+                    power.failed();
+                    return;
+                }
+
+                power.mode(if state.output_power(&output) {
+                    Mode::On
+                } else {
+                    Mode::Off
+                });
+                state.output_power_state().powers.push((power, output));
+            }
+            zwlr_output_power_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZwlrOutputPowerV1, OutputPowerData, D> for OutputPowerManagementState
+where
+    D: Dispatch<ZwlrOutputPowerV1, OutputPowerData>,
+    D: OutputPowerHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        power: &ZwlrOutputPowerV1,
+        request: zwlr_output_power_v1::Request,
+        data: &OutputPowerData,
+        _display: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_output_power_v1::Request::SetMode { mode } => {
+                // failed power objects don't control anything
+                let controlling = state
+                    .output_power_state()
+                    .powers
+                    .iter()
+                    .any(|(p, _)| p == power);
+                let Some(output) = data.output.as_ref().filter(|_| controlling) else {
+                    return;
+                };
+
+                match mode {
+                    WEnum::Value(Mode::On) => state.set_output_power(output, true),
+                    WEnum::Value(Mode::Off) => state.set_output_power(output, false),
+                    _ => power.post_error(
+                        zwlr_output_power_v1::Error::InvalidMode,
+                        "invalid power mode",
+                    ),
+                }
+            }
+            zwlr_output_power_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        power: &ZwlrOutputPowerV1,
+        _data: &OutputPowerData,
+    ) {
+        state
+            .output_power_state()
+            .powers
+            .retain(|(p, _)| p != power);
+    }
+}
+
+/// Handler trait for wlr-output-power-management.
+pub trait OutputPowerHandler {
+    fn output_power_state(&mut self) -> &mut OutputPowerManagementState;
+
+    /// Whether the output is powered on.
+    fn output_power(&self, output: &Output) -> bool;
+
+    /// Turns the output on or off, the handler has to report the change with
+    /// [`OutputPowerManagementState::mode_changed`].
+    fn set_output_power(&mut self, output: &Output, on: bool);
+}
+
+#[allow(missing_docs)]
+#[macro_export]
+macro_rules! delegate_output_power_management {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_power_management::v1::server::zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1: ()
+        ] => $crate::protocols::output_power_management::OutputPowerManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_power_management::v1::server::zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1: ()
+        ] => $crate::protocols::output_power_management::OutputPowerManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_power_management::v1::server::zwlr_output_power_v1::ZwlrOutputPowerV1: $crate::protocols::output_power_management::OutputPowerData
+        ] => $crate::protocols::output_power_management::OutputPowerManagementState);
+    };
+}
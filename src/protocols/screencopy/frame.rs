@@ -7,7 +7,7 @@ use smithay::reexports::wayland_protocols_wlr::screencopy::v1::server::zwlr_scre
     Flags, Request, ZwlrScreencopyFrameV1,
 };
 use smithay::reexports::wayland_server::protocol::wl_buffer::WlBuffer;
-use smithay::reexports::wayland_server::{Client, DataInit, Dispatch, DisplayHandle};
+use smithay::reexports::wayland_server::{Client, DataInit, Dispatch, DisplayHandle, Resource};
 use smithay::utils::{Physical, Rectangle};
 
 use crate::protocols::screencopy::{ScreencopyHandler, ScreencopyManagerState};
@@ -77,6 +77,16 @@ impl Screencopy {
         &self.buffer
     }
 
+    /// Whether the client asked to only be sent the frame once the output got damaged.
+    pub fn with_damage(&self) -> bool {
+        self.send_damage
+    }
+
+    /// Whether the client still has the frame, it might've been destroyed while waiting.
+    pub fn is_alive(&self) -> bool {
+        self.frame.is_alive()
+    }
+
     /// Get the region which should be copied.
     pub fn region(&self) -> Rectangle<i32, Physical> {
         self.region
@@ -64,7 +64,7 @@ where
 {
     fn request(
         state: &mut D,
-        _client: &Client,
+        client: &Client,
         manager: &ZwlrScreencopyManagerV1,
         request: Request,
         _data: &(),
@@ -124,6 +124,11 @@ where
             },
         );
 
+        if !state.can_capture(client) {
+            frame.failed();
+            return;
+        }
+
         // Send desired SHM buffer parameters.
         frame.buffer(
             wl_shm::Format::Argb8888,
@@ -146,6 +151,11 @@ pub trait ScreencopyHandler {
 
     /// Handle new screencopy request.
     fn frame(&mut self, frame: Screencopy);
+
+    /// Whether the client is allowed to capture outputs, frames of clients that aren't fail right away.
+    fn can_capture(&self, _client: &Client) -> bool {
+        true
+    }
 }
 
 #[allow(missing_docs)]
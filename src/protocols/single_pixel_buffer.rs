@@ -0,0 +1,435 @@
+//! wp-single-pixel-buffer protocol.
+
+use std::cell::RefCell;
+
+use _single_pixel_buffer::wp_single_pixel_buffer_manager_v1::{
+    Request, WpSinglePixelBufferManagerV1,
+};
+use smithay::backend::renderer::element::solid::SolidColorRenderElement;
+use smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement;
+use smithay::backend::renderer::element::{Id, Kind};
+use smithay::backend::renderer::utils::{CommitCounter, RendererSurfaceStateUserData};
+use smithay::backend::renderer::{ImportAll, Renderer};
+use smithay::reexports::wayland_protocols::wp::single_pixel_buffer::v1::server as _single_pixel_buffer;
+use smithay::reexports::wayland_server::protocol::wl_buffer::{self, WlBuffer};
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+use smithay::utils::{Logical, Physical, Point, Rectangle, Scale, Size};
+use smithay::wayland::compositor::{
+    with_states, with_surface_tree_downward, BufferAssignment, SubsurfaceCachedState,
+    SurfaceAttributes, SurfaceData, TraversalAction,
+};
+use smithay::wayland::viewporter::ViewportCachedState;
+use tracing::warn;
+
+const MANAGER_VERSION: u32 = 1;
+
+pub struct SinglePixelBufferState;
+
+impl SinglePixelBufferState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<WpSinglePixelBufferManagerV1, ()>,
+        D: Dispatch<WpSinglePixelBufferManagerV1, ()>,
+        D: Dispatch<WlBuffer, SinglePixelBuffer>,
+        D: 'static,
+    {
+        display.create_global::<D, WpSinglePixelBufferManagerV1, _>(MANAGER_VERSION, ());
+
+        Self
+    }
+}
+
+/// Color of a single pixel buffer, the values are premultiplied by alpha.
+#[derive(Debug, Clone, Copy)]
+pub struct SinglePixelBuffer {
+    pub r: u32,
+    pub g: u32,
+    pub b: u32,
+    pub a: u32,
+}
+
+impl SinglePixelBuffer {
+    pub fn color(&self) -> [f32; 4] {
+        let max = u32::MAX as f64;
+        [
+            (self.r as f64 / max) as f32,
+            (self.g as f64 / max) as f32,
+            (self.b as f64 / max) as f32,
+            (self.a as f64 / max) as f32,
+        ]
+    }
+}
+
+impl<D> GlobalDispatch<WpSinglePixelBufferManagerV1, (), D> for SinglePixelBufferState
+where
+    D: GlobalDispatch<WpSinglePixelBufferManagerV1, ()>,
+    D: Dispatch<WpSinglePixelBufferManagerV1, ()>,
+    D: Dispatch<WlBuffer, SinglePixelBuffer>,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        manager: New<WpSinglePixelBufferManagerV1>,
+        _manager_state: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+}
+
+impl<D> Dispatch<WpSinglePixelBufferManagerV1, (), D> for SinglePixelBufferState
+where
+    D: GlobalDispatch<WpSinglePixelBufferManagerV1, ()>,
+    D: Dispatch<WpSinglePixelBufferManagerV1, ()>,
+    D: Dispatch<WlBuffer, SinglePixelBuffer>,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _manager: &WpSinglePixelBufferManagerV1,
+        request: Request,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            Request::CreateU32RgbaBuffer { id, r, g, b, a } => {
+                data_init.init(id, SinglePixelBuffer { r, g, b, a });
+            }
+            Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WlBuffer, SinglePixelBuffer, D> for SinglePixelBufferState
+where
+    D: Dispatch<WlBuffer, SinglePixelBuffer>,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _buffer: &WlBuffer,
+        request: wl_buffer::Request,
+        _data: &SinglePixelBuffer,
+        _display: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wl_buffer::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Single pixel buffer attached to a surface.
+///
+/// It is stored inside of WlSurface, since smithay doesn't know how to import these buffers
+#[derive(Debug, Default)]
+struct SinglePixelBufferSurfaceState {
+    color: Option<[f32; 4]>,
+    commit: CommitCounter,
+}
+
+/// Should be called on `WlSurface::commit`, before the buffer gets handed to the renderer.
+///
+/// A single pixel buffer never reaches the renderer, only its color is kept and the buffer is
+/// released right away
+pub fn handle_commit(surface: &WlSurface) {
+    with_states(surface, |states| {
+        let color = {
+            let mut attributes = states.cached_state.current::<SurfaceAttributes>();
+            let color = match attributes.buffer.as_ref() {
+                Some(BufferAssignment::NewBuffer(buffer)) => buffer
+                    .data::<SinglePixelBuffer>()
+                    .map(|single_pixel| single_pixel.color()),
+                Some(BufferAssignment::Removed) => None,
+                // the buffer didn't change
+                None => return,
+            };
+            if color.is_some() {
+                // the renderer lets go of the surface's previous buffer as if it was removed
+                if let Some(BufferAssignment::NewBuffer(buffer)) =
+                    attributes.buffer.replace(BufferAssignment::Removed)
+                {
+                    buffer.release();
+                }
+            }
+            color
+        };
+
+        states
+            .data_map
+            .insert_if_missing(RefCell::<SinglePixelBufferSurfaceState>::default);
+        let mut state = states
+            .data_map
+            .get::<RefCell<SinglePixelBufferSurfaceState>>()
+            .unwrap()
+            .borrow_mut();
+
+        if state.color.is_some() || color.is_some() {
+            state.color = color;
+            state.commit.increment();
+        }
+    });
+}
+
+/// Whether the surface's content is a single pixel buffer, the renderer doesn't know about it
+pub fn has_single_pixel_buffer(surface: &WlSurface) -> bool {
+    with_states(surface, |states| single_pixel_color(states).is_some())
+}
+
+fn single_pixel_color(states: &SurfaceData) -> Option<([f32; 4], CommitCounter)> {
+    let state = states
+        .data_map
+        .get::<RefCell<SinglePixelBufferSurfaceState>>()?
+        .borrow();
+    state.color.map(|color| (color, state.commit))
+}
+
+/// Size of the surface's single pixel buffer, smithay doesn't know it since it never sees the
+/// buffer
+fn single_pixel_size(states: &SurfaceData) -> Option<Size<i32, Logical>> {
+    single_pixel_color(states)?;
+    Some(viewport_size(states))
+}
+
+/// Without a viewport the buffer is just one pixel big
+fn viewport_size(states: &SurfaceData) -> Size<i32, Logical> {
+    states
+        .cached_state
+        .current::<ViewportCachedState>()
+        .dst
+        .unwrap_or_else(|| (1, 1).into())
+}
+
+fn subsurface_offset(states: &SurfaceData) -> Point<i32, Logical> {
+    if states.role == Some("subsurface") {
+        states
+            .cached_state
+            .current::<SubsurfaceCachedState>()
+            .location
+    } else {
+        (0, 0).into()
+    }
+}
+
+fn solid_element(
+    surface: &WlSurface,
+    states: &SurfaceData,
+    location: Point<i32, Physical>,
+    scale: Scale<f64>,
+    (color, commit): ([f32; 4], CommitCounter),
+    alpha: f32,
+    kind: Kind,
+) -> SolidColorRenderElement {
+    let size = viewport_size(states);
+
+    let mut color = color;
+    color.iter_mut().for_each(|c| *c *= alpha);
+
+    SolidColorRenderElement::new(
+        Id::from_wayland_resource(surface),
+        Rectangle::from_loc_and_size(location, size.to_physical_precise_round(scale)),
+        commit,
+        color,
+        kind,
+    )
+}
+
+/// Like smithay's `render_elements_from_surface_tree`, but surfaces with a single pixel buffer
+/// become solid color elements at their place in the tree
+pub fn render_elements_from_surface_tree<R, E>(
+    renderer: &mut R,
+    surface: &WlSurface,
+    location: impl Into<Point<i32, Physical>>,
+    scale: impl Into<Scale<f64>>,
+    alpha: f32,
+    kind: Kind,
+) -> Vec<E>
+where
+    R: Renderer + ImportAll,
+    <R as Renderer>::TextureId: 'static,
+    E: From<WaylandSurfaceRenderElement<R>> + From<SolidColorRenderElement>,
+{
+    let location = location.into();
+    let scale = scale.into();
+    let mut elements = Vec::new();
+
+    with_surface_tree_downward(
+        surface,
+        location,
+        |_, states, location| {
+            let has_buffer = states
+                .data_map
+                .get::<RendererSurfaceStateUserData>()
+                .map_or(false, |data| data.borrow().buffer().is_some());
+            // like smithay, the subsurfaces of a surface without content aren't shown
+            if !has_buffer && single_pixel_color(states).is_none() {
+                return TraversalAction::SkipChildren;
+            }
+            TraversalAction::DoChildren(
+                *location + subsurface_offset(states).to_physical_precise_round(scale),
+            )
+        },
+        |surface, states, location| {
+            let location = *location + subsurface_offset(states).to_physical_precise_round(scale);
+
+            if let Some(color) = single_pixel_color(states) {
+                elements.push(E::from(solid_element(
+                    surface, states, location, scale, color, alpha, kind,
+                )));
+                return;
+            }
+
+            match WaylandSurfaceRenderElement::from_surface(
+                renderer,
+                surface,
+                states,
+                location.to_f64(),
+                alpha,
+                kind,
+            ) {
+                Ok(Some(element)) => elements.push(E::from(element)),
+                Ok(None) => {}
+                Err(err) => warn!("Failed to import a surface: {}", err),
+            }
+        },
+        |_, _, _| true,
+    );
+
+    elements
+}
+
+/// Solid color elements for all surfaces in the tree that have a single pixel buffer attached,
+/// for surfaces smithay draws itself
+pub fn solid_elements_from_surface_tree(
+    surface: &WlSurface,
+    location: impl Into<Point<i32, Physical>>,
+    scale: impl Into<Scale<f64>>,
+    alpha: f32,
+) -> Vec<SolidColorRenderElement> {
+    let location = location.into();
+    let scale = scale.into();
+    let mut elements = Vec::new();
+
+    with_surface_tree_downward(
+        surface,
+        location,
+        |_, states, location| {
+            TraversalAction::DoChildren(
+                *location + subsurface_offset(states).to_physical_precise_round(scale),
+            )
+        },
+        |surface, states, location| {
+            let location = *location + subsurface_offset(states).to_physical_precise_round(scale);
+            if let Some(color) = single_pixel_color(states) {
+                elements.push(solid_element(
+                    surface,
+                    states,
+                    location,
+                    scale,
+                    color,
+                    alpha,
+                    Kind::Unspecified,
+                ));
+            }
+        },
+        |_, _, _| true,
+    );
+
+    elements
+}
+
+/// Bounding box of the surfaces in the tree that have a single pixel buffer attached, these are
+/// left out of smithay's bounding boxes
+pub fn bbox_from_surface_tree(
+    surface: &WlSurface,
+    location: impl Into<Point<i32, Logical>>,
+) -> Option<Rectangle<i32, Logical>> {
+    let mut bbox: Option<Rectangle<i32, Logical>> = None;
+
+    with_surface_tree_downward(
+        surface,
+        location.into(),
+        |_, states, location| TraversalAction::DoChildren(*location + subsurface_offset(states)),
+        |_, states, location| {
+            let location = *location + subsurface_offset(states);
+            if let Some(size) = single_pixel_size(states) {
+                let rect = Rectangle::from_loc_and_size(location, size);
+                bbox = Some(bbox.map_or(rect, |bbox| bbox.merge(rect)));
+            }
+        },
+        |_, _, _| true,
+    );
+
+    bbox
+}
+
+/// The topmost surface in the tree with a single pixel buffer attached that takes input at
+/// `point`, with its location, smithay's `under_from_surface_tree` never finds these
+pub fn under_from_surface_tree(
+    surface: &WlSurface,
+    point: Point<f64, Logical>,
+    location: impl Into<Point<i32, Logical>>,
+) -> Option<(WlSurface, Point<i32, Logical>)> {
+    let mut found = None;
+
+    with_surface_tree_downward(
+        surface,
+        location.into(),
+        |_, states, location| TraversalAction::DoChildren(*location + subsurface_offset(states)),
+        |surface, states, location| {
+            if found.is_some() {
+                return;
+            }
+            let location = *location + subsurface_offset(states);
+            let Some(size) = single_pixel_size(states) else {
+                return;
+            };
+
+            let point = point - location.to_f64();
+            let in_input_region = states
+                .cached_state
+                .current::<SurfaceAttributes>()
+                .input_region
+                .as_ref()
+                .map_or(true, |region| region.contains(point.to_i32_floor()));
+            if in_input_region
+                && Rectangle::from_loc_and_size((0, 0), size)
+                    .to_f64()
+                    .contains(point)
+            {
+                found = Some((surface.clone(), location));
+            }
+        },
+        |_, _, _| true,
+    );
+
+    found
+}
+
+#[allow(missing_docs)]
+#[macro_export]
+macro_rules! delegate_single_pixel_buffer_manager {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::single_pixel_buffer::v1::server::wp_single_pixel_buffer_manager_v1::WpSinglePixelBufferManagerV1: ()
+        ] => $crate::protocols::single_pixel_buffer::SinglePixelBufferState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::single_pixel_buffer::v1::server::wp_single_pixel_buffer_manager_v1::WpSinglePixelBufferManagerV1: ()
+        ] => $crate::protocols::single_pixel_buffer::SinglePixelBufferState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_server::protocol::wl_buffer::WlBuffer: $crate::protocols::single_pixel_buffer::SinglePixelBuffer
+        ] => $crate::protocols::single_pixel_buffer::SinglePixelBufferState);
+    };
+}
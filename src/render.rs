@@ -1,18 +1,39 @@
+use std::cell::RefCell;
+
 use smithay::{
     backend::renderer::{
-        element::{surface::WaylandSurfaceRenderElement, AsRenderElements, RenderElement, Wrap},
-        ImportAll, ImportMem, Renderer,
+        element::{
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            surface::WaylandSurfaceRenderElement,
+            texture::{TextureBuffer, TextureRenderElement},
+            utils::{
+                constrain_space_element, ConstrainAlign, ConstrainBehavior, ConstrainReference,
+                ConstrainScaleBehavior, CropRenderElement, RelocateRenderElement,
+                RescaleRenderElement,
+            },
+            AsRenderElements, Kind, RenderElement, Wrap,
+        },
+        ImportAll, ImportMem, Renderer, Texture,
+    },
+    desktop::{
+        layer_map_for_output,
+        space::{Space, SpaceElement, SpaceRenderElements},
     },
-    desktop::space::{Space, SpaceRenderElements},
     output::Output,
+    utils::{Logical, Point, Rectangle, Size},
+    wayland::shell::wlr_layer::Layer as WlrLayer,
 };
 
 #[cfg(feature = "debug")]
 use crate::drawing::FpsElement;
 use crate::{
-    drawing::{PointerRenderElement, CLEAR_COLOR, CLEAR_COLOR_FULLSCREEN},
+    drawing::{
+        ClockElement, PointerRenderElement, ACTIVE_OUTPUT_BORDER_WIDTH, ACTIVE_OUTPUT_COLOR,
+        CLEAR_COLOR, CLEAR_COLOR_FULLSCREEN, GRID_COLOR, GRID_LINE_WIDTH,
+    },
+    protocols::single_pixel_buffer,
     shell::FullscreenSurface,
-    window::{WindowElement, WindowRenderElement},
+    window::{sticky_copies, WindowElement, WindowRenderElement},
 };
 
 smithay::backend::renderer::element::render_elements! {
@@ -20,6 +41,9 @@ smithay::backend::renderer::element::render_elements! {
         R: ImportAll + ImportMem;
     Pointer=PointerRenderElement<R>,
     Surface=WaylandSurfaceRenderElement<R>,
+    Idle=TextureRenderElement<<R as Renderer>::TextureId>,
+    Solid=SolidColorRenderElement,
+    Clock=ClockElement<<R as Renderer>::TextureId>,
     #[cfg(feature = "debug")]
     // Note: We would like to borrow this element instead, but that would introduce
     // a feature-dependent lifetime, which introduces a lot more feature bounds
@@ -33,6 +57,9 @@ impl<R: Renderer> std::fmt::Debug for CustomRenderElements<R> {
         match self {
             Self::Pointer(arg0) => f.debug_tuple("Pointer").field(arg0).finish(),
             Self::Surface(arg0) => f.debug_tuple("Surface").field(arg0).finish(),
+            Self::Idle(arg0) => f.debug_tuple("Idle").field(arg0).finish(),
+            Self::Solid(arg0) => f.debug_tuple("Solid").field(arg0).finish(),
+            Self::Clock(arg0) => f.debug_tuple("Clock").field(arg0).finish(),
             #[cfg(feature = "debug")]
             Self::Fps(arg0) => f.debug_tuple("Fps").field(arg0).finish(),
             Self::_GenericCatcher(arg0) => f.debug_tuple("_GenericCatcher").field(arg0).finish(),
@@ -45,7 +72,7 @@ smithay::backend::renderer::element::render_elements! {
     Space=SpaceRenderElements<R, E>,
     Window=Wrap<E>,
     Custom=CustomRenderElements<R>,
-    // Preview=CropRenderElement<RelocateRenderElement<RescaleRenderElement<WindowRenderElement<R>>>>,
+    Preview=CropRenderElement<RelocateRenderElement<RescaleRenderElement<WindowRenderElement<R>>>>,
 }
 
 impl<R: Renderer + ImportAll + ImportMem, E: RenderElement<R> + std::fmt::Debug> std::fmt::Debug
@@ -56,75 +83,114 @@ impl<R: Renderer + ImportAll + ImportMem, E: RenderElement<R> + std::fmt::Debug>
             Self::Space(arg0) => f.debug_tuple("Space").field(arg0).finish(),
             Self::Window(arg0) => f.debug_tuple("Window").field(arg0).finish(),
             Self::Custom(arg0) => f.debug_tuple("Custom").field(arg0).finish(),
-            // Self::Preview(arg0) => f.debug_tuple("Preview").field(arg0).finish(),
+            Self::Preview(arg0) => f.debug_tuple("Preview").field(arg0).finish(),
             Self::_GenericCatcher(arg0) => f.debug_tuple("_GenericCatcher").field(arg0).finish(),
         }
     }
 }
 
-// pub fn space_preview_elements<'a, R, C>(
-//     renderer: &'a mut R,
-//     space: &'a Space<Window>,
-//     output: &'a Output,
-// ) -> impl Iterator<Item = C> + 'a
-// where
-//     R: Renderer + ImportAll + ImportMem,
-//     R::TextureId: Clone + 'static,
-//     C: From<CropRenderElement<RelocateRenderElement<RescaleRenderElement<WindowRenderElement<R>>>>>
-//         + 'a,
-// {
-//     let constrain_behavior = ConstrainBehavior {
-//         reference: ConstrainReference::BoundingBox,
-//         behavior: ConstrainScaleBehavior::Fit,
-//         align: ConstrainAlign::CENTER,
-//     };
-
-//     let preview_padding = 10;
-
-//     let elements_on_space = space.elements_for_output(output).count();
-//     let output_scale = output.current_scale().fractional_scale();
-//     let output_transform = output.current_transform();
-//     let output_size = output
-//         .current_mode()
-//         .map(|mode| {
-//             output_transform
-//                 .transform_size(mode.size)
-//                 .to_f64()
-//                 .to_logical(output_scale)
-//         })
-//         .unwrap_or_default();
-
-//     let max_elements_per_row = 4;
-//     let elements_per_row = usize::min(elements_on_space, max_elements_per_row);
-//     let rows = f64::ceil(elements_on_space as f64 / elements_per_row as f64);
-
-//     let preview_size = Size::from((
-//         f64::round(output_size.w / elements_per_row as f64) as i32 - preview_padding * 2,
-//         f64::round(output_size.h / rows) as i32 - preview_padding * 2,
-//     ));
-
-//     space
-//         .elements_for_output(output)
-//         .enumerate()
-//         .flat_map(move |(element_index, window)| {
-//             let column = element_index % elements_per_row;
-//             let row = element_index / elements_per_row;
-//             let preview_location = Point::from((
-//                 preview_padding + (preview_padding + preview_size.w) * column as i32,
-//                 preview_padding + (preview_padding + preview_size.h) * row as i32,
-//             ));
-//             let constrain = Rectangle::from_loc_and_size(preview_location, preview_size);
-//             constrain_space_element(
-//                 renderer,
-//                 window,
-//                 preview_location,
-//                 1.0,
-//                 output_scale,
-//                 constrain,
-//                 constrain_behavior,
-//             )
-//         })
-// }
+/// Scaled down previews of the windows on the output, laid out in a grid
+pub fn space_preview_elements<'a, R, C>(
+    renderer: &'a mut R,
+    space: &'a Space<WindowElement>,
+    output: &'a Output,
+) -> impl Iterator<Item = C> + 'a
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Clone + 'static,
+    C: From<CropRenderElement<RelocateRenderElement<RescaleRenderElement<WindowRenderElement<R>>>>>
+        + 'a,
+{
+    let constrain_behavior = ConstrainBehavior {
+        reference: ConstrainReference::BoundingBox,
+        behavior: ConstrainScaleBehavior::Fit,
+        align: ConstrainAlign::CENTER,
+    };
+
+    let preview_padding = 10;
+
+    let elements_on_space = space.elements_for_output(output).count();
+    let output_scale = output.current_scale().fractional_scale();
+    let output_transform = output.current_transform();
+    let output_size = output
+        .current_mode()
+        .map(|mode| {
+            output_transform
+                .transform_size(mode.size)
+                .to_f64()
+                .to_logical(output_scale)
+        })
+        .unwrap_or_default();
+
+    let max_elements_per_row = 4;
+    let elements_per_row = usize::min(elements_on_space, max_elements_per_row);
+    let rows = f64::ceil(elements_on_space as f64 / elements_per_row as f64);
+
+    let preview_size = Size::from((
+        f64::round(output_size.w / elements_per_row as f64) as i32 - preview_padding * 2,
+        f64::round(output_size.h / rows) as i32 - preview_padding * 2,
+    ));
+
+    space
+        .elements_for_output(output)
+        .enumerate()
+        .flat_map(move |(element_index, window)| {
+            let column = element_index % elements_per_row;
+            let row = element_index / elements_per_row;
+            let preview_location = Point::from((
+                preview_padding + (preview_padding + preview_size.w) * column as i32,
+                preview_padding + (preview_padding + preview_size.h) * row as i32,
+            ));
+            let constrain = Rectangle::from_loc_and_size(preview_location, preview_size);
+            constrain_space_element(
+                renderer,
+                window,
+                preview_location,
+                1.0,
+                output_scale,
+                constrain,
+                constrain_behavior,
+            )
+        })
+}
+
+/// The windows of a workspace inside `region`, scaled by `scale` with `region`'s corner at the
+/// origin, whether or not the workspace is shown anywhere
+pub fn workspace_capture_elements<R>(
+    renderer: &mut R,
+    space: &Space<WindowElement>,
+    region: Rectangle<i32, Logical>,
+    scale: f64,
+) -> Vec<WindowRenderElement<R>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Clone + 'static,
+{
+    // the space lists its windows bottom to top, render elements go top to bottom
+    space
+        .elements()
+        .rev()
+        .filter(|window| {
+            space
+                .element_bbox(window)
+                .map_or(false, |bbox| bbox.overlaps(region))
+        })
+        .flat_map(|window| {
+            let location = space
+                .element_location(window)
+                .unwrap_or_else(|| (0, 0).into())
+                - window.geometry().loc
+                - region.loc;
+            AsRenderElements::<R>::render_elements::<WindowRenderElement<R>>(
+                window,
+                renderer,
+                location.to_physical_precise_round(scale),
+                scale.into(),
+                1.0,
+            )
+        })
+        .collect()
+}
 
 #[profiling::function]
 pub fn output_elements<R>(
@@ -133,7 +199,12 @@ pub fn output_elements<R>(
     custom_elements: impl IntoIterator<Item = CustomRenderElements<R>>,
     renderer: &mut R,
     current_workspace_index: usize,
-    // show_window_preview: bool,
+    idle_texture: Option<&TextureBuffer<R::TextureId>>,
+    active_output_indicator: bool,
+    show_window_preview: bool,
+    grid_size: Option<i32>,
+    zen_window: Option<&WindowElement>,
+    spotlight: Option<(Rectangle<i32, Logical>, f32)>,
 ) -> (
     Vec<OutputRenderElements<R, WindowRenderElement<R>>>,
     [f32; 4],
@@ -142,18 +213,11 @@ where
     R: Renderer + ImportAll + ImportMem,
     R::TextureId: Clone + 'static,
 {
-    if let Some(window) =
-        output
-            .user_data()
-            .get::<FullscreenSurface>()
-            .and_then(|f| match f.get() {
-                (Some(window), Some(workspace_index))
-                    if workspace_index == current_workspace_index =>
-                {
-                    Some(window)
-                }
-                _ => None,
-            })
+    if let Some(window) = output
+        .user_data()
+        .get::<FullscreenSurface>()
+        .and_then(|f| f.visible_on(current_workspace_index))
+        .or_else(|| zen_window.cloned())
     {
         let scale = output.current_scale().fractional_scale().into();
         let window_render_elements: Vec<WindowRenderElement<R>> =
@@ -175,9 +239,108 @@ where
             .map(OutputRenderElements::from)
             .collect::<Vec<_>>();
 
-        // if show_window_preview && space.elements_for_output(output).count() > 0 {
-        //     output_render_elements.extend(space_preview_elements(renderer, space, output));
-        // }
+        if let Some(grid_size) = grid_size {
+            output_render_elements.extend(
+                grid_elements(output, space, grid_size)
+                    .into_iter()
+                    .map(|e| OutputRenderElements::Custom(CustomRenderElements::Solid(e))),
+            );
+        }
+
+        if let Some((area, alpha)) = spotlight {
+            output_render_elements.extend(
+                spotlight_elements(output, space, area, alpha)
+                    .into_iter()
+                    .map(|e| OutputRenderElements::Custom(CustomRenderElements::Solid(e))),
+            );
+        }
+
+        if active_output_indicator {
+            output_render_elements.extend(
+                active_output_elements(output, space)
+                    .into_iter()
+                    .map(|e| OutputRenderElements::Custom(CustomRenderElements::Solid(e))),
+            );
+        }
+
+        // show the idle image in the middle of the output when there's nothing else to show
+        if let Some(texture) = idle_texture.filter(|_| space.elements().next().is_none()) {
+            if let Some(output_geometry) = space.output_geometry(output) {
+                let scale = output.current_scale().fractional_scale();
+                let texture_size = texture.texture().size();
+                let texture_size: Size<i32, Logical> = (texture_size.w, texture_size.h).into();
+                let location: Point<i32, Logical> = (
+                    (output_geometry.size.w - texture_size.w) / 2,
+                    (output_geometry.size.h - texture_size.h) / 2,
+                )
+                    .into();
+
+                output_render_elements.push(OutputRenderElements::Custom(
+                    CustomRenderElements::Idle(TextureRenderElement::from_texture_buffer(
+                        location.to_f64().to_physical(scale),
+                        texture,
+                        None,
+                        None,
+                        None,
+                        Kind::Unspecified,
+                    )),
+                ));
+            }
+        }
+
+        if show_window_preview && space.elements_for_output(output).count() > 0 {
+            output_render_elements.extend(space_preview_elements(renderer, space, output));
+        }
+
+        // smithay can't import single pixel buffers, so layer surfaces using them are drawn here,
+        // above the windows for the top and overlay layers and below them for the rest
+        let mut lower_layer_elements = Vec::new();
+        {
+            let scale = output.current_scale().fractional_scale();
+            let map = layer_map_for_output(output);
+            for layer in map.layers().rev() {
+                let Some(geometry) = map.layer_geometry(layer) else {
+                    continue;
+                };
+                let elements = single_pixel_buffer::solid_elements_from_surface_tree(
+                    layer.wl_surface(),
+                    geometry.loc.to_physical_precise_round(scale),
+                    scale,
+                    1.0,
+                )
+                .into_iter()
+                .map(|e| OutputRenderElements::Custom(CustomRenderElements::Solid(e)));
+
+                match layer.layer() {
+                    WlrLayer::Top | WlrLayer::Overlay => output_render_elements.extend(elements),
+                    WlrLayer::Bottom | WlrLayer::Background => {
+                        lower_layer_elements.extend(elements)
+                    }
+                }
+            }
+        }
+
+        // sticky windows living on another output get drawn here as well
+        {
+            let scale = output.current_scale().fractional_scale();
+            for (window, location) in sticky_copies(space, output) {
+                let render_location =
+                    (location - window.geometry().loc).to_physical_precise_round(scale);
+                let window_render_elements: Vec<WindowRenderElement<R>> =
+                    AsRenderElements::<R>::render_elements(
+                        window,
+                        renderer,
+                        render_location,
+                        scale.into(),
+                        1.0,
+                    );
+                output_render_elements.extend(
+                    window_render_elements
+                        .into_iter()
+                        .map(|e| OutputRenderElements::Window(Wrap::from(e))),
+                );
+            }
+        }
 
         let space_elements = smithay::desktop::space::space_render_elements::<_, WindowElement, _>(
             renderer,
@@ -187,6 +350,7 @@ where
         )
         .expect("output without mode?");
         output_render_elements.extend(space_elements.into_iter().map(OutputRenderElements::Space));
+        output_render_elements.extend(lower_layer_elements);
 
         (output_render_elements, CLEAR_COLOR)
     }
@@ -208,3 +372,169 @@ where
 //     let (elements, clear_color) = output_elements(output, space, custom_elements, renderer);
 //     damage_tracker.render_output(renderer, age, &elements, clear_color)
 // }
+
+/// Border buffers of the active output indicator, stored in the output's user data
+#[derive(Default)]
+struct ActiveOutputIndicator(RefCell<[SolidColorBuffer; 4]>);
+
+/// A border around the edges of the output
+fn active_output_elements(
+    output: &Output,
+    space: &Space<WindowElement>,
+) -> Vec<SolidColorRenderElement> {
+    let Some(output_geometry) = space.output_geometry(output) else {
+        return Vec::new();
+    };
+    let scale = output.current_scale().fractional_scale();
+    let (w, h) = (output_geometry.size.w, output_geometry.size.h);
+    let border = ACTIVE_OUTPUT_BORDER_WIDTH;
+
+    // top, bottom, left, right
+    let edges: [(Point<i32, Logical>, Size<i32, Logical>); 4] = [
+        ((0, 0).into(), (w, border).into()),
+        ((0, h - border).into(), (w, border).into()),
+        ((0, 0).into(), (border, h).into()),
+        ((w - border, 0).into(), (border, h).into()),
+    ];
+
+    output
+        .user_data()
+        .insert_if_missing(ActiveOutputIndicator::default);
+    let mut buffers = output
+        .user_data()
+        .get::<ActiveOutputIndicator>()
+        .unwrap()
+        .0
+        .borrow_mut();
+
+    buffers
+        .iter_mut()
+        .zip(edges)
+        .map(|(buffer, (location, size))| {
+            buffer.update(size, ACTIVE_OUTPUT_COLOR);
+            SolidColorRenderElement::from_buffer(
+                buffer,
+                location.to_physical_precise_round(scale),
+                scale,
+                1.0,
+                Kind::Unspecified,
+            )
+        })
+        .collect()
+}
+
+/// Buffers dimming the output around the spotlight, stored in the output's user data
+#[derive(Default)]
+struct SpotlightOverlay(RefCell<[SolidColorBuffer; 4]>);
+
+/// Dims the output everywhere except `area`, which is in global coordinates
+fn spotlight_elements(
+    output: &Output,
+    space: &Space<WindowElement>,
+    area: Rectangle<i32, Logical>,
+    alpha: f32,
+) -> Vec<SolidColorRenderElement> {
+    let Some(output_geometry) = space.output_geometry(output) else {
+        return Vec::new();
+    };
+    let scale = output.current_scale().fractional_scale();
+    let (w, h) = (output_geometry.size.w, output_geometry.size.h);
+
+    // an area off this output leaves nothing lit
+    let lit = area
+        .intersection(output_geometry)
+        .map(|mut lit| {
+            lit.loc -= output_geometry.loc;
+            lit
+        })
+        .unwrap_or_else(|| Rectangle::from_loc_and_size((0, 0), (0, 0)));
+    let (x, y) = (lit.loc.x, lit.loc.y);
+    let (right, bottom) = (x + lit.size.w, y + lit.size.h);
+
+    // above, below, left and right of the lit area
+    let parts: [(Point<i32, Logical>, Size<i32, Logical>); 4] = [
+        ((0, 0).into(), (w, y).into()),
+        ((0, bottom).into(), (w, h - bottom).into()),
+        ((0, y).into(), (x, lit.size.h).into()),
+        ((right, y).into(), (w - right, lit.size.h).into()),
+    ];
+
+    output
+        .user_data()
+        .insert_if_missing(SpotlightOverlay::default);
+    let mut buffers = output
+        .user_data()
+        .get::<SpotlightOverlay>()
+        .unwrap()
+        .0
+        .borrow_mut();
+
+    buffers
+        .iter_mut()
+        .zip(parts)
+        .filter(|(_, (_, size))| size.w > 0 && size.h > 0)
+        .map(|(buffer, (location, size))| {
+            buffer.update(size, [0.0, 0.0, 0.0, alpha]);
+            SolidColorRenderElement::from_buffer(
+                buffer,
+                location.to_physical_precise_round(scale),
+                scale,
+                1.0,
+                Kind::Unspecified,
+            )
+        })
+        .collect()
+}
+
+/// Line buffers of the grid overlay, stored in the output's user data
+#[derive(Default)]
+struct GridOverlay(RefCell<Vec<SolidColorBuffer>>);
+
+/// Vertical and horizontal lines every `grid_size` logical pixels
+fn grid_elements(
+    output: &Output,
+    space: &Space<WindowElement>,
+    grid_size: i32,
+) -> Vec<SolidColorRenderElement> {
+    let Some(output_geometry) = space.output_geometry(output) else {
+        return Vec::new();
+    };
+    let scale = output.current_scale().fractional_scale();
+    let (w, h) = (output_geometry.size.w, output_geometry.size.h);
+
+    let vertical = (grid_size..w).step_by(grid_size as usize).map(
+        |x| -> (Point<i32, Logical>, Size<i32, Logical>) {
+            ((x, 0).into(), (GRID_LINE_WIDTH, h).into())
+        },
+    );
+    let horizontal = (grid_size..h).step_by(grid_size as usize).map(
+        |y| -> (Point<i32, Logical>, Size<i32, Logical>) {
+            ((0, y).into(), (w, GRID_LINE_WIDTH).into())
+        },
+    );
+    let lines = vertical.chain(horizontal).collect::<Vec<_>>();
+
+    output.user_data().insert_if_missing(GridOverlay::default);
+    let mut buffers = output
+        .user_data()
+        .get::<GridOverlay>()
+        .unwrap()
+        .0
+        .borrow_mut();
+    buffers.resize_with(lines.len(), SolidColorBuffer::default);
+
+    buffers
+        .iter_mut()
+        .zip(lines)
+        .map(|(buffer, (location, size))| {
+            buffer.update(size, GRID_COLOR);
+            SolidColorRenderElement::from_buffer(
+                buffer,
+                location.to_physical_precise_round(scale),
+                scale,
+                1.0,
+                Kind::Unspecified,
+            )
+        })
+        .collect()
+}
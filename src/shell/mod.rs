@@ -1,19 +1,116 @@
 use std::cell::RefCell;
 
 use smithay::{
-    desktop::{layer_map_for_output, Space},
-    output::Output,
-    reexports::wayland_server::protocol::{wl_output::WlOutput, wl_surface::WlSurface},
-    utils::{Logical, Point, Rectangle},
+    desktop::{layer_map_for_output, space::SpaceElement, Space},
+    input::pointer::MotionEvent,
+    output::{Output, Scale as OutputScale},
+    reexports::{
+        wayland_protocols::xdg::shell::server::xdg_toplevel,
+        wayland_server::protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    },
+    utils::{IsAlive, Logical, Point, Rectangle, Size, SERIAL_COUNTER},
+    wayland::seat::WaylandFocus,
 };
 
-use crate::window::WindowElement;
+use tracing::debug;
+
+use crate::{window::WindowElement, workspace::Workspace, Backend, Buddaraysh, Direction};
 
 mod x11;
 pub mod xdg;
 
+/// Geometry of a window before it got maximized
+#[derive(Debug, Default)]
+struct OldGeometry(RefCell<Option<Rectangle<i32, Logical>>>);
+impl OldGeometry {
+    pub fn save(&self, geo: Rectangle<i32, Logical>) {
+        *self.0.borrow_mut() = Some(geo);
+    }
+
+    pub fn restore(&self) -> Option<Rectangle<i32, Logical>> {
+        self.0.borrow_mut().take()
+    }
+}
+
+/// Location for a maximized window that's being dragged and shrunk back to `new_size`,
+/// so the pointer stays over the same relative spot of the window
+fn unmaximized_window_location(
+    pointer_location: Point<f64, Logical>,
+    maximized_geometry: Rectangle<i32, Logical>,
+    new_size: Size<i32, Logical>,
+) -> Point<i32, Logical> {
+    let relative_x = if maximized_geometry.size.w > 0 {
+        (pointer_location.x - maximized_geometry.loc.x as f64) / maximized_geometry.size.w as f64
+    } else {
+        0.0
+    };
+    // keep the pointer the same distance from the top, so it stays on the titlebar
+    let offset_y =
+        (pointer_location.y - maximized_geometry.loc.y as f64).clamp(0.0, new_size.h.max(0) as f64);
+
+    (
+        (pointer_location.x - relative_x * new_size.w as f64) as i32,
+        (pointer_location.y - offset_y) as i32,
+    )
+        .into()
+}
+
+/// Where a window at `location` on an output at `source` that's going away ends up on the
+/// output at `target`, keeping the offset into the output as far as the window of `size`
+/// fits on the new one. A window bigger than `target` gets its top left corner on it
+fn evacuated_location(
+    location: Point<i32, Logical>,
+    size: Size<i32, Logical>,
+    source: Rectangle<i32, Logical>,
+    target: Rectangle<i32, Logical>,
+) -> Point<i32, Logical> {
+    let location = location - source.loc + target.loc;
+    let max_x = (target.loc.x + target.size.w - size.w).max(target.loc.x);
+    let max_y = (target.loc.y + target.size.h - size.h).max(target.loc.y);
+    (
+        location.x.clamp(target.loc.x, max_x),
+        location.y.clamp(target.loc.y, max_y),
+    )
+        .into()
+}
+
+/// Where a window with `geometry` on an output at `source` that's going away ends up on the
+/// output at `target`. A maximized window goes back to `old_geometry`, what it had before it
+/// got maximized, if that's known
+fn evacuated_geometry(
+    geometry: Rectangle<i32, Logical>,
+    old_geometry: Option<Rectangle<i32, Logical>>,
+    source: Rectangle<i32, Logical>,
+    target: Rectangle<i32, Logical>,
+) -> Rectangle<i32, Logical> {
+    let geometry = old_geometry.unwrap_or(geometry);
+    Rectangle::from_loc_and_size(
+        evacuated_location(geometry.loc, geometry.size, source, target),
+        geometry.size,
+    )
+}
+
+/// Pairs `windows` with their transients. Windows that are transients of another one in the
+/// list are left out, they get moved along with their parent
+fn with_transients(
+    workspace: &Workspace,
+    windows: Vec<(WindowElement, Point<i32, Logical>)>,
+) -> Vec<(WindowElement, Point<i32, Logical>, Vec<WindowElement>)> {
+    let transients: Vec<Vec<WindowElement>> = windows
+        .iter()
+        .map(|(window, _)| workspace.transients_of(window))
+        .collect();
+
+    windows
+        .iter()
+        .zip(&transients)
+        .filter(|((window, _), _)| !transients.iter().flatten().any(|t| t == window))
+        .map(|((window, location), transients)| (window.clone(), *location, transients.clone()))
+        .collect()
+}
+
 fn place_new_window(
-    space: &mut Space<WindowElement>,
+    workspace: &mut Workspace,
     pointer_location: Point<f64, Logical>,
     window: &WindowElement,
     activate: bool,
@@ -22,6 +119,7 @@ fn place_new_window(
     // or if there is not output in a [0;800]x[0;800] square
     use rand::distributions::{Distribution, Uniform};
 
+    let space = workspace.space();
     let output = space
         .output_under(pointer_location)
         .next()
@@ -52,7 +150,7 @@ fn place_new_window(
     let x = x_range.sample(&mut rng);
     let y = y_range.sample(&mut rng);
 
-    space.map_element(window.clone(), (x, y), activate);
+    workspace.map_window(window.clone(), (x, y), activate);
 }
 
 fn fullscreen_output_geometry(
@@ -79,23 +177,1239 @@ fn fullscreen_output_geometry(
         .and_then(|o| space.output_geometry(&o))
 }
 
-#[derive(Default)]
-pub struct FullscreenSurface {
-    window: RefCell<Option<WindowElement>>,
-    workspace_index: RefCell<Option<usize>>,
+/// Where a maximized window goes on `output`, the part of it layer-shell surfaces like bars
+/// don't reserve
+fn maximized_geometry(workspace: &Workspace, output: &Output) -> Option<Rectangle<i32, Logical>> {
+    let geometry = workspace.output_geometry(output)?;
+    let zone = layer_map_for_output(output).non_exclusive_zone();
+    Some(Rectangle::from_loc_and_size(
+        geometry.loc + zone.loc,
+        zone.size,
+    ))
 }
 
+#[derive(Default)]
+pub struct FullscreenSurface(RefCell<FullscreenState<WindowElement>>);
+
 impl FullscreenSurface {
     pub fn set(&self, window: WindowElement, workspace_index: usize) {
-        *self.window.borrow_mut() = Some(window);
-        *self.workspace_index.borrow_mut() = Some(workspace_index);
+        self.0.borrow_mut().set(window, workspace_index);
     }
 
     pub fn get(&self) -> (Option<WindowElement>, Option<usize>) {
-        (self.window.borrow().clone(), *self.workspace_index.borrow())
+        let state = self.0.borrow();
+        (state.window.clone(), state.workspace_index)
     }
 
     pub fn clear(&self) -> (Option<WindowElement>, Option<usize>) {
-        (self.window.borrow_mut().take(), self.workspace_index.take())
+        self.0.borrow_mut().clear()
+    }
+
+    /// The window to show fullscreen while the workspace at `current_workspace_index` is active
+    pub fn visible_on(&self, current_workspace_index: usize) -> Option<WindowElement> {
+        self.0.borrow().visible_on(current_workspace_index)
+    }
+
+    /// Keeps `window` fullscreen, if it is, after it got moved to the workspace at
+    /// `workspace_index`
+    pub fn window_moved(&self, window: &WindowElement, workspace_index: usize) {
+        self.0.borrow_mut().window_moved(window, workspace_index);
+    }
+}
+
+/// The bookkeeping behind [`FullscreenSurface`], generic over the window so it can be tested
+/// without a client
+struct FullscreenState<W> {
+    window: Option<W>,
+    workspace_index: Option<usize>,
+}
+
+impl<W> Default for FullscreenState<W> {
+    fn default() -> Self {
+        Self {
+            window: None,
+            workspace_index: None,
+        }
+    }
+}
+
+impl<W: Clone + PartialEq> FullscreenState<W> {
+    fn set(&mut self, window: W, workspace_index: usize) {
+        self.window = Some(window);
+        self.workspace_index = Some(workspace_index);
+    }
+
+    fn clear(&mut self) -> (Option<W>, Option<usize>) {
+        (self.window.take(), self.workspace_index.take())
+    }
+
+    fn visible_on(&self, current_workspace_index: usize) -> Option<W> {
+        match (&self.window, self.workspace_index) {
+            (Some(window), Some(index)) if index == current_workspace_index => Some(window.clone()),
+            _ => None,
+        }
+    }
+
+    fn window_moved(&mut self, window: &W, workspace_index: usize) {
+        if self.window.as_ref() == Some(window) {
+            self.workspace_index = Some(workspace_index);
+        }
+    }
+}
+
+/// A toplevel that unmapped itself by committing a null buffer, without being destroyed
+pub struct UnmappedWindow {
+    window: WindowElement,
+    workspace_index: usize,
+    location: Point<i32, Logical>,
+    state: RemapState,
+}
+
+/// How far an unmapped toplevel got towards being mapped again. Unmapping resets an xdg
+/// toplevel, so like a new one it commits without a buffer first and waits for an initial
+/// configure before it attaches one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RemapState {
+    Unmapped,
+    Configured,
+}
+
+/// What a commit of an unmapped toplevel calls for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RemapStep {
+    /// send the initial configure again
+    Configure,
+    /// map it again where it was
+    Map,
+    /// nothing until it attaches a buffer
+    Wait,
+}
+
+impl RemapState {
+    fn on_commit(&mut self, has_buffer: bool) -> RemapStep {
+        match (*self, has_buffer) {
+            (_, true) => RemapStep::Map,
+            (RemapState::Unmapped, false) => {
+                *self = RemapState::Configured;
+                RemapStep::Configure
+            }
+            (RemapState::Configured, false) => RemapStep::Wait,
+        }
+    }
+}
+
+/// The window shown alone over its output while zen mode is on
+pub struct ZenWindow {
+    window: WindowElement,
+    output: Output,
+    workspace_index: usize,
+    /// where the window was and how big it was before zen mode
+    old_geometry: Rectangle<i32, Logical>,
+    was_ssd: bool,
+}
+
+impl ZenWindow {
+    /// The zen window, if it's shown on `output` right now
+    pub fn window_on(&self, output: &Output, workspace_index: usize) -> Option<&WindowElement> {
+        (&self.output == output && self.workspace_index == workspace_index && self.window.alive())
+            .then_some(&self.window)
+    }
+}
+
+impl<BackendData: Backend> Buddaraysh<BackendData> {
+    /// Unfullscreens the window that's fullscreen on `output`, unless it's `window`.
+    ///
+    /// An output only remembers one fullscreen window, so without this a window fullscreened
+    /// on another workspace would keep its fullscreen state without being shown as such.
+    pub fn release_fullscreen(&self, output: &Output, window: &WindowElement) {
+        let Some(fullscreen) = output.user_data().get::<FullscreenSurface>() else {
+            return;
+        };
+        let (Some(previous), workspace_index) = fullscreen.get() else {
+            return;
+        };
+        if &previous == window {
+            return;
+        }
+
+        fullscreen.clear();
+        unfullscreen(&previous);
+
+        #[cfg(feature = "xwayland")]
+        if let WindowElement::X11(ref w) = previous {
+            let bbox = workspace_index
+                .and_then(|index| self.workspaces.workspaces().get(index).copied())
+                .and_then(|workspace| workspace.window_bbox(&previous));
+            if let Err(err) = w.configure(bbox) {
+                tracing::error!(?err, "Failed to configure X11 window");
+            }
+        }
+    }
+
+    /// Puts a window that just left fullscreen back on the workspace it was fullscreened on,
+    /// it only gets the keyboard focus if that workspace is the one being shown
+    pub fn restore_unfullscreened(
+        &mut self,
+        window: &WindowElement,
+        workspace_index: Option<usize>,
+    ) {
+        let Some(workspace_index) = workspace_index else {
+            return;
+        };
+
+        let current_index = self.workspaces.workspace_index_of(window);
+        if current_index != Some(workspace_index) {
+            let location = current_index
+                .and_then(|index| self.workspaces.workspaces().get(index).copied())
+                .and_then(|workspace| workspace.window_location(window))
+                .unwrap_or_default();
+            let Some(workspace) = self.workspaces.get_mut(workspace_index) else {
+                return;
+            };
+            workspace.map_window(window.clone(), location, false);
+
+            if let Some(workspace) = current_index.and_then(|index| self.workspaces.get_mut(index))
+            {
+                workspace.unmap_window(window);
+            }
+        }
+
+        let keyboard = self.seat.get_keyboard().unwrap();
+        let serial = SERIAL_COUNTER.next_serial();
+        if workspace_index == self.workspaces.current_workspace_index() {
+            keyboard.set_focus(self, Some(window.clone().into()), serial);
+        } else if keyboard
+            .current_focus()
+            .and_then(|focus| focus.wl_surface())
+            .is_some_and(|surface| window.wl_surface().as_ref() == Some(&surface))
+        {
+            // the window isn't shown anymore, so it can't keep the focus
+            keyboard.set_focus(self, None, serial);
+        }
+    }
+
+    /// Takes a toplevel that committed a null buffer out of its workspace, it's kept around
+    /// to come back at the same spot once it attaches a buffer again
+    pub fn unmap_toplevel(&mut self, window: &WindowElement) {
+        let Some(workspace_index) = self.workspaces.workspace_index_of(window) else {
+            return;
+        };
+        let Some(workspace) = self.workspaces.get_mut(workspace_index) else {
+            return;
+        };
+        let location = workspace.window_location(window).unwrap_or_default();
+        workspace.unmap_window(window);
+
+        // unmapping resets the toplevel's state, fullscreen included
+        for output in self.workspaces.outputs() {
+            if let Some(fullscreen) = output.user_data().get::<FullscreenSurface>() {
+                if fullscreen.get().0.as_ref() == Some(window) {
+                    fullscreen.clear();
+                }
+            }
+        }
+
+        let keyboard = self.seat.get_keyboard().unwrap();
+        if keyboard
+            .current_focus()
+            .and_then(|focus| focus.wl_surface())
+            .is_some_and(|surface| window.wl_surface().as_ref() == Some(&surface))
+        {
+            keyboard.set_focus(self, None, SERIAL_COUNTER.next_serial());
+        }
+
+        debug!(?window, "Toplevel unmapped");
+        self.unmapped_windows.push(UnmappedWindow {
+            window: window.clone(),
+            workspace_index,
+            location,
+            state: RemapState::Unmapped,
+        });
+    }
+
+    /// Handles a commit of a toplevel that unmapped itself, the first one without a buffer gets
+    /// the initial configure sent again and the first one with a buffer maps it again
+    pub fn commit_unmapped(&mut self, surface: &WlSurface, has_buffer: bool) {
+        let Some(unmapped) = self
+            .unmapped_windows
+            .iter_mut()
+            .find(|unmapped| unmapped.window.wl_surface().as_ref() == Some(surface))
+        else {
+            return;
+        };
+
+        match unmapped.state.on_commit(has_buffer) {
+            RemapStep::Configure => {
+                let WindowElement::Wayland(ref w) = unmapped.window else {
+                    return;
+                };
+                let toplevel = w.toplevel().clone();
+                // start over like a new toplevel, what it asks for now gets applied by the
+                // initial configure
+                toplevel.with_pending_state(|state| {
+                    state.states.unset(xdg_toplevel::State::Maximized);
+                    state.states.unset(xdg_toplevel::State::Fullscreen);
+                    state.states.unset(xdg_toplevel::State::Activated);
+                    state.states.unset(xdg_toplevel::State::Resizing);
+                    state.size = None;
+                    state.fullscreen_output = None;
+                });
+                debug!(window = ?unmapped.window, "Configuring unmapped toplevel again");
+                self.initial_configure_toplevel(&toplevel);
+            }
+            RemapStep::Map => self.remap_toplevel(surface),
+            RemapStep::Wait => {}
+        }
+    }
+
+    /// Maps an unmapped toplevel again where it was, after it attached a new buffer
+    fn remap_toplevel(&mut self, surface: &WlSurface) {
+        let Some(index) = self
+            .unmapped_windows
+            .iter()
+            .position(|unmapped| unmapped.window.wl_surface().as_ref() == Some(surface))
+        else {
+            return;
+        };
+        let unmapped = self.unmapped_windows.remove(index);
+
+        let current_index = self.workspaces.current_workspace_index();
+        let workspace_index = Some(unmapped.workspace_index)
+            .filter(|&index| self.workspaces.get(index).is_some())
+            .unwrap_or(current_index);
+        let activate = !self.focus_lock && workspace_index == current_index;
+
+        debug!(window = ?unmapped.window, "Toplevel mapped again");
+        if let WindowElement::Wayland(ref w) = unmapped.window {
+            w.on_commit();
+        }
+        if let Some(workspace) = self.workspaces.get_mut(workspace_index) {
+            workspace.map_window(unmapped.window.clone(), unmapped.location, activate);
+        }
+        if activate {
+            let keyboard = self.seat.get_keyboard().unwrap();
+            keyboard.set_focus(
+                self,
+                Some(unmapped.window.into()),
+                SERIAL_COUNTER.next_serial(),
+            );
+        } else {
+            self.refresh_focus_dim();
+        }
+    }
+
+    /// Forgets an unmapped toplevel, returning it if it was one
+    pub fn forget_unmapped(&mut self, surface: &WlSurface) -> Option<WindowElement> {
+        let index = self
+            .unmapped_windows
+            .iter()
+            .position(|unmapped| unmapped.window.wl_surface().as_ref() == Some(surface))?;
+        Some(self.unmapped_windows.remove(index).window)
+    }
+
+    /// Moves the windows of an output that's going away onto the first remaining output.
+    ///
+    /// Fullscreen and maximized windows get restored first, their saved geometry would
+    /// otherwise point at the gone output. `geometry` is where the output used to be.
+    pub fn evacuate_output(&mut self, output: &Output, geometry: Rectangle<i32, Logical>) {
+        if let Some(fullscreen) = output.user_data().get::<FullscreenSurface>() {
+            if let (Some(window), _) = fullscreen.clear() {
+                unfullscreen(&window);
+            }
+        }
+
+        let Some(target) = self
+            .workspaces
+            .outputs()
+            .next()
+            .and_then(|o| self.workspaces.current_workspace().output_geometry(o))
+        else {
+            return;
+        };
+
+        let mut moved_transients = Vec::new();
+        for (index, workspace) in self.workspaces.workspaces_mut().enumerate() {
+            let windows: Vec<(WindowElement, Point<i32, Logical>)> = workspace
+                .windows()
+                .filter_map(|window| Some((window.clone(), workspace.window_location(window)?)))
+                .filter(|(_, location)| geometry.contains(*location))
+                .collect();
+
+            for (window, location, transients) in with_transients(workspace, windows) {
+                let maximized = window.is_maximized();
+                let old_geometry = if maximized {
+                    window
+                        .user_data()
+                        .get::<OldGeometry>()
+                        .and_then(|data| data.restore())
+                } else {
+                    None
+                };
+                let evacuated = evacuated_geometry(
+                    Rectangle::from_loc_and_size(location, window.geometry().size),
+                    old_geometry,
+                    geometry,
+                    target,
+                );
+
+                match window {
+                    WindowElement::Wayland(ref w) => {
+                        if maximized {
+                            w.toplevel().with_pending_state(|state| {
+                                state.states.unset(xdg_toplevel::State::Maximized);
+                                state.size = old_geometry.map(|old| old.size);
+                            });
+                            w.toplevel().send_pending_configure();
+                        }
+                    }
+                    #[cfg(feature = "xwayland")]
+                    WindowElement::X11(ref w) => {
+                        if maximized {
+                            if let Err(err) = w.set_maximized(false) {
+                                tracing::error!(?err, "Failed to unmaximize X11 window");
+                            }
+                        }
+                        if let Err(err) = w.configure(evacuated) {
+                            tracing::error!(?err, "Failed to configure X11 window");
+                        }
+                    }
+                }
+
+                let offset = evacuated.loc - old_geometry.map_or(location, |old| old.loc);
+                moved_transients.push((index, transients, offset));
+                workspace.map_window(window, evacuated.loc, false);
+            }
+        }
+
+        // dialogs come along, staying where they were next to their parent
+        for (index, transients, offset) in moved_transients {
+            self.move_transients(&transients, index, index, offset);
+        }
+    }
+
+    /// Moves the windows on the current workspace of the output under the pointer onto the
+    /// output named `target_name`, at the same relative spot.
+    ///
+    /// Maximized windows get maximized on the target instead, a fullscreen one stops being
+    /// fullscreen like it would when its output goes away.
+    pub fn move_windows_to_output(&mut self, target_name: &str) {
+        let workspace = self.workspaces.current_workspace();
+        let Some(source) = workspace
+            .output_under(self.pointer.current_location())
+            .next()
+            .cloned()
+        else {
+            return;
+        };
+        let Some(target) = workspace
+            .outputs()
+            .find(|o| o.name() == target_name)
+            .cloned()
+        else {
+            tracing::warn!(output = target_name, "No output to move the windows to");
+            return;
+        };
+        if source == target {
+            return;
+        }
+        let (Some(source_geometry), Some(target_geometry)) = (
+            workspace.output_geometry(&source),
+            workspace.output_geometry(&target),
+        ) else {
+            return;
+        };
+
+        self.release_current_fullscreen(&source);
+        let current = self.workspaces.current_workspace_index();
+        let windows = self.windows_in(current, source_geometry);
+        self.remap_windows(current, windows, source_geometry, &target, target_geometry);
+        self.update_suspended_windows();
+    }
+
+    /// Swaps the windows on the current workspace of the output under the pointer with the
+    /// ones of the next output, the same way [`Self::move_windows_to_output`] moves them
+    pub fn swap_outputs(&mut self) {
+        let workspace = self.workspaces.current_workspace();
+        let outputs: Vec<Output> = workspace.outputs().cloned().collect();
+        let Some(source_index) = workspace
+            .output_under(self.pointer.current_location())
+            .next()
+            .and_then(|output| outputs.iter().position(|o| o == output))
+        else {
+            return;
+        };
+        if outputs.len() < 2 {
+            return;
+        }
+        let source = outputs[source_index].clone();
+        let target = outputs[(source_index + 1) % outputs.len()].clone();
+        let (Some(source_geometry), Some(target_geometry)) = (
+            workspace.output_geometry(&source),
+            workspace.output_geometry(&target),
+        ) else {
+            return;
+        };
+
+        self.release_current_fullscreen(&source);
+        self.release_current_fullscreen(&target);
+        // both sets are picked before anything moves, so nothing gets moved back
+        let current = self.workspaces.current_workspace_index();
+        let mut source_windows = self.windows_in(current, source_geometry);
+        let mut target_windows = self.windows_in(current, target_geometry);
+        // a dialog on the other output comes along with its parent instead
+        let workspace = self.workspaces.current_workspace();
+        let transients_of = |windows: &[(WindowElement, Point<i32, Logical>)]| {
+            windows
+                .iter()
+                .flat_map(|(window, _)| workspace.transients_of(window))
+                .collect::<Vec<_>>()
+        };
+        let (source_transients, target_transients) = (
+            transients_of(&source_windows),
+            transients_of(&target_windows),
+        );
+        source_windows.retain(|(window, _)| !target_transients.contains(window));
+        target_windows.retain(|(window, _)| !source_transients.contains(window));
+        self.remap_windows(
+            current,
+            source_windows,
+            source_geometry,
+            &target,
+            target_geometry,
+        );
+        self.remap_windows(
+            current,
+            target_windows,
+            target_geometry,
+            &source,
+            source_geometry,
+        );
+        self.update_suspended_windows();
+    }
+
+    /// Changes the scale of the output named `name`. Windows on it keep their relative spots on
+    /// every workspace, and the outputs right of it move over so they still line up.
+    pub fn set_output_scale(&mut self, name: &str, scale: f64) {
+        if !scale.is_finite() || scale <= 0.0 {
+            tracing::warn!(scale, "Invalid output scale");
+            return;
+        }
+        let workspace = self.workspaces.current_workspace();
+        let Some(output) = workspace.outputs().find(|o| o.name() == name).cloned() else {
+            tracing::warn!(output = name, "No output to scale");
+            return;
+        };
+        let Some(old_geometry) = workspace.output_geometry(&output) else {
+            return;
+        };
+        let old_geometries: Vec<(Output, Rectangle<i32, Logical>)> = workspace
+            .outputs()
+            .filter_map(|o| Some((o.clone(), workspace.output_geometry(o)?)))
+            .collect();
+
+        output.change_current_state(None, None, Some(OutputScale::Fractional(scale)), None);
+        for workspace in self.workspaces.workspaces_mut() {
+            workspace.add_output(&output, old_geometry.loc);
+        }
+        layer_map_for_output(&output).arrange();
+        let Some(new_geometry) = self.workspaces.current_workspace().output_geometry(&output)
+        else {
+            return;
+        };
+
+        let shift = new_geometry.size.w - old_geometry.size.w;
+        let mut moved = Vec::new();
+        for (o, geometry) in old_geometries {
+            if o == output {
+                moved.push((o, geometry, new_geometry));
+            } else if shift != 0 && geometry.loc.x >= old_geometry.loc.x + old_geometry.size.w {
+                let location = geometry.loc + Point::from((shift, 0));
+                o.change_current_state(None, None, None, Some(location));
+                for workspace in self.workspaces.workspaces_mut() {
+                    workspace.add_output(&o, location);
+                }
+                moved.push((
+                    o,
+                    geometry,
+                    Rectangle::from_loc_and_size(location, geometry.size),
+                ));
+            }
+        }
+
+        for index in 0..self.workspaces.workspaces().len() {
+            // picked before anything moves, so nothing gets moved twice
+            let windows: Vec<_> = moved
+                .iter()
+                .map(|(_, old, _)| self.windows_in(index, *old))
+                .collect();
+            for ((o, old, new), windows) in moved.iter().zip(windows) {
+                self.remap_windows(index, windows, *old, o, *new);
+            }
+        }
+
+        // a fullscreen window keeps covering the whole output
+        if let Some(window) = output
+            .user_data()
+            .get::<FullscreenSurface>()
+            .and_then(|fullscreen| fullscreen.get().0)
+        {
+            match window {
+                WindowElement::Wayland(ref w) => {
+                    w.toplevel().with_pending_state(|state| {
+                        state.size = Some(new_geometry.size);
+                    });
+                    w.toplevel().send_pending_configure();
+                }
+                #[cfg(feature = "xwayland")]
+                WindowElement::X11(ref w) => {
+                    if let Err(err) = w.configure(new_geometry) {
+                        tracing::error!(?err, "Failed to configure X11 window");
+                    }
+                }
+            }
+        }
+
+        for (o, _, _) in &moved {
+            self.backend_data.reset_buffers(o);
+        }
+        tracing::info!(output = name, scale, "Changed output scale");
+    }
+
+    /// Takes the window out of fullscreen if it's fullscreen on `output` on the current workspace
+    fn release_current_fullscreen(&mut self, output: &Output) {
+        let current_index = self.workspaces.current_workspace_index();
+        if let Some(fullscreen) = output.user_data().get::<FullscreenSurface>() {
+            if let (Some(window), Some(index)) = fullscreen.get() {
+                if index == current_index {
+                    fullscreen.clear();
+                    unfullscreen(&window);
+                    self.backend_data.reset_buffers(output);
+                }
+            }
+        }
+    }
+
+    /// Windows of the workspace at `index` whose top left corner is in `geometry`
+    fn windows_in(
+        &self,
+        index: usize,
+        geometry: Rectangle<i32, Logical>,
+    ) -> Vec<(WindowElement, Point<i32, Logical>)> {
+        let Some(workspace) = self.workspaces.workspaces().get(index).copied() else {
+            return Vec::new();
+        };
+        workspace
+            .windows()
+            .filter_map(|window| Some((window.clone(), workspace.window_location(window)?)))
+            .filter(|(_, location)| geometry.contains(*location))
+            .collect()
+    }
+
+    /// Moves `windows` of the workspace at `index` from the output at `source_geometry` to
+    /// `target`, at the same relative spot, maximized windows get maximized on the target
+    fn remap_windows(
+        &mut self,
+        index: usize,
+        windows: Vec<(WindowElement, Point<i32, Logical>)>,
+        source_geometry: Rectangle<i32, Logical>,
+        target: &Output,
+        target_geometry: Rectangle<i32, Logical>,
+    ) {
+        let Some(workspace) = self.workspaces.get_mut(index) else {
+            return;
+        };
+        let maximized_target = maximized_geometry(workspace, target).unwrap_or(target_geometry);
+
+        // scales the offset into the source output to the target's size, keeping the whole
+        // window on the target when it fits
+        let remap = |location: Point<i32, Logical>, size: Size<i32, Logical>| {
+            let offset = location - source_geometry.loc;
+            let x = offset.x as f64 * target_geometry.size.w as f64 / source_geometry.size.w as f64;
+            let y = offset.y as f64 * target_geometry.size.h as f64 / source_geometry.size.h as f64;
+            Point::<i32, Logical>::from((
+                (target_geometry.loc.x + x as i32)
+                    .min(target_geometry.loc.x + target_geometry.size.w - size.w)
+                    .max(target_geometry.loc.x),
+                (target_geometry.loc.y + y as i32)
+                    .min(target_geometry.loc.y + target_geometry.size.h - size.h)
+                    .max(target_geometry.loc.y),
+            ))
+        };
+
+        let mut moved_transients = Vec::new();
+        for (window, location, transients) in with_transients(workspace, windows) {
+            let maximized = window.is_maximized();
+
+            let geometry = if maximized {
+                // unmaximizing should bring it back on the target too
+                if let Some(old_geometry) = window.user_data().get::<OldGeometry>() {
+                    if let Some(old) = old_geometry.restore() {
+                        old_geometry.save(Rectangle::from_loc_and_size(
+                            remap(old.loc, old.size),
+                            old.size,
+                        ));
+                    }
+                }
+                maximized_target
+            } else {
+                let size = window.geometry().size;
+                Rectangle::from_loc_and_size(remap(location, size), size)
+            };
+
+            match window {
+                WindowElement::Wayland(ref w) => {
+                    if maximized {
+                        w.toplevel().with_pending_state(|state| {
+                            state.size = Some(geometry.size);
+                        });
+                        w.toplevel().send_pending_configure();
+                    }
+                }
+                #[cfg(feature = "xwayland")]
+                WindowElement::X11(ref w) => {
+                    if let Err(err) = w.configure(geometry) {
+                        tracing::error!(?err, "Failed to configure X11 window");
+                    }
+                }
+            }
+
+            moved_transients.push((transients, geometry.loc - location));
+            workspace.map_window(window, geometry.loc, false);
+        }
+
+        // dialogs come along, staying where they were next to their parent
+        for (transients, offset) in moved_transients {
+            self.move_transients(&transients, index, index, offset);
+        }
+    }
+
+    /// Fits the maximized windows of `output` to the part of it layer-shell surfaces don't
+    /// reserve, after a bar got added, removed or changed its exclusive zone
+    pub fn remaximize_windows(&mut self, output: &Output) {
+        for workspace in self.workspaces.workspaces_mut() {
+            let Some(output_geometry) = workspace.output_geometry(output) else {
+                continue;
+            };
+            let Some(geometry) = maximized_geometry(workspace, output) else {
+                continue;
+            };
+
+            let windows = workspace
+                .windows()
+                .filter(|window| window.is_maximized())
+                .filter_map(|window| Some((window.clone(), workspace.window_location(window)?)))
+                .filter(|(_, location)| output_geometry.contains(*location))
+                .collect::<Vec<_>>();
+
+            for (window, location) in windows {
+                if location == geometry.loc && window.geometry().size == geometry.size {
+                    continue;
+                }
+
+                match window {
+                    WindowElement::Wayland(ref w) => {
+                        w.toplevel().with_pending_state(|state| {
+                            state.size = Some(geometry.size);
+                        });
+                        w.toplevel().send_pending_configure();
+                    }
+                    #[cfg(feature = "xwayland")]
+                    WindowElement::X11(ref w) => {
+                        if let Err(err) = w.configure(geometry) {
+                            tracing::error!(?err, "Failed to configure X11 window");
+                        }
+                    }
+                }
+
+                workspace.map_window(window, geometry.loc, false);
+            }
+        }
+    }
+
+    /// Moves a window from wherever it is onto the current workspace, centered on `output`.
+    ///
+    /// It stops being fullscreen, so it doesn't cover whatever is on the output it's brought to.
+    pub fn summon_window(&mut self, window: &WindowElement, output: &Output) {
+        let Some(output_geometry) = self.workspaces.current_workspace().output_geometry(output)
+        else {
+            return;
+        };
+
+        for o in self.workspaces.outputs() {
+            if let Some(fullscreen) = o.user_data().get::<FullscreenSurface>() {
+                if fullscreen.get().0.as_ref() == Some(window) {
+                    fullscreen.clear();
+                    unfullscreen(window);
+                }
+            }
+        }
+
+        // dialogs come along, staying where they were next to their parent
+        let source = self
+            .workspaces
+            .workspace_index_of(window)
+            .and_then(|index| {
+                let workspace = self.workspaces.get_mut(index)?;
+                let old_location = workspace.window_location(window)?;
+                let transients = workspace.transients_of(window);
+                workspace.unmap_window(window);
+                Some((index, old_location, transients))
+            });
+
+        let size = window.geometry().size;
+        let location: Point<i32, Logical> = (
+            output_geometry.loc.x + (output_geometry.size.w - size.w).max(0) / 2,
+            output_geometry.loc.y + (output_geometry.size.h - size.h).max(0) / 2,
+        )
+            .into();
+
+        #[cfg(feature = "xwayland")]
+        if let WindowElement::X11(ref w) = window {
+            if let Err(err) = w.configure(Rectangle::from_loc_and_size(location, size)) {
+                tracing::error!(?err, "Failed to configure X11 window");
+            }
+        }
+
+        self.workspaces
+            .current_workspace_mut()
+            .map_window(window.clone(), location, true);
+        if let Some((index, old_location, transients)) = source {
+            let current = self.workspaces.current_workspace_index();
+            self.move_transients(&transients, index, current, location - old_location);
+        }
+        self.update_suspended_windows();
+    }
+
+    /// Moves `transients` from workspace `from` to workspace `to`, shifted by `offset` so they
+    /// stay in the same place relative to their parent, `from` and `to` can be the same
+    pub fn move_transients(
+        &mut self,
+        transients: &[WindowElement],
+        from: usize,
+        to: usize,
+        offset: Point<i32, Logical>,
+    ) {
+        for window in transients {
+            let Some(source) = self.workspaces.get_mut(from) else {
+                return;
+            };
+            let Some(location) = source.window_location(window) else {
+                continue;
+            };
+            // mapping it again on the same workspace only moves it
+            if from != to {
+                source.unmap_window(window);
+            }
+
+            let location = location + offset;
+            #[cfg(feature = "xwayland")]
+            if let WindowElement::X11(ref w) = window {
+                if let Err(err) = w.configure(Rectangle::from_loc_and_size(
+                    location,
+                    window.geometry().size,
+                )) {
+                    tracing::error!(?err, "Failed to configure X11 window");
+                }
+            }
+
+            if let Some(target) = self.workspaces.get_mut(to) {
+                target.map_window(window.clone(), location, false);
+            }
+        }
+    }
+
+    /// Shows the focused window alone over its whole output, without its title bar
+    /// or any layer-shell bars, or puts everything back the way it was
+    pub fn toggle_zen(&mut self) {
+        if let Some(zen) = self.zen.take() {
+            self.leave_zen(zen);
+            return;
+        }
+
+        let keyboard = self.seat.get_keyboard().unwrap();
+        let Some(window) = keyboard
+            .current_focus()
+            .and_then(|focused| focused.wl_surface())
+            .and_then(|surface| self.window_for_surface(&surface))
+        else {
+            return;
+        };
+
+        // fullscreen windows already cover their output
+        let is_fullscreen = self.workspaces.outputs().any(|o| {
+            o.user_data()
+                .get::<FullscreenSurface>()
+                .and_then(|f| f.get().0)
+                .map(|w| w == window)
+                .unwrap_or(false)
+        });
+        if is_fullscreen {
+            return;
+        }
+
+        let workspace = self.workspaces.current_workspace();
+        let Some(location) = workspace.window_location(&window) else {
+            return;
+        };
+        let Some(output) = workspace.outputs_for_window(&window).into_iter().next() else {
+            return;
+        };
+        let Some(output_geometry) = workspace.output_geometry(&output) else {
+            return;
+        };
+
+        let was_ssd = window.decoration_state().is_ssd;
+        window.set_ssd(false);
+        let old_geometry = Rectangle::from_loc_and_size(location, window.geometry().size);
+
+        match window {
+            WindowElement::Wayland(ref w) => {
+                w.toplevel().with_pending_state(|state| {
+                    state.size = Some(output_geometry.size);
+                });
+                w.toplevel().send_pending_configure();
+            }
+            #[cfg(feature = "xwayland")]
+            WindowElement::X11(ref w) => {
+                if let Err(err) = w.configure(output_geometry) {
+                    tracing::error!(?err, "Failed to configure X11 window");
+                }
+            }
+        }
+
+        self.workspaces.current_workspace_mut().map_window(
+            window.clone(),
+            output_geometry.loc,
+            true,
+        );
+        self.backend_data.reset_buffers(&output);
+
+        self.zen = Some(ZenWindow {
+            window,
+            output,
+            workspace_index: self.workspaces.current_workspace_index(),
+            old_geometry,
+            was_ssd,
+        });
+    }
+
+    fn leave_zen(&mut self, zen: ZenWindow) {
+        self.backend_data.reset_buffers(&zen.output);
+
+        // a closed window has nothing left to restore
+        if !zen.window.alive() {
+            return;
+        }
+
+        let window = zen.window;
+        window.set_ssd(zen.was_ssd);
+
+        match window {
+            WindowElement::Wayland(ref w) => {
+                w.toplevel().with_pending_state(|state| {
+                    state.size = Some(zen.old_geometry.size);
+                });
+                w.toplevel().send_pending_configure();
+            }
+            #[cfg(feature = "xwayland")]
+            WindowElement::X11(ref w) => {
+                if let Err(err) = w.configure(zen.old_geometry) {
+                    tracing::error!(?err, "Failed to configure X11 window");
+                }
+            }
+        }
+
+        if let Some(workspace) = self
+            .workspaces
+            .workspace_index_of(&window)
+            .and_then(|index| self.workspaces.get_mut(index))
+        {
+            workspace.map_window(window, zen.old_geometry.loc, false);
+        }
+    }
+
+    /// Moves windows that drifted out of view back onto the output they overlap the most,
+    /// or the first output if they aren't on any
+    pub fn tidy_windows(&mut self) {
+        let fullscreen_windows: Vec<WindowElement> = self
+            .workspaces
+            .outputs()
+            .filter_map(|o| o.user_data().get::<FullscreenSurface>())
+            .filter_map(|f| f.get().0)
+            .collect();
+
+        for workspace in self.workspaces.workspaces_mut() {
+            let output_geometries: Vec<Rectangle<i32, Logical>> = workspace
+                .outputs()
+                .filter_map(|o| workspace.output_geometry(o))
+                .collect();
+            let Some(first_output) = output_geometries.first().copied() else {
+                continue;
+            };
+
+            let windows: Vec<(WindowElement, Rectangle<i32, Logical>)> = workspace
+                .windows()
+                .filter(|window| !fullscreen_windows.contains(window))
+                .filter_map(|window| {
+                    let location = workspace.window_location(window)?;
+                    let geometry = Rectangle::from_loc_and_size(location, window.geometry().size);
+                    Some((window.clone(), geometry))
+                })
+                .collect();
+
+            for (window, geometry) in windows {
+                // the top left corner is where the title bar is grabbed, so it has to be visible
+                let in_view = output_geometries
+                    .iter()
+                    .any(|output| output.contains(geometry.loc));
+                if in_view {
+                    continue;
+                }
+
+                let target = output_geometries
+                    .iter()
+                    .filter_map(|output| Some((output, output.intersection(geometry)?)))
+                    .max_by_key(|(_, overlap)| overlap.size.w * overlap.size.h)
+                    .map(|(output, _)| *output)
+                    .unwrap_or(first_output);
+
+                let location: Point<i32, Logical> = (
+                    geometry
+                        .loc
+                        .x
+                        .min(target.loc.x + target.size.w - geometry.size.w)
+                        .max(target.loc.x),
+                    geometry
+                        .loc
+                        .y
+                        .min(target.loc.y + target.size.h - geometry.size.h)
+                        .max(target.loc.y),
+                )
+                    .into();
+
+                #[cfg(feature = "xwayland")]
+                if let WindowElement::X11(ref w) = window {
+                    if let Err(err) =
+                        w.configure(Rectangle::from_loc_and_size(location, geometry.size))
+                    {
+                        tracing::error!(?err, "Failed to configure X11 window");
+                    }
+                }
+
+                workspace.map_window(window, location, false);
+            }
+        }
+    }
+
+    /// Moves the focused window by `move_step` pixels, or by the grid size while the grid is
+    /// shown. Fullscreen, maximized and zen windows stay where they are
+    pub fn nudge_window(&mut self, direction: Direction) {
+        let keyboard = self.seat.get_keyboard().unwrap();
+        let Some(window) = keyboard
+            .current_focus()
+            .and_then(|focused| focused.wl_surface())
+            .and_then(|surface| self.window_for_surface(&surface))
+        else {
+            return;
+        };
+
+        let is_fullscreen = self.workspaces.outputs().any(|o| {
+            o.user_data()
+                .get::<FullscreenSurface>()
+                .and_then(|f| f.get().0)
+                .map(|w| w == window)
+                .unwrap_or(false)
+        });
+        let maximized = window.is_maximized();
+        let is_zen = self.zen.as_ref().map_or(false, |zen| zen.window == window);
+        if is_fullscreen || maximized || is_zen {
+            return;
+        }
+
+        let Some(mut location) = self.workspaces.current_workspace().window_location(&window)
+        else {
+            return;
+        };
+        let step = self.snap_grid().unwrap_or(self.config.move_step);
+        match direction {
+            Direction::Left => location.x -= step,
+            Direction::Right => location.x += step,
+            Direction::Up => location.y -= step,
+            Direction::Down => location.y += step,
+        }
+
+        #[cfg(feature = "xwayland")]
+        if let WindowElement::X11(ref w) = window {
+            if let Err(err) = w.configure(Rectangle::from_loc_and_size(
+                location,
+                window.geometry().size,
+            )) {
+                tracing::error!(?err, "Failed to configure X11 window");
+            }
+        }
+        self.workspaces
+            .current_workspace_mut()
+            .map_window(window, location, true);
+
+        // the window may have moved under or away from the pointer
+        let pointer = self.pointer.clone();
+        let location = pointer.current_location();
+        let under = self.surface_under(location);
+        pointer.motion(
+            self,
+            under,
+            &MotionEvent {
+                location,
+                serial: SERIAL_COUNTER.next_serial(),
+                time: self.start_time.elapsed().as_millis() as u32,
+            },
+        );
+        pointer.frame(self);
+    }
+}
+
+/// Takes a window out of the fullscreen state, without touching the output's fullscreen slot
+fn unfullscreen(window: &WindowElement) {
+    match window {
+        WindowElement::Wayland(w) => {
+            w.toplevel().with_pending_state(|state| {
+                state.states.unset(xdg_toplevel::State::Fullscreen);
+                state.size = None;
+                state.fullscreen_output = None;
+            });
+            w.toplevel().send_pending_configure();
+        }
+        #[cfg(feature = "xwayland")]
+        WindowElement::X11(w) => {
+            if let Err(err) = w.set_fullscreen(false) {
+                tracing::error!(?err, "Failed to unfullscreen X11 window");
+            }
+            window.set_ssd(!w.is_decorated());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use smithay::utils::{Logical, Point, Rectangle};
+
+    use super::{evacuated_geometry, evacuated_location, FullscreenState, RemapState, RemapStep};
+
+    #[test]
+    fn evacuated_location_keeps_the_offset_into_the_output() {
+        let source = Rectangle::<i32, Logical>::from_loc_and_size((1920, 0), (2560, 1440));
+        let target = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+
+        assert_eq!(
+            evacuated_location((2020, 100).into(), (800, 600).into(), source, target),
+            Point::from((100, 100))
+        );
+    }
+
+    #[test]
+    fn evacuated_location_keeps_the_window_on_a_smaller_target() {
+        let source = Rectangle::<i32, Logical>::from_loc_and_size((1920, 0), (2560, 1440));
+        let target = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+
+        assert_eq!(
+            evacuated_location((4000, 1300).into(), (800, 600).into(), source, target),
+            Point::from((1120, 480))
+        );
+    }
+
+    #[test]
+    fn evacuated_location_of_a_window_bigger_than_the_target() {
+        let source = Rectangle::<i32, Logical>::from_loc_and_size((1920, 0), (2560, 1440));
+        let target = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+
+        assert_eq!(
+            evacuated_location((2000, 50).into(), (2400, 1300).into(), source, target),
+            Point::from((0, 0))
+        );
+    }
+
+    #[test]
+    fn evacuated_location_moves_onto_an_output_to_the_right() {
+        let source = Rectangle::<i32, Logical>::from_loc_and_size((0, 0), (1920, 1080));
+        let target = Rectangle::from_loc_and_size((1920, 200), (1280, 1024));
+
+        assert_eq!(
+            evacuated_location((-50, 10).into(), (800, 600).into(), source, target),
+            Point::from((1920, 210))
+        );
+    }
+
+    #[test]
+    fn evacuating_a_maximized_window_restores_its_old_geometry() {
+        let source = Rectangle::<i32, Logical>::from_loc_and_size((1920, 0), (2560, 1440));
+        let target = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+        let old_geometry = Rectangle::from_loc_and_size((2020, 100), (800, 600));
+
+        assert_eq!(
+            evacuated_geometry(source, Some(old_geometry), source, target),
+            Rectangle::from_loc_and_size((100, 100), (800, 600))
+        );
+    }
+
+    #[test]
+    fn evacuating_a_maximized_window_without_old_geometry_puts_it_on_the_target() {
+        let source = Rectangle::<i32, Logical>::from_loc_and_size((1920, 0), (2560, 1440));
+        let target = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+
+        assert_eq!(
+            evacuated_geometry(source, None, source, target).loc,
+            target.loc
+        );
+    }
+
+    #[test]
+    fn unmapped_toplevel_is_configured_once_before_it_maps() {
+        let mut state = RemapState::Unmapped;
+
+        assert_eq!(state.on_commit(false), RemapStep::Configure);
+        assert_eq!(state, RemapState::Configured);
+        assert_eq!(state.on_commit(false), RemapStep::Wait);
+        assert_eq!(state.on_commit(true), RemapStep::Map);
+    }
+
+    #[test]
+    fn unmapped_toplevel_attaching_a_buffer_right_away_maps() {
+        let mut state = RemapState::Unmapped;
+
+        assert_eq!(state.on_commit(true), RemapStep::Map);
+    }
+
+    #[test]
+    fn fullscreen_window_is_shown_again_after_switching_back() {
+        let mut fullscreen = FullscreenState::default();
+        fullscreen.set("firefox", 0);
+
+        assert_eq!(fullscreen.visible_on(0), Some("firefox"));
+        // switching away
+        assert_eq!(fullscreen.visible_on(1), None);
+        // and back
+        assert_eq!(fullscreen.visible_on(0), Some("firefox"));
+    }
+
+    #[test]
+    fn fullscreen_window_follows_it_to_another_workspace() {
+        let mut fullscreen = FullscreenState::default();
+        fullscreen.set("firefox", 0);
+
+        fullscreen.window_moved(&"foot", 1);
+        assert_eq!(fullscreen.visible_on(0), Some("firefox"));
+
+        fullscreen.window_moved(&"firefox", 2);
+        assert_eq!(fullscreen.visible_on(0), None);
+        assert_eq!(fullscreen.visible_on(2), Some("firefox"));
+    }
+
+    #[test]
+    fn cleared_fullscreen_is_not_shown() {
+        let mut fullscreen = FullscreenState::default();
+        fullscreen.set("firefox", 0);
+
+        assert_eq!(fullscreen.clear(), (Some("firefox"), Some(0)));
+        assert_eq!(fullscreen.visible_on(0), None);
     }
 }
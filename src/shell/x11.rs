@@ -38,19 +38,7 @@ use crate::{
     Backend, Buddaraysh, CalloopData,
 };
 
-use super::place_new_window;
-
-#[derive(Debug, Default)]
-struct OldGeometry(RefCell<Option<Rectangle<i32, Logical>>>);
-impl OldGeometry {
-    pub fn save(&self, geo: Rectangle<i32, Logical>) {
-        *self.0.borrow_mut() = Some(geo);
-    }
-
-    pub fn restore(&self) -> Option<Rectangle<i32, Logical>> {
-        self.0.borrow_mut().take()
-    }
-}
+use super::{maximized_geometry, place_new_window, unmaximized_window_location, OldGeometry};
 
 impl<BackendData: Backend> XwmHandler for CalloopData<BackendData> {
     fn xwm_state(&mut self, _xwm: XwmId) -> &mut X11Wm {
@@ -65,19 +53,26 @@ impl<BackendData: Backend> XwmHandler for CalloopData<BackendData> {
             tracing::warn!(?window, ?err, "Failed to send Xwayland Mapped-Event");
         }
 
+        let current_workspace_index = self.state.workspaces.current_workspace_index();
+        let workspace_index = self
+            .state
+            .take_pending_spawn(window.pid())
+            .filter(|&index| self.state.workspaces.get(index).is_some())
+            .unwrap_or(current_workspace_index);
+
         let window = WindowElement::X11(window);
+        window.save_preferred_size(window.geometry().size);
+        let workspace = self.state.workspaces.get_mut(workspace_index).unwrap();
         place_new_window(
-            self.state.workspaces.current_workspace_mut().space_mut(),
+            workspace,
             self.state.pointer.current_location(),
             &window,
-            true,
+            !self.state.focus_lock && workspace_index == current_workspace_index,
         );
-        let bbox = self
-            .state
-            .workspaces
-            .current_workspace()
-            .window_bbox(&window)
-            .unwrap();
+        let bbox = workspace.window_bbox(&window).unwrap();
+        // dimmed right away when it doesn't get the focus
+        self.state.refresh_focus_dim();
+        self.state.stop_startup_feedback();
         let WindowElement::X11(xsurface) = &window else {
             unreachable!()
         };
@@ -130,6 +125,9 @@ impl<BackendData: Backend> XwmHandler for CalloopData<BackendData> {
             .windows()
             .find(|e| matches!(e, WindowElement::X11(w) if w == &window))
             .cloned();
+        let workspace_index = maybe
+            .as_ref()
+            .map(|_| self.state.workspaces.current_workspace_index());
         if let Some(win) = maybe {
             tracing::debug!("removing x11 window from windows");
             self.state
@@ -144,6 +142,8 @@ impl<BackendData: Backend> XwmHandler for CalloopData<BackendData> {
                 .retain(|win| win != &window);
         } else {
             window.set_mapped(false).unwrap();
+            self.state
+                .window_closed(&WindowElement::X11(window), workspace_index);
         }
     }
 
@@ -249,6 +249,7 @@ impl<BackendData: Backend> XwmHandler for CalloopData<BackendData> {
                 .output_geometry(output)
                 .unwrap();
 
+            self.state.release_fullscreen(output, elem);
             window.set_fullscreen(true).unwrap();
             elem.set_ssd(false);
             window.configure(geometry).unwrap();
@@ -264,33 +265,53 @@ impl<BackendData: Backend> XwmHandler for CalloopData<BackendData> {
     }
 
     fn unfullscreen_request(&mut self, _xwm: XwmId, window: X11Surface) {
-        if let Some(elem) = self
+        // the workspace with the window might not be the current one anymore
+        let Some(elem) = self
             .state
             .workspaces
-            .current_workspace()
-            .windows()
-            .find(|e| matches!(e, WindowElement::X11(w) if w == &window))
-        {
-            window.set_fullscreen(false).unwrap();
-            elem.set_ssd(!window.is_decorated());
-            if let Some(output) = self.state.workspaces.outputs().find(|o| {
+            .workspaces()
+            .iter()
+            .find_map(|workspace| {
+                workspace
+                    .windows()
+                    .find(|e| matches!(e, WindowElement::X11(w) if w == &window))
+                    .cloned()
+            })
+        else {
+            return;
+        };
+
+        window.set_fullscreen(false).unwrap();
+        elem.set_ssd(!window.is_decorated());
+        if let Some(output) = self
+            .state
+            .workspaces
+            .outputs()
+            .find(|o| {
                 o.user_data()
                     .get::<FullscreenSurface>()
                     .and_then(|f| f.get().0)
-                    .map(|w| &w == elem)
+                    .map(|w| w == elem)
                     .unwrap_or(false)
-            }) {
-                trace!("Unfullscreening: {:?}", elem);
-                output
-                    .user_data()
-                    .get::<FullscreenSurface>()
-                    .unwrap()
-                    .clear();
-                window
-                    .configure(self.state.workspaces.current_workspace().window_bbox(elem))
-                    .unwrap();
-                self.state.backend_data.reset_buffers(output);
-            }
+            })
+            .cloned()
+        {
+            trace!("Unfullscreening: {:?}", elem);
+            let (_, workspace_index) = output
+                .user_data()
+                .get::<FullscreenSurface>()
+                .unwrap()
+                .clear();
+            self.state.restore_unfullscreened(&elem, workspace_index);
+
+            let bbox = self
+                .state
+                .workspaces
+                .workspace_index_of(&elem)
+                .and_then(|index| self.state.workspaces.workspaces().get(index).copied())
+                .and_then(|workspace| workspace.window_bbox(&elem));
+            window.configure(bbox).unwrap();
+            self.state.backend_data.reset_buffers(&output);
         }
     }
 
@@ -448,6 +469,7 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
             *state.borrow_mut() = ResizeSurfaceState::Resizing {
                 edges: edges.into(),
                 initial_rect,
+                centered: false,
             };
         });
 
@@ -457,6 +479,7 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
             edges,
             initial_rect,
             last_window_size: initial_rect.size,
+            centered: false,
         };
 
         pointer.set_grab(self, grab, serial, Focus::Clear);
@@ -490,11 +513,7 @@ impl<BackendData: Backend> Buddaraysh<BackendData> {
             .or_else(|| self.workspaces.outputs().next())
             // Assumes that at least one output exists
             .expect("No outputs found");
-        let geometry = self
-            .workspaces
-            .current_workspace()
-            .output_geometry(output)
-            .unwrap();
+        let geometry = maximized_geometry(self.workspaces.current_workspace(), output).unwrap();
 
         window.set_maximized(true).unwrap();
         window.configure(geometry).unwrap();
@@ -532,6 +551,8 @@ impl<BackendData: Backend> Buddaraysh<BackendData> {
         // If surface is maximized then unmaximize it
         if window.is_maximized() {
             window.set_maximized(false).unwrap();
+            let maximized_geo =
+                Rectangle::from_loc_and_size(initial_window_location, element.geometry().size);
             let pos = self.pointer.current_location();
             initial_window_location = (pos.x as i32, pos.y as i32).into();
             if let Some(old_geo) = window
@@ -539,6 +560,8 @@ impl<BackendData: Backend> Buddaraysh<BackendData> {
                 .get::<OldGeometry>()
                 .and_then(|data| data.restore())
             {
+                initial_window_location =
+                    unmaximized_window_location(pos, maximized_geo, old_geo.size);
                 window
                     .configure(Rectangle::from_loc_and_size(
                         initial_window_location,
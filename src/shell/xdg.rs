@@ -4,8 +4,7 @@ use smithay::{
     delegate_xdg_activation, delegate_xdg_shell,
     desktop::{
         find_popup_root_surface, get_popup_toplevel_coords, layer_map_for_output,
-        space::SpaceElement, LayerSurface, PopupKind, PopupManager, Space, Window,
-        WindowSurfaceType,
+        space::SpaceElement, LayerSurface, PopupKind, PopupManager, Window, WindowSurfaceType,
     },
     input::{
         pointer::{Focus, GrabStartData as PointerGrabStartData},
@@ -42,10 +41,14 @@ use crate::{
     shell::FullscreenSurface,
     ssd::HEADER_BAR_HEIGHT,
     window::WindowElement,
+    workspace::Workspaces,
     Backend, Buddaraysh,
 };
 
-use super::{fullscreen_output_geometry, place_new_window};
+use super::{
+    fullscreen_output_geometry, maximized_geometry, place_new_window, unmaximized_window_location,
+    OldGeometry,
+};
 
 impl<BackendData: Backend + 'static> XdgShellHandler for Buddaraysh<BackendData> {
     fn xdg_shell_state(&mut self) -> &mut XdgShellState {
@@ -53,13 +56,46 @@ impl<BackendData: Backend + 'static> XdgShellHandler for Buddaraysh<BackendData>
     }
 
     fn new_toplevel(&mut self, surface: ToplevelSurface) {
+        let pid = self
+            .display_handle
+            .get_client(surface.wl_surface().id())
+            .ok()
+            .and_then(|client| client.get_credentials(&self.display_handle).ok())
+            .map(|credentials| credentials.pid as u32);
+        let current_workspace_index = self.workspaces.current_workspace_index();
+        let workspace_index = self
+            .take_pending_spawn(pid)
+            .filter(|&index| self.workspaces.get(index).is_some())
+            .unwrap_or(current_workspace_index);
+
         let window = WindowElement::Wayland(Window::new(surface));
         place_new_window(
-            self.workspaces.current_workspace_mut().space_mut(),
+            self.workspaces.get_mut(workspace_index).unwrap(),
             self.pointer.current_location(),
             &window,
-            true,
+            !self.focus_lock && workspace_index == current_workspace_index,
         );
+        // dimmed right away when it doesn't get the focus
+        self.refresh_focus_dim();
+        self.stop_startup_feedback();
+    }
+
+    fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
+        if let Some(window) = self.forget_unmapped(surface.wl_surface()) {
+            self.window_closed(&window, None);
+            return;
+        }
+
+        let Some(window) = self.workspaces.window_for_surface(surface.wl_surface()) else {
+            return;
+        };
+
+        let workspace_index = self.workspaces.workspace_index_of(&window);
+        for workspace in self.workspaces.workspaces_mut() {
+            workspace.unmap_window(&window);
+        }
+
+        self.window_closed(&window, workspace_index);
     }
 
     fn new_popup(&mut self, surface: PopupSurface, _positioner: PositionerState) {
@@ -112,67 +148,75 @@ impl<BackendData: Backend + 'static> XdgShellHandler for Buddaraysh<BackendData>
     }
 
     fn maximize_request(&mut self, surface: ToplevelSurface) {
-        // TODO
+        // remembered until the initial commit, the window gets maximized right when it's mapped
+        if !surface.is_initial_configure_sent() {
+            surface.with_pending_state(|state| {
+                state.states.set(xdg_toplevel::State::Maximized);
+            });
+            return;
+        }
+
+        self.maximize_toplevel(&surface);
         surface.send_configure();
     }
 
     fn unmaximize_request(&mut self, surface: ToplevelSurface) {
-        // TODO
+        if !surface.is_initial_configure_sent() {
+            surface.with_pending_state(|state| {
+                state.states.unset(xdg_toplevel::State::Maximized);
+            });
+            return;
+        }
+
+        if !surface
+            .current_state()
+            .states
+            .contains(xdg_toplevel::State::Maximized)
+        {
+            return;
+        }
+
+        let Some(window) = self.workspaces.window_for_surface(surface.wl_surface()) else {
+            return;
+        };
+        let old_geo = window
+            .user_data()
+            .get::<OldGeometry>()
+            .and_then(|data| data.restore());
+
+        surface.with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Maximized);
+            state.size = old_geo.map(|geo| geo.size);
+        });
+        if let Some(old_geo) = old_geo {
+            if let Some(workspace) = self
+                .workspaces
+                .workspace_index_of(&window)
+                .and_then(|index| self.workspaces.get_mut(index))
+            {
+                workspace.map_window(window, old_geo.loc, false);
+            }
+        }
+
+        surface.send_pending_configure();
     }
 
-    fn fullscreen_request(&mut self, surface: ToplevelSurface, mut wl_output: Option<WlOutput>) {
+    fn fullscreen_request(&mut self, surface: ToplevelSurface, wl_output: Option<WlOutput>) {
+        // remembered until the initial commit, the window gets fullscreened right when it's mapped
+        if !surface.is_initial_configure_sent() {
+            surface.with_pending_state(|state| {
+                state.states.set(xdg_toplevel::State::Fullscreen);
+                state.fullscreen_output = wl_output;
+            });
+            return;
+        }
+
         if surface
             .current_state()
             .capabilities
             .contains(xdg_toplevel::WmCapabilities::Fullscreen)
         {
-            // NOTE: This is only one part of the solution. We can set the
-            // location and configure size here, but the surface should be rendered fullscreen
-            // independently from its buffer size
-            let wl_surface = surface.wl_surface();
-
-            let output_geometry = fullscreen_output_geometry(
-                wl_surface,
-                wl_output.as_ref(),
-                self.workspaces.current_workspace_mut().space_mut(),
-            );
-
-            if let Some(geometry) = output_geometry {
-                let output = wl_output
-                    .as_ref()
-                    .and_then(Output::from_resource)
-                    .unwrap_or_else(|| self.workspaces.outputs().next().unwrap().clone());
-                let client = self.display_handle.get_client(wl_surface.id()).unwrap();
-                for output in output.client_outputs(&client) {
-                    wl_output = Some(output);
-                }
-                let window = self
-                    .workspaces
-                    .current_workspace()
-                    .windows()
-                    .find(|window| {
-                        window
-                            .wl_surface()
-                            .map(|s| s == *wl_surface)
-                            .unwrap_or(false)
-                    })
-                    .unwrap();
-
-                surface.with_pending_state(|state| {
-                    state.states.set(xdg_toplevel::State::Fullscreen);
-                    state.size = Some(geometry.size);
-                    state.fullscreen_output = wl_output;
-                });
-                output
-                    .user_data()
-                    .insert_if_missing(FullscreenSurface::default);
-                output
-                    .user_data()
-                    .get::<FullscreenSurface>()
-                    .unwrap()
-                    .set(window.clone(), self.workspaces.current_workspace_index());
-                trace!("Fullscreening: {:?}", window);
-            }
+            self.fullscreen_toplevel(&surface, wl_output);
         }
 
         // The protocol demands us to always reply with a configure,
@@ -198,8 +242,11 @@ impl<BackendData: Backend + 'static> XdgShellHandler for Buddaraysh<BackendData>
             let output = Output::from_resource(&output).unwrap();
             if let Some(fullscreen) = output.user_data().get::<FullscreenSurface>() {
                 trace!("Unfullscreening: {:?}", fullscreen.get());
-                fullscreen.clear();
+                let (window, workspace_index) = fullscreen.clear();
                 self.backend_data.reset_buffers(&output);
+                if let Some(window) = window {
+                    self.restore_unfullscreened(&window, workspace_index);
+                }
             }
         }
 
@@ -237,13 +284,9 @@ fn check_grab<BackendData: Backend + 'static>(
 }
 
 /// Should be called on `WlSurface::commit`
-pub fn handle_commit(popups: &mut PopupManager, space: &Space<WindowElement>, surface: &WlSurface) {
+pub fn handle_commit(popups: &mut PopupManager, workspaces: &Workspaces, surface: &WlSurface) {
     // Handle toplevel commits.
-    if let Some(WindowElement::Wayland(ref window)) = space
-        .elements()
-        .find(|window| window.wl_surface().map(|s| s == *surface).unwrap_or(false))
-        .cloned()
-    {
+    if let Some(WindowElement::Wayland(ref window)) = workspaces.window_for_surface(surface) {
         let initial_configure_sent = with_states(surface, |states| {
             states
                 .data_map
@@ -257,6 +300,10 @@ pub fn handle_commit(popups: &mut PopupManager, space: &Space<WindowElement>, su
         if !initial_configure_sent {
             window.toplevel().send_configure();
         }
+
+        // the first size the client commits is the one it picked for itself,
+        // since the initial configure doesn't include a size
+        WindowElement::Wayland(window.clone()).save_preferred_size(window.geometry().size);
     }
 
     // Handle popup commits.
@@ -285,6 +332,121 @@ pub fn handle_commit(popups: &mut PopupManager, space: &Space<WindowElement>, su
 }
 
 impl<BackendData: Backend> Buddaraysh<BackendData> {
+    /// Sends the initial configure of a toplevel, honoring the maximized or fullscreen state
+    /// the client asked for before it, instead of mapping it normally first
+    pub fn initial_configure_toplevel(&mut self, surface: &ToplevelSurface) {
+        let (fullscreen, maximized, wl_output) = surface.with_pending_state(|state| {
+            (
+                state.states.contains(xdg_toplevel::State::Fullscreen),
+                state.states.contains(xdg_toplevel::State::Maximized),
+                state.fullscreen_output.clone(),
+            )
+        });
+
+        if fullscreen {
+            // only set once the window is actually fullscreened
+            surface.with_pending_state(|state| {
+                state.states.unset(xdg_toplevel::State::Fullscreen);
+                state.fullscreen_output = None;
+            });
+            self.fullscreen_toplevel(surface, wl_output);
+        } else if maximized {
+            self.maximize_toplevel(surface);
+        }
+
+        surface.send_configure();
+    }
+
+    /// Makes the toplevel cover the output, with the size of it as the pending state
+    fn fullscreen_toplevel(&mut self, surface: &ToplevelSurface, mut wl_output: Option<WlOutput>) {
+        // NOTE: This is only one part of the solution. We can set the
+        // location and configure size here, but the surface should be rendered fullscreen
+        // independently from its buffer size
+        let wl_surface = surface.wl_surface();
+
+        let output_geometry = fullscreen_output_geometry(
+            wl_surface,
+            wl_output.as_ref(),
+            self.workspaces.current_workspace_mut().space_mut(),
+        );
+        // the window might not be on the current workspace, e.g. when it was spawned elsewhere
+        let window = self.workspaces.window_for_surface(wl_surface);
+
+        let (Some(geometry), Some(window)) = (output_geometry, window) else {
+            return;
+        };
+        let Some(output) = wl_output
+            .as_ref()
+            .and_then(Output::from_resource)
+            .or_else(|| self.workspaces.outputs().next().cloned())
+        else {
+            return;
+        };
+        let client = self.display_handle.get_client(wl_surface.id()).unwrap();
+        for output in output.client_outputs(&client) {
+            wl_output = Some(output);
+        }
+        let workspace_index = self
+            .workspaces
+            .workspace_index_of(&window)
+            .unwrap_or_else(|| self.workspaces.current_workspace_index());
+
+        surface.with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Fullscreen);
+            state.size = Some(geometry.size);
+            state.fullscreen_output = wl_output;
+        });
+        self.release_fullscreen(&output, &window);
+        output
+            .user_data()
+            .insert_if_missing(FullscreenSurface::default);
+        output
+            .user_data()
+            .get::<FullscreenSurface>()
+            .unwrap()
+            .set(window.clone(), workspace_index);
+        trace!("Fullscreening: {:?}", window);
+    }
+
+    /// Makes the toplevel cover the output it's on, remembering where it was to restore it later
+    fn maximize_toplevel(&mut self, surface: &ToplevelSurface) {
+        let Some(window) = self.workspaces.window_for_surface(surface.wl_surface()) else {
+            return;
+        };
+        let Some(workspace_index) = self.workspaces.workspace_index_of(&window) else {
+            return;
+        };
+        let workspace = self.workspaces.workspaces()[workspace_index];
+
+        let Some(location) = workspace.window_location(&window) else {
+            return;
+        };
+        let old_geo = Rectangle::from_loc_and_size(location, window.geometry().size);
+        let Some(geometry) = workspace
+            .outputs_for_window(&window)
+            .first()
+            // The window hasn't been mapped yet, use the primary output instead
+            .or_else(|| self.workspaces.outputs().next())
+            .and_then(|output| maximized_geometry(workspace, output))
+        else {
+            return;
+        };
+
+        surface.with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Maximized);
+            state.size = Some(geometry.size);
+        });
+        window.user_data().insert_if_missing(OldGeometry::default);
+        window
+            .user_data()
+            .get::<OldGeometry>()
+            .unwrap()
+            .save(old_geo);
+        if let Some(workspace) = self.workspaces.get_mut(workspace_index) {
+            workspace.map_window(window, geometry.loc, false);
+        }
+    }
+
     pub fn move_request_xdg(
         &mut self,
         surface: &ToplevelSurface,
@@ -325,26 +487,31 @@ impl<BackendData: Backend> Buddaraysh<BackendData> {
             .states
             .contains(xdg_toplevel::State::Maximized)
         {
+            let old_geo = window
+                .user_data()
+                .get::<OldGeometry>()
+                .and_then(|data| data.restore());
+
             surface.with_pending_state(|state| {
                 state.states.unset(xdg_toplevel::State::Maximized);
-                state.size = None;
+                state.size = old_geo.map(|geo| geo.size);
             });
 
             surface.send_configure();
 
-            // NOTE: In real compositor mouse location should be mapped to a new window size
-            // For example, you could:
-            // 1) transform mouse pointer position from compositor space to window space (location relative)
-            // 2) divide the x coordinate by width of the window to get the percentage
-            //   - 0.0 would be on the far left of the window
-            //   - 0.5 would be in middle of the window
-            //   - 1.0 would be on the far right of the window
-            // 3) multiply the percentage by new window width
-            // 4) by doing that, drag will look a lot more natural
-            //
-            // but for now setting location to pointer location is fine
             let pos = pointer.current_location();
-            initial_window_location = (pos.x as i32, pos.y as i32).into();
+            initial_window_location = match old_geo {
+                // map the pointer location to the restored size, so the pointer stays
+                // over the same relative spot of the window and the drag looks natural
+                Some(old_geo) => {
+                    let maximized_geo = Rectangle::from_loc_and_size(
+                        initial_window_location,
+                        window.geometry().size,
+                    );
+                    unmaximized_window_location(pos, maximized_geo, old_geo.size)
+                }
+                None => (pos.x as i32, pos.y as i32).into(),
+            };
         }
 
         let grab = MoveSurfaceGrab {
@@ -414,6 +581,7 @@ impl<BackendData: Backend> Buddaraysh<BackendData> {
             *state.borrow_mut() = ResizeSurfaceState::Resizing {
                 edges,
                 initial_rect,
+                centered: false,
             };
         });
 
@@ -423,6 +591,7 @@ impl<BackendData: Backend> Buddaraysh<BackendData> {
             edges,
             initial_rect,
             last_window_size: initial_rect.size,
+            centered: false,
         };
 
         pointer.set_grab(self, grab, serial, Focus::Clear);
@@ -509,6 +678,13 @@ impl<BackendData: Backend + 'static> XdgActivationHandler for Buddaraysh<Backend
         surface: WlSurface,
     ) {
         debug!("activation request");
+
+        // a surface that isn't mapped yet is an app that is still starting up
+        if self.workspaces.window_for_surface(&surface).is_some() {
+            self.stop_startup_feedback();
+        } else {
+            self.start_startup_feedback();
+        }
     }
 }
 
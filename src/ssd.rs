@@ -21,6 +21,9 @@ pub struct WindowState {
     pub is_ssd: bool,
     pub ptr_entered_window: bool,
     pub header_bar: HeaderBar,
+    /// alpha of the dark overlay drawn on top of the window, None if it's not dimmed
+    pub dim_alpha: Option<f32>,
+    pub dim_overlay: SolidColorBuffer,
 }
 
 #[derive(Debug, Clone)]
@@ -236,6 +239,8 @@ impl WindowElement {
                     close_button: SolidColorBuffer::default(),
                     maximize_button: SolidColorBuffer::default(),
                 },
+                dim_alpha: None,
+                dim_overlay: SolidColorBuffer::default(),
             })
         });
 
@@ -1,29 +1,36 @@
 use std::{
+    collections::{HashMap, VecDeque},
     ffi::OsString,
     os::fd::OwnedFd,
     sync::{atomic::AtomicBool, Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use smithay::{
+    backend::input::TouchSlot,
     delegate_data_control, delegate_pointer_gestures, delegate_presentation,
-    delegate_primary_selection, delegate_relative_pointer,
+    delegate_primary_selection, delegate_relative_pointer, delegate_security_context,
+    delegate_viewporter,
     desktop::{layer_map_for_output, space::SpaceElement, PopupManager},
     input::{
-        pointer::{CursorImageStatus, PointerHandle},
+        keyboard::ModifiersState,
+        pointer::{CursorIcon, CursorImageStatus, PointerHandle},
         Seat, SeatState,
     },
+    output::Output,
     reexports::{
         calloop::{
-            generic::Generic, EventLoop, Interest, LoopHandle, LoopSignal, Mode, PostAction,
+            generic::Generic,
+            timer::{TimeoutAction, Timer},
+            EventLoop, Interest, LoopHandle, LoopSignal, Mode, PostAction, RegistrationToken,
         },
         wayland_server::{
             backend::{ClientData, ClientId, DisconnectReason},
             protocol::wl_surface::WlSurface,
-            Display, DisplayHandle,
+            Client, Display, DisplayHandle,
         },
     },
-    utils::{Clock, Logical, Monotonic, Point},
+    utils::{Clock, IsAlive, Logical, Monotonic, Point, Rectangle},
     wayland::{
         compositor::{CompositorClientState, CompositorState},
         keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitState,
@@ -32,6 +39,11 @@ use smithay::{
         pointer_gestures::PointerGesturesState,
         presentation::PresentationState,
         relative_pointer::RelativePointerManagerState,
+        seat::WaylandFocus,
+        security_context::{
+            SecurityContext, SecurityContextHandler, SecurityContextListenerSource,
+            SecurityContextState,
+        },
         selection::{
             data_device::{DataDeviceHandler, DataDeviceState},
             primary_selection::{PrimarySelectionHandler, PrimarySelectionState},
@@ -44,6 +56,7 @@ use smithay::{
         },
         shm::ShmState,
         socket::ListeningSocketSource,
+        viewporter::ViewporterState,
         xdg_activation::XdgActivationState,
     },
     xwayland::X11Surface,
@@ -59,8 +72,18 @@ use smithay::{
 };
 
 use crate::{
-    cursor::Cursor, focus::FocusTarget, shell::FullscreenSurface, window::WindowElement,
-    workspace::Workspaces, Backend, CalloopData,
+    clock::ClockConfig,
+    config::Config,
+    cursor::Cursor,
+    delegate_single_pixel_buffer_manager, env_flag, env_parse,
+    focus::FocusTarget,
+    ipc::IpcSocket,
+    is_truthy,
+    protocols::single_pixel_buffer::SinglePixelBufferState,
+    shell::{FullscreenSurface, UnmappedWindow, ZenWindow},
+    window::{sticky_copies, ClosedWindow, WindowElement},
+    workspace::Workspaces,
+    Backend, CalloopData, Direction, LogFilterHandle, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT,
 };
 
 pub struct Buddaraysh<BackendData: Backend + 'static> {
@@ -97,7 +120,79 @@ pub struct Buddaraysh<BackendData: Backend + 'static> {
     pub seat_name: String,
     pub seat: Seat<Self>,
     pub cursor_status: Arc<Mutex<CursorImageStatus>>,
+    /// busy cursor timer of an app that was launched but didn't map a window yet
+    pub startup_feedback: Option<RegistrationToken>,
+    pub startup_timeout: Duration,
+    pub pending_spawns: Vec<PendingSpawn>,
+    /// command spawned as the terminal
+    pub terminal: String,
+    pub clock_config: ClockConfig,
+    /// text of the clock overlay, None while it's hidden
+    pub clock_text: Option<String>,
+    /// refreshes the clock text every minute while it's shown
+    pub clock_timer: Option<RegistrationToken>,
+    /// the window zen mode shows alone over its output
+    pub zen: Option<ZenWindow>,
+    /// when the last input event came in, the auto-tidy timer waits for this to be long enough ago
+    pub last_input: Instant,
     pub pointer: PointerHandle<Buddaraysh<BackendData>>,
+    /// what was under the pointer where the last relative motion left it, only valid within
+    /// one batch of input events
+    pub pointer_under_cache: Option<(
+        Point<f64, Logical>,
+        Option<(FocusTarget, Point<i32, Logical>)>,
+    )>,
+    /// which output each touch screen is mapped to
+    pub touch_outputs: TouchOutputs,
+    /// output and surface each touch point went down on
+    pub touch_slots: HashMap<TouchSlot, TouchPoint>,
+
+    pub focus_dim: bool,
+    pub focus_dim_alpha: f32,
+    /// draws a grid of previews of the windows above everything else
+    pub show_window_preview: bool,
+    /// draws a grid over the output under the pointer and snaps moved and resized windows to it
+    pub grid_overlay: bool,
+    pub grid_size: i32,
+    pub dim_fullscreen: bool,
+    /// dims everything but the area around the pointer or the focused window
+    pub spotlight: bool,
+
+    /// keeps the keyboard focus on the current window until toggled off
+    pub focus_lock: bool,
+    /// forwards every key to the focused window, except the chord that turns it off
+    pub passthrough: bool,
+    /// name of the output the pointer is kept on, moving it with Super held takes it elsewhere
+    pub pointer_output_lock: Option<String>,
+    /// window focus-follows-mouse last saw under the pointer, focus only moves when it changes
+    pub hovered_window: Option<WindowElement>,
+
+    /// modifier and buttons that move and resize windows
+    pub mouse_bindings: MouseBindings,
+    /// keybinds from the config file
+    pub config: Config,
+    /// control socket for scripts, `None` if it couldn't be opened
+    pub ipc: Option<IpcSocket>,
+    pub print_commands: PrintCommands,
+    pub scroll_config: ScrollConfig,
+
+    /// marks the active output with a border, if there is more than one
+    pub active_output_indicator: Option<ActiveOutputMode>,
+
+    pub log_filter: LogFilterHandle,
+    pub verbose_logging: bool,
+
+    /// quit once a window of this app closes
+    pub kiosk_app: Option<String>,
+    /// quit once the last window closes
+    pub quit_on_empty: bool,
+    /// the last few closed windows, oldest first
+    pub recently_closed: VecDeque<ClosedWindow>,
+    /// toplevels that committed a null buffer, until they attach a buffer again
+    pub unmapped_windows: Vec<UnmappedWindow>,
+
+    /// app ids of sandboxed clients that are still allowed to use screencopy and data-control
+    pub sandbox_allowlist: Vec<String>,
 
     #[cfg(feature = "xwayland")]
     pub xwayland: XWayland,
@@ -107,12 +202,424 @@ pub struct Buddaraysh<BackendData: Backend + 'static> {
     pub xdisplay: Option<u32>,
 }
 
+/// What decides which output is the active one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActiveOutputMode {
+    /// the output under the pointer
+    Pointer,
+    /// the output of the focused window, or the pointer if no window is focused
+    Focus,
+}
+
+/// What the spotlight follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpotlightTarget {
+    Pointer,
+    /// the focused window, nothing is dimmed while no window is focused
+    Focus,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SpotlightConfig {
+    pub target: SpotlightTarget,
+    /// how far the lit area reaches from the pointer, or past the edges of the focused window
+    pub radius: i32,
+    pub alpha: f32,
+}
+
+impl Default for SpotlightConfig {
+    fn default() -> Self {
+        Self {
+            target: SpotlightTarget::Pointer,
+            radius: 200,
+            alpha: 0.6,
+        }
+    }
+}
+
+/// Commands spawned by the Print key, alone and with shift or ctrl held
+#[derive(Debug, Clone)]
+pub struct PrintCommands {
+    pub print: Option<String>,
+    pub shift_print: Option<String>,
+    pub ctrl_print: Option<String>,
+}
+
+impl PrintCommands {
+    pub fn from_env() -> Self {
+        // setting a variable to an empty string unbinds the key
+        let command = |var: &str, default: Option<&str>| match std::env::var(var) {
+            Ok(x) => Some(x.trim().to_owned()).filter(|x| !x.is_empty()),
+            Err(_) => default.map(String::from),
+        };
+
+        Self {
+            print: command(
+                "BUD_PRINT_COMMAND",
+                Some("grimblast --freeze copysave area ~/Pictures/$(date +%Y-%m-%d_%H-%m-%s).png"),
+            ),
+            shift_print: command("BUD_SHIFT_PRINT_COMMAND", None),
+            ctrl_print: command("BUD_CTRL_PRINT_COMMAND", None),
+        }
+    }
+
+    /// The command to spawn for Print with the given modifiers held, if any
+    pub fn command_for(&self, modifiers: &ModifiersState) -> Option<&str> {
+        match (modifiers.shift, modifiers.ctrl) {
+            (false, false) => self.print.as_deref(),
+            (true, false) => self.shift_print.as_deref(),
+            (false, true) => self.ctrl_print.as_deref(),
+            (true, true) => None,
+        }
+    }
+}
+
+/// Modifier that has to be held to move or resize windows with the mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseModifier {
+    Logo,
+    Alt,
+    Ctrl,
+    Shift,
+}
+
+impl MouseModifier {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "logo" | "super" => Some(MouseModifier::Logo),
+            "alt" => Some(MouseModifier::Alt),
+            "ctrl" => Some(MouseModifier::Ctrl),
+            "shift" => Some(MouseModifier::Shift),
+            _ => None,
+        }
+    }
+
+    pub fn is_pressed(&self, modifiers: &ModifiersState) -> bool {
+        match self {
+            MouseModifier::Logo => modifiers.logo,
+            MouseModifier::Alt => modifiers.alt,
+            MouseModifier::Ctrl => modifiers.ctrl,
+            MouseModifier::Shift => modifiers.shift,
+        }
+    }
+}
+
+/// Modifier and buttons of the move/resize mouse bindings, and buttons bound to actions
+#[derive(Debug, Clone)]
+pub struct MouseBindings {
+    pub modifier: MouseModifier,
+    pub move_button: u32,
+    pub resize_button: u32,
+    /// holding it while resizing moves the opposite edges too, keeping the window centered
+    pub center_resize_modifier: MouseModifier,
+    actions: Vec<(u32, Action)>,
+}
+
+impl MouseBindings {
+    pub fn from_env() -> Self {
+        let modifier = std::env::var("BUD_MOUSE_MODIFIER")
+            .ok()
+            .and_then(|x| {
+                let modifier = MouseModifier::parse(&x);
+                if modifier.is_none() {
+                    tracing::warn!(modifier = %x, "Unknown mouse modifier, using logo");
+                }
+                modifier
+            })
+            .unwrap_or(MouseModifier::Logo);
+
+        let center_resize_modifier = std::env::var("BUD_CENTER_RESIZE_MODIFIER")
+            .ok()
+            .and_then(|x| {
+                let modifier = MouseModifier::parse(&x);
+                if modifier.is_none() {
+                    tracing::warn!(modifier = %x, "Unknown center resize modifier, using ctrl");
+                }
+                modifier
+            })
+            .unwrap_or(MouseModifier::Ctrl);
+
+        let move_button = std::env::var("BUD_MOVE_BUTTON")
+            .ok()
+            .and_then(|x| parse_button(&x))
+            .unwrap_or(BTN_LEFT);
+
+        let resize_button = std::env::var("BUD_RESIZE_BUTTON")
+            .ok()
+            .and_then(|x| parse_button(&x))
+            .unwrap_or(BTN_RIGHT);
+
+        // e.g. "side=prev-workspace,extra=next-workspace"
+        let actions = std::env::var("BUD_MOUSE_ACTIONS")
+            .map(|x| {
+                x.split(',')
+                    .filter(|binding| !binding.trim().is_empty())
+                    .filter_map(|binding| {
+                        let parsed = binding.split_once('=').and_then(|(button, action)| {
+                            Some((parse_button(button.trim())?, parse_action(action.trim())?))
+                        });
+                        if parsed.is_none() {
+                            tracing::warn!(binding, "Invalid mouse action binding");
+                        }
+                        parsed
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            modifier,
+            move_button,
+            resize_button,
+            center_resize_modifier,
+            actions,
+        }
+    }
+
+    /// The action bound to `button`, if any
+    pub fn action_for(&self, button: u32) -> Option<Action> {
+        self.actions
+            .iter()
+            .find(|(b, _)| *b == button)
+            .map(|(_, action)| action.clone())
+    }
+}
+
+/// Scroll speed and which devices scroll the other way around
+#[derive(Debug, Clone)]
+pub struct ScrollConfig {
+    pub factor: f64,
+    natural_scroll: NaturalScroll,
+}
+
+#[derive(Debug, Clone)]
+enum NaturalScroll {
+    All,
+    /// names of the devices with natural scrolling
+    Devices(Vec<String>),
+}
+
+impl ScrollConfig {
+    pub fn from_env() -> Self {
+        let factor = env_parse::<f64>("BUD_SCROLL_FACTOR")
+            .filter(|x| x.is_finite() && *x > 0.0)
+            .unwrap_or(1.0);
+
+        let natural_scroll = match std::env::var("BUD_NATURAL_SCROLL") {
+            Ok(x) if is_truthy(&x) => NaturalScroll::All,
+            Ok(x) => NaturalScroll::Devices(
+                x.split(',')
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect(),
+            ),
+            Err(_) => NaturalScroll::Devices(Vec::new()),
+        };
+
+        Self {
+            factor,
+            natural_scroll,
+        }
+    }
+
+    pub fn is_natural(&self, device_name: &str) -> bool {
+        match &self.natural_scroll {
+            NaturalScroll::All => true,
+            NaturalScroll::Devices(names) => names.iter().any(|name| name == device_name),
+        }
+    }
+}
+
+/// Outputs touch screens are mapped to, a touch screen only ever covers one output
+#[derive(Debug, Clone, Default)]
+pub struct TouchOutputs {
+    /// output of the touch screens without an entry of their own
+    default: Option<String>,
+    /// device name and output name
+    devices: Vec<(String, String)>,
+}
+
+impl TouchOutputs {
+    pub fn from_env() -> Self {
+        std::env::var("BUD_TOUCH_OUTPUT")
+            .map(|x| Self::parse(&x))
+            .unwrap_or_default()
+    }
+
+    /// Parses `<output>` or a list of `<device>=<output>` entries, the bare output being the
+    /// default for the other devices
+    fn parse(value: &str) -> Self {
+        let mut touch_outputs = Self::default();
+        for entry in value.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            match entry.split_once('=') {
+                Some((device, output)) => touch_outputs
+                    .devices
+                    .push((device.trim().to_owned(), output.trim().to_owned())),
+                None => touch_outputs.default = Some(entry.to_owned()),
+            }
+        }
+        touch_outputs
+    }
+
+    pub fn output_for(&self, device_name: &str) -> Option<&str> {
+        self.devices
+            .iter()
+            .find(|(device, _)| device == device_name)
+            .map(|(_, output)| output.as_str())
+            .or(self.default.as_deref())
+    }
+}
+
+/// A touch point that went down on a surface
+#[derive(Debug, Clone)]
+pub struct TouchPoint {
+    /// output the touch screen was mapped to when the point went down
+    pub output: Output,
+    /// location of the surface in the global space
+    pub surface_location: Point<f64, Logical>,
+}
+
+/// Parses an action of `BUD_MOUSE_ACTIONS` or a keybind of the config file
+pub fn parse_action(name: &str) -> Option<Action> {
+    let (name, arg) = match name.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (name, None),
+    };
+
+    let action = match (name, arg) {
+        ("next-workspace", None) => Action::SwitchToNextWorkspace,
+        ("prev-workspace", None) => Action::SwitchToPreviousWorkspace,
+        // workspaces are numbered from 1, like their keybinds
+        ("workspace", Some(index)) => {
+            Action::SwitchToWorkspace(index.parse::<usize>().ok()?.checked_sub(1)?)
+        }
+        ("spawn", Some(cmd)) => Action::Spawn(cmd.to_string()),
+        ("spawn-on", Some(argument)) => {
+            let (index, cmd) = argument.split_once(':')?;
+            Action::SpawnOnWorkspace {
+                cmd: cmd.to_string(),
+                workspace: index.trim().parse::<usize>().ok()?.checked_sub(1)?,
+            }
+        }
+        ("close", None) => Action::Close,
+        ("fullscreen", None) => Action::ToggleFullscreen,
+        ("window-preview", None) => Action::ToggleWindowPreview,
+        ("summon", Some(query)) => Action::SummonWindow(query.to_string()),
+        ("evacuate", Some(output)) => Action::EvacuateOutput(output.to_string()),
+        ("swap-outputs", None) => Action::SwapOutputs,
+        ("toggle-output", Some(output)) => Action::ToggleOutputEnabled(output.to_string()),
+        ("dump-frame", None) => Action::DumpFrame,
+        ("scale", Some(argument)) => {
+            let (output, scale) = argument.split_once('=')?;
+            Action::SetOutputScale {
+                output: output.trim().to_owned(),
+                scale: scale.trim().parse().ok()?,
+            }
+        }
+        ("focus", Some(direction)) => Action::FocusDirection(parse_direction(direction)?),
+        ("move", Some(direction)) => Action::MoveWindowDirection(parse_direction(direction)?),
+        ("save-preset", Some(name)) => Action::SaveLayoutPreset(name.to_string()),
+        ("load-preset", Some(name)) => Action::LoadLayoutPreset(name.to_string()),
+        ("move-to-workspace", Some(index)) => {
+            Action::MoveToWorkspace(index.parse::<usize>().ok()?.checked_sub(1)?)
+        }
+        ("merge-into", Some(index)) => {
+            Action::MergeWorkspaceInto(index.parse::<usize>().ok()?.checked_sub(1)?)
+        }
+        ("quit", None) => Action::Quit,
+        ("notify-last-closed", None) => Action::NotifyLastClosed,
+        ("debug-logging", None) => Action::ToggleDebugLogging,
+        ("focus-other-output", None) => Action::FocusLastOnOtherOutput,
+        ("focus-layer", None) => Action::FocusLayerOrWindow,
+        ("passthrough", None) => Action::TogglePassthrough,
+        ("terminal-here", None) => Action::SpawnTerminalHere,
+        ("focus-dim", None) => Action::ToggleFocusDim,
+        ("focus-lock", None) => Action::ToggleFocusLock,
+        ("clock", None) => Action::ToggleClock,
+        ("sticky", None) => Action::ToggleStickyAllOutputs,
+        ("grid", None) => Action::ToggleGridOverlay,
+        ("zen", None) => Action::ToggleZen,
+        ("spotlight", None) => Action::ToggleSpotlight,
+        ("confine-pointer", None) => Action::ToggleSingleOutputPointer,
+        ("reset-size", None) => Action::ResetSize,
+        ("reset-window-state", None) => Action::ResetWindowState,
+        ("next-layout", None) => Action::NextInputSource,
+        ("reload-config", None) => Action::ReloadConfig,
+        _ => return None,
+    };
+
+    Some(action)
+}
+
+fn parse_direction(name: &str) -> Option<Direction> {
+    match name {
+        "left" => Some(Direction::Left),
+        "right" => Some(Direction::Right),
+        "up" => Some(Direction::Up),
+        "down" => Some(Direction::Down),
+        _ => None,
+    }
+}
+
+/// Parses a button name, or a raw button code from linux/input-event-codes.h
+pub fn parse_button(name: &str) -> Option<u32> {
+    match name.to_lowercase().as_str() {
+        "left" => Some(BTN_LEFT),
+        "right" => Some(BTN_RIGHT),
+        "middle" => Some(BTN_MIDDLE),
+        "side" => Some(BTN_SIDE),
+        "extra" => Some(BTN_EXTRA),
+        "forward" => Some(BTN_FORWARD),
+        "back" => Some(BTN_BACK),
+        code => {
+            let code = code.strip_prefix("0x").map_or_else(
+                || code.parse::<u32>().ok(),
+                |hex| u32::from_str_radix(hex, 16).ok(),
+            );
+            if code.is_none() {
+                tracing::warn!(button = name, "Unknown mouse button");
+            }
+            code
+        }
+    }
+}
+
+/// How long a spawned program has to map its first window
+const PENDING_SPAWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A program spawned with `Action::SpawnOnWorkspace` that didn't map a window yet
+#[derive(Debug)]
+pub struct PendingSpawn {
+    pid: u32,
+    workspace_index: usize,
+    spawned_at: Instant,
+}
+
+impl PendingSpawn {
+    pub fn new(pid: u32, workspace_index: usize) -> Self {
+        Self {
+            pid,
+            workspace_index,
+            spawned_at: Instant::now(),
+        }
+    }
+}
+
+/// Reads the parent of `pid` from procfs
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // the command name can contain spaces, the fields after it are "state ppid ..."
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
 impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
     pub fn new(
         loop_handle: LoopHandle<'static, CalloopData<BackendData>>,
         event_loop: &mut EventLoop<CalloopData<BackendData>>,
         display: Display<Self>,
         backend_data: BackendData,
+        log_filter: LogFilterHandle,
     ) -> Self {
         let start_time = std::time::Instant::now();
 
@@ -125,11 +632,24 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
         let xdg_activation_state = XdgActivationState::new::<Self>(&display_handle);
         let wlr_layer_shell_state = WlrLayerShellState::new::<Self>(&display_handle);
         let primary_selection_state = PrimarySelectionState::new::<Self>(&display_handle);
-        let data_control_state = DataControlState::new::<Self, _>(
-            &display_handle,
-            Some(&primary_selection_state),
-            |_| true,
-        );
+        let sandbox_allowlist: Vec<String> = std::env::var("BUD_SANDBOX_ALLOWLIST")
+            .map(|x| {
+                x.split(',')
+                    .map(|app_id| app_id.trim().to_string())
+                    .filter(|app_id| !app_id.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // sandboxed clients could use data-control to snoop on the clipboard
+        let data_control_state = {
+            let sandbox_allowlist = sandbox_allowlist.clone();
+            DataControlState::new::<Self, _>(
+                &display_handle,
+                Some(&primary_selection_state),
+                move |client| client_is_privileged(client, &sandbox_allowlist),
+            )
+        };
         let shm_state = ShmState::new::<Self>(&display_handle, vec![]);
         let output_manager_state = OutputManagerState::new_with_xdg_output::<Self>(&display_handle);
         let mut seat_state = SeatState::new();
@@ -140,6 +660,14 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
             KeyboardShortcutsInhibitState::new::<Self>(&display_handle);
         PointerConstraintsState::new::<Self>(&display_handle);
         RelativePointerManagerState::new::<Self>(&display_handle);
+        ViewporterState::new::<Self>(&display_handle);
+        SinglePixelBufferState::new::<Self>(&display_handle);
+        // sandboxed clients shouldn't be able to create new security contexts
+        SecurityContextState::new::<Self, _>(&display_handle, |client| {
+            client
+                .get_data::<ClientState>()
+                .map_or(true, |client_state| client_state.security_context.is_none())
+        });
         PointerGesturesState::new::<Self>(&display_handle);
 
         // A seat is a group of keyboards, pointer and touch devices.
@@ -149,16 +677,9 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
 
         // Notify clients that we have a keyboard, for the sake of the example we assume that keyboard is always present.
         // You may want to track keyboard hot-plug in real compositor.
-        seat.add_keyboard(
-            smithay::input::keyboard::XkbConfig {
-                layout: "us,ara",
-                options: Some(String::from("grp:alt_shift_toggle")),
-                ..Default::default()
-            },
-            500,
-            25,
-        )
-        .unwrap();
+        let config = Config::load();
+        seat.add_keyboard(config.keyboard.xkb_config(), 500, 25)
+            .unwrap();
 
         // Notify clients that we have a pointer (mouse)
         // Here we assume that there is always pointer plugged in
@@ -173,12 +694,71 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
         let socket_name = Self::init_wayland_listener(display, event_loop);
 
         std::env::set_var("WAYLAND_DISPLAY", &socket_name);
+        let ipc = IpcSocket::new(&loop_handle, &socket_name);
 
         // Get the loop signal, used to stop the event loop
         let loop_signal = event_loop.get_signal();
 
         let cursor_status = Arc::new(Mutex::new(CursorImageStatus::default_named()));
 
+        let startup_timeout = env_parse::<u64>("BUD_STARTUP_TIMEOUT")
+            .map(Duration::from_millis)
+            .unwrap_or(Duration::from_secs(5));
+
+        let terminal = std::env::var("BUD_TERMINAL")
+            .ok()
+            .filter(|terminal| !terminal.is_empty())
+            .unwrap_or_else(|| String::from("kitty"));
+
+        // seconds without input after which stray windows get moved back into view
+        if let Some(period) = env_parse::<u64>("BUD_AUTO_TIDY")
+            .filter(|x| *x > 0)
+            .map(Duration::from_secs)
+        {
+            event_loop
+                .handle()
+                .insert_source(Timer::from_duration(period), move |_, _, data| {
+                    let idle = data.state.last_input.elapsed();
+                    if idle < period {
+                        return TimeoutAction::ToDuration(period - idle);
+                    }
+
+                    data.state.tidy_windows();
+                    TimeoutAction::ToDuration(period)
+                })
+                .expect("failed to schedule auto-tidy timer");
+        }
+
+        let focus_dim_alpha = env_parse::<f32>("BUD_DIM_ALPHA")
+            .map(|x| x.clamp(0.0, 1.0))
+            .unwrap_or(0.3);
+
+        let dim_fullscreen = env_flag("BUD_DIM_FULLSCREEN");
+
+        let touch_outputs = TouchOutputs::from_env();
+
+        let active_output_indicator =
+            std::env::var("BUD_ACTIVE_OUTPUT_BORDER")
+                .ok()
+                .and_then(|x| match x.to_lowercase().as_str() {
+                    "pointer" => Some(ActiveOutputMode::Pointer),
+                    "focus" => Some(ActiveOutputMode::Focus),
+                    _ => None,
+                });
+
+        let grid_size = env_parse::<i32>("BUD_GRID_SIZE")
+            .map(|x| x.max(4))
+            .unwrap_or(32);
+
+        let mouse_bindings = MouseBindings::from_env();
+        let scroll_config = ScrollConfig::from_env();
+
+        let kiosk_app = std::env::var("BUD_KIOSK_APP")
+            .ok()
+            .filter(|app_id| !app_id.is_empty());
+
+        let quit_on_empty = env_flag("BUD_QUIT_ON_EMPTY");
+
         #[cfg(feature = "xwayland")]
         let xwayland = {
             XWaylandKeyboardGrabState::new::<Self>(&display_handle);
@@ -227,7 +807,7 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
             start_time,
             display_handle,
 
-            workspaces: Workspaces::default(),
+            workspaces: Workspaces::new(config.workspaces),
             override_redirect_windows: Vec::new(),
             loop_signal,
             socket_name,
@@ -253,8 +833,44 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
             loop_handle,
             clock,
             pointer,
+            touch_outputs,
+            touch_slots: HashMap::new(),
             cursor_status,
+            startup_feedback: None,
+            startup_timeout,
+            pending_spawns: Vec::new(),
+            terminal,
+            clock_config: ClockConfig::from_env(),
+            clock_text: None,
+            clock_timer: None,
+            zen: None,
+            last_input: Instant::now(),
+            pointer_under_cache: None,
             seat_name,
+            focus_dim: false,
+            focus_dim_alpha,
+            show_window_preview: false,
+            grid_overlay: false,
+            grid_size,
+            dim_fullscreen,
+            spotlight: false,
+            focus_lock: false,
+            passthrough: false,
+            pointer_output_lock: None,
+            hovered_window: None,
+            mouse_bindings,
+            config,
+            ipc,
+            print_commands: PrintCommands::from_env(),
+            scroll_config,
+            active_output_indicator,
+            log_filter,
+            verbose_logging: false,
+            kiosk_app,
+            quit_on_empty,
+            recently_closed: VecDeque::new(),
+            unmapped_windows: Vec::new(),
+            sandbox_allowlist,
             running: Arc::new(AtomicBool::new(true)),
             #[cfg(feature = "xwayland")]
             xwayland,
@@ -311,10 +927,30 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
         socket_name
     }
 
+    /// [`Self::surface_under`] for the start of a relative pointer motion, reuses the lookup the
+    /// previous motion event did at its end if the pointer is still where it left it
+    pub fn pointer_surface_under(
+        &mut self,
+        pos: Point<f64, Logical>,
+    ) -> Option<(FocusTarget, Point<i32, Logical>)> {
+        let cached = self
+            .pointer_under_cache
+            .as_ref()
+            .filter(|(location, under)| {
+                *location == pos && under.as_ref().map_or(true, |(target, _)| target.alive())
+            });
+        if let Some((_, under)) = cached {
+            return under.clone();
+        }
+
+        self.surface_under(pos)
+    }
+
     pub fn surface_under(
         &self,
         pos: Point<f64, Logical>,
     ) -> Option<(FocusTarget, Point<i32, Logical>)> {
+        profiling::scope!("surface_under");
         let output = self.workspaces.outputs().find(|o| {
             let geometry = self
                 .workspaces
@@ -330,20 +966,18 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
             .unwrap();
         let layers = layer_map_for_output(output);
 
-        if let Some(window) =
-            output
-                .user_data()
-                .get::<FullscreenSurface>()
-                .and_then(|f| match f.get() {
-                    (Some(window), Some(workspace_index))
-                        if workspace_index == self.workspaces.current_workspace_index() =>
-                    {
-                        Some(window)
-                    }
-                    _ => None,
-                })
+        if let Some(window) = output
+            .user_data()
+            .get::<FullscreenSurface>()
+            .and_then(|f| f.visible_on(self.workspaces.current_workspace_index()))
         {
             return Some((window.into(), output_geo.loc));
+        } else if let Some(window) = self
+            .zen
+            .as_ref()
+            .and_then(|zen| zen.window_on(output, self.workspaces.current_workspace_index()))
+        {
+            return Some((window.clone().into(), output_geo.loc));
         } else if let Some(layer) = layers
             .layer_under(WlrLayer::Overlay, pos)
             .or_else(|| layers.layer_under(WlrLayer::Top, pos))
@@ -357,6 +991,17 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
         {
             let window = FocusTarget::Window(WindowElement::X11(or.clone()));
             return Some((window, output_geo.loc + or.geometry().loc));
+        } else if let Some((window, location)) =
+            sticky_copies(self.workspaces.current_workspace().space(), output).find_map(
+                |(window, location)| {
+                    let render_location = output_geo.loc + location - window.geometry().loc;
+                    window
+                        .is_in_input_region(&(pos - render_location.to_f64()))
+                        .then_some((window, render_location))
+                },
+            )
+        {
+            return Some((window.clone().into(), location));
         } else if let Some((window, location)) =
             self.workspaces.current_workspace().window_under(pos)
         {
@@ -371,6 +1016,143 @@ impl<BackendData: Backend + 'static> Buddaraysh<BackendData> {
 
         None
     }
+
+    /// The output that should get the active output indicator, if it's enabled
+    pub fn active_output(&self) -> Option<Output> {
+        let mode = self.active_output_indicator?;
+        if self.workspaces.outputs().nth(1).is_none() {
+            return None;
+        }
+
+        let focused_output = (mode == ActiveOutputMode::Focus)
+            .then(|| {
+                let window = self
+                    .seat
+                    .get_keyboard()?
+                    .current_focus()?
+                    .wl_surface()
+                    .and_then(|surface| self.window_for_surface(&surface))?;
+                self.workspaces
+                    .current_workspace()
+                    .outputs_for_window(&window)
+                    .into_iter()
+                    .next()
+            })
+            .flatten();
+
+        focused_output.or_else(|| {
+            self.workspaces
+                .output_under(self.pointer.current_location())
+                .next()
+                .cloned()
+        })
+    }
+
+    /// Grid size windows snap to while the grid overlay is shown
+    pub fn snap_grid(&self) -> Option<i32> {
+        self.grid_overlay.then_some(self.grid_size)
+    }
+
+    /// The output the grid overlay is drawn on, the one under the pointer
+    pub fn grid_output(&self) -> Option<Output> {
+        if !self.grid_overlay {
+            return None;
+        }
+
+        self.workspaces
+            .output_under(self.pointer.current_location())
+            .next()
+            .cloned()
+    }
+
+    /// The area the spotlight leaves undimmed, in global coordinates
+    pub fn spotlight_area(&self) -> Option<Rectangle<i32, Logical>> {
+        if !self.spotlight {
+            return None;
+        }
+
+        let radius = self.config.spotlight.radius;
+        match self.config.spotlight.target {
+            SpotlightTarget::Pointer => {
+                let location = self.pointer.current_location().to_i32_round();
+                Some(Rectangle::from_loc_and_size(
+                    (location.x - radius, location.y - radius),
+                    (radius * 2, radius * 2),
+                ))
+            }
+            SpotlightTarget::Focus => {
+                let window = self
+                    .seat
+                    .get_keyboard()?
+                    .current_focus()?
+                    .wl_surface()
+                    .and_then(|surface| self.window_for_surface(&surface))?;
+                let mut geometry = self
+                    .workspaces
+                    .current_workspace()
+                    .space()
+                    .element_geometry(&window)?;
+                geometry.loc -= (radius, radius).into();
+                geometry.size += (radius * 2, radius * 2).into();
+                Some(geometry)
+            }
+        }
+    }
+
+    /// Workspace a new window of the process `pid` should go to, if it was spawned for one.
+    ///
+    /// Programs are spawned through `sh -c`, so the ancestors of `pid` are checked too
+    pub fn take_pending_spawn(&mut self, pid: Option<u32>) -> Option<usize> {
+        self.pending_spawns
+            .retain(|spawn| spawn.spawned_at.elapsed() < PENDING_SPAWN_TIMEOUT);
+
+        let mut pid = pid?;
+        loop {
+            if let Some(index) = self.pending_spawns.iter().position(|s| s.pid == pid) {
+                return Some(self.pending_spawns.remove(index).workspace_index);
+            }
+            pid = parent_pid(pid).filter(|&ppid| ppid > 1)?;
+        }
+    }
+
+    /// Shows a busy cursor until the launched app maps a window or `startup_timeout` elapses
+    pub fn start_startup_feedback(&mut self) {
+        if self.startup_timeout.is_zero() {
+            return;
+        }
+
+        if let Some(token) = self.startup_feedback.take() {
+            self.loop_handle.remove(token);
+        }
+
+        let token = self
+            .loop_handle
+            .insert_source(Timer::from_duration(self.startup_timeout), |_, _, data| {
+                // the timer is dropped once we return, so there's nothing to remove
+                data.state.startup_feedback = None;
+                data.state.stop_startup_feedback();
+                TimeoutAction::Drop
+            })
+            .expect("failed to schedule startup feedback timer");
+        self.startup_feedback = Some(token);
+
+        let mut cursor_status = self.cursor_status.lock().unwrap();
+        // clients keep their own cursor while the pointer is over them
+        if let CursorImageStatus::Named(CursorIcon::Default) = *cursor_status {
+            *cursor_status = CursorImageStatus::Named(CursorIcon::Progress);
+        }
+    }
+
+    pub fn stop_startup_feedback(&mut self) {
+        if let Some(token) = self.startup_feedback.take() {
+            self.loop_handle.remove(token);
+        }
+
+        let mut cursor_status = self.cursor_status.lock().unwrap();
+        if let CursorImageStatus::Named(CursorIcon::Progress) = *cursor_status {
+            *cursor_status = CursorImageStatus::default_named();
+        }
+    }
 }
 
 impl<BackendData: Backend> PrimarySelectionHandler for Buddaraysh<BackendData> {
@@ -389,9 +1171,52 @@ impl<BackendData: Backend> DataControlHandler for Buddaraysh<BackendData> {
 }
 delegate_data_control!(@<BackendData: Backend + 'static> Buddaraysh<BackendData>);
 
+impl<BackendData: Backend> SecurityContextHandler for Buddaraysh<BackendData> {
+    fn context_created(
+        &mut self,
+        source: SecurityContextListenerSource,
+        security_context: SecurityContext,
+    ) {
+        // clients connecting through this socket are tagged with the sandbox's security context
+        self.loop_handle
+            .insert_source(source, move |client_stream, _, data| {
+                let client_state = ClientState {
+                    security_context: Some(security_context.clone()),
+                    ..ClientState::default()
+                };
+                if let Err(err) = data
+                    .display_handle
+                    .insert_client(client_stream, Arc::new(client_state))
+                {
+                    tracing::warn!("Error adding wayland client: {}", err);
+                }
+            })
+            .expect("Failed to init the security context socket source.");
+    }
+}
+delegate_security_context!(@<BackendData: Backend + 'static> Buddaraysh<BackendData>);
+
+/// Returns true if the client isn't sandboxed, or its sandbox app id is in the allowlist
+pub fn client_is_privileged(client: &Client, sandbox_allowlist: &[String]) -> bool {
+    let Some(security_context) = client
+        .get_data::<ClientState>()
+        .and_then(|client_state| client_state.security_context.as_ref())
+    else {
+        return true;
+    };
+
+    security_context
+        .app_id
+        .as_ref()
+        .map(|app_id| sandbox_allowlist.contains(app_id))
+        .unwrap_or(false)
+}
+
 #[derive(Default)]
 pub struct ClientState {
     pub compositor_state: CompositorClientState,
+    /// Set if the client connected through a sandbox's security context socket
+    pub security_context: Option<SecurityContext>,
 }
 
 impl ClientData for ClientState {
@@ -464,3 +1289,101 @@ impl<BackendData: Backend + 'static> DataDeviceHandler for Buddaraysh<BackendDat
 delegate_pointer_gestures!(@<BackendData: Backend + 'static> Buddaraysh<BackendData>);
 delegate_presentation!(@<BackendData: Backend + 'static> Buddaraysh<BackendData>);
 delegate_relative_pointer!(@<BackendData: Backend + 'static> Buddaraysh<BackendData>);
+delegate_viewporter!(@<BackendData: Backend + 'static> Buddaraysh<BackendData>);
+delegate_single_pixel_buffer_manager!(@<BackendData: Backend + 'static> Buddaraysh<BackendData>);
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_action, parse_button, TouchOutputs};
+    use crate::{Action, Direction, BTN_LEFT, BTN_SIDE};
+
+    #[test]
+    fn parse_action_without_argument() {
+        assert!(matches!(parse_action("close"), Some(Action::Close)));
+        assert!(matches!(
+            parse_action("reload-config"),
+            Some(Action::ReloadConfig)
+        ));
+        assert!(parse_action("close:now").is_none());
+        assert!(parse_action("no-such-action").is_none());
+    }
+
+    #[test]
+    fn parse_action_workspaces_count_from_one() {
+        assert!(matches!(
+            parse_action("workspace:1"),
+            Some(Action::SwitchToWorkspace(0))
+        ));
+        assert!(matches!(
+            parse_action("merge-into:3"),
+            Some(Action::MergeWorkspaceInto(2))
+        ));
+        assert!(parse_action("workspace:0").is_none());
+        assert!(parse_action("workspace").is_none());
+    }
+
+    #[test]
+    fn parse_action_spawn_keeps_colons_in_the_command() {
+        assert!(matches!(
+            parse_action("spawn:foot -e sh -c 'sleep 1; echo a:b'"),
+            Some(Action::Spawn(cmd)) if cmd == "foot -e sh -c 'sleep 1; echo a:b'"
+        ));
+        assert!(matches!(
+            parse_action("spawn-on:2:firefox http://localhost"),
+            Some(Action::SpawnOnWorkspace { cmd, workspace: 1 }) if cmd == "firefox http://localhost"
+        ));
+        assert!(parse_action("spawn-on:firefox").is_none());
+    }
+
+    #[test]
+    fn parse_action_with_arguments() {
+        assert!(matches!(
+            parse_action("focus:left"),
+            Some(Action::FocusDirection(Direction::Left))
+        ));
+        assert!(parse_action("focus:sideways").is_none());
+        assert!(matches!(
+            parse_action("scale:DP-1=1.5"),
+            Some(Action::SetOutputScale { output, scale }) if output == "DP-1" && scale == 1.5
+        ));
+        assert!(matches!(
+            parse_action("toggle-output:eDP-1"),
+            Some(Action::ToggleOutputEnabled(output)) if output == "eDP-1"
+        ));
+        assert!(matches!(
+            parse_action("save-preset:work"),
+            Some(Action::SaveLayoutPreset(name)) if name == "work"
+        ));
+    }
+
+    #[test]
+    fn parse_button_names_and_codes() {
+        assert_eq!(parse_button("left"), Some(BTN_LEFT));
+        assert_eq!(parse_button("Side"), Some(BTN_SIDE));
+        assert_eq!(parse_button("275"), Some(BTN_SIDE));
+        assert_eq!(parse_button("0x113"), Some(BTN_SIDE));
+        assert_eq!(parse_button("thumb"), None);
+    }
+
+    #[test]
+    fn touch_outputs_bare_output_is_the_default() {
+        let touch_outputs = TouchOutputs::parse("eDP-1");
+
+        assert_eq!(touch_outputs.output_for("ELAN Touchscreen"), Some("eDP-1"));
+    }
+
+    #[test]
+    fn touch_outputs_devices_get_their_own_output() {
+        let touch_outputs = TouchOutputs::parse("eDP-1, Wacom Pen = DP-1");
+
+        assert_eq!(touch_outputs.output_for("Wacom Pen"), Some("DP-1"));
+        assert_eq!(touch_outputs.output_for("ELAN Touchscreen"), Some("eDP-1"));
+    }
+
+    #[test]
+    fn touch_outputs_without_a_default() {
+        let touch_outputs = TouchOutputs::parse("Wacom Pen=DP-1");
+
+        assert_eq!(touch_outputs.output_for("ELAN Touchscreen"), None);
+    }
+}
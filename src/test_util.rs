@@ -0,0 +1,25 @@
+use smithay::output::{Mode, Output, PhysicalProperties, Scale, Subpixel};
+
+/// An output with a current mode, scale and position, like the backends set them up
+pub fn output(name: &str, size: (i32, i32), location: (i32, i32), scale: f64) -> Output {
+    let output = Output::new(
+        name.to_string(),
+        PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: Subpixel::Unknown,
+            make: String::new(),
+            model: String::new(),
+        },
+    );
+    let mode = Mode {
+        size: size.into(),
+        refresh: 60_000,
+    };
+    output.change_current_state(
+        Some(mode),
+        None,
+        Some(Scale::Fractional(scale)),
+        Some(location.into()),
+    );
+    output
+}
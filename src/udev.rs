@@ -1,18 +1,17 @@
 #[cfg(feature = "xwayland")]
 use std::ffi::OsString;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     io,
-    path::Path,
+    path::{Path, PathBuf},
     sync::{atomic::Ordering, Mutex},
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 #[cfg(feature = "renderer_sync")]
 use smithay::backend::drm::compositor::PrimaryPlaneElement;
 #[cfg(feature = "egl")]
 use smithay::backend::renderer::ImportEgl;
-#[cfg(feature = "debug")]
 use smithay::backend::renderer::ImportMem;
 use smithay::{
     backend::{
@@ -35,7 +34,7 @@ use smithay::{
             damage::{Error as OutputDamageTrackerError, OutputDamageTracker},
             element::{
                 default_primary_scanout_output_compare, texture::TextureBuffer,
-                utils::select_dmabuf_feedback, AsRenderElements, RenderElement,
+                utils::select_dmabuf_feedback, AsRenderElements, Element, Id, RenderElement,
                 RenderElementStates,
             },
             gles::{GlesRenderer, GlesTexture},
@@ -59,8 +58,8 @@ use smithay::{
             update_surface_primary_scanout_output, OutputPresentationFeedback,
         },
     },
-    input::pointer::{CursorImageAttributes, CursorImageStatus},
-    output::{Mode as WlMode, Output, PhysicalProperties, Subpixel},
+    input::pointer::{CursorIcon, CursorImageAttributes, CursorImageStatus, MotionEvent},
+    output::{Mode as WlMode, Output, PhysicalProperties, Scale as OutputScale, Subpixel},
     reexports::{
         ash::vk::ExtPhysicalDeviceDrmFn,
         calloop::{
@@ -68,7 +67,7 @@ use smithay::{
             EventLoop, LoopHandle, RegistrationToken,
         },
         drm::{
-            control::{connector, crtc, Device, ModeTypeFlags},
+            control::{connector, crtc, Device, Mode as DrmMode, ModeTypeFlags},
             Device as _,
         },
         gbm::BufferObject,
@@ -81,12 +80,12 @@ use smithay::{
         wayland_server::{
             backend::GlobalId,
             protocol::{wl_output::WlOutput, wl_shm, wl_surface},
-            Display, DisplayHandle,
+            Client, Display, DisplayHandle,
         },
     },
     utils::{
         Clock, DeviceFd, IsAlive, Logical, Monotonic, Physical, Point, Rectangle, Scale, Size,
-        Transform,
+        Transform, SERIAL_COUNTER,
     },
     wayland::{
         compositor,
@@ -110,13 +109,19 @@ use smithay_drm_extras::{
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
-    delegate_screencopy_manager,
-    drawing::{PointerElement, CLEAR_COLOR},
-    protocols::screencopy::{frame::Screencopy, ScreencopyHandler, ScreencopyManagerState},
-    render::{output_elements, CustomRenderElements},
+    clock::ClockConfig,
+    delegate_output_power_management, delegate_screencopy_manager,
+    drawing::{ClockElement, PointerElement, CLEAR_COLOR, NUMBERS_PNG},
+    env_flag, env_parse,
+    protocols::{
+        output_power_management::{OutputPowerHandler, OutputPowerManagementState},
+        screencopy::{frame::Screencopy, ScreencopyHandler, ScreencopyManagerState},
+    },
+    render::{output_elements, workspace_capture_elements, CustomRenderElements},
+    state::client_is_privileged,
     systemd,
     window::WindowElement,
-    Backend, Buddaraysh, CalloopData,
+    Action, Backend, Buddaraysh, CalloopData, LogFilterHandle,
 };
 
 type UdevRenderer<'a, 'b, 'c> =
@@ -136,12 +141,102 @@ const SUPPORTED_FORMATS: &[Fourcc] = &[
 ];
 const SUPPORTED_FORMATS_8BIT_ONLY: &[Fourcc] = &[Fourcc::Abgr8888, Fourcc::Argb8888];
 
+/// Longest wait between dpms checks, so timeouts changed by a config reload apply soon
+const DPMS_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Debug, PartialEq)]
 struct UdevOutputId {
     device_id: DrmNode,
     crtc: crtc::Handle,
 }
 
+/// Mode requested for an output, like "2560x1440@144", or "2560x1440" for its highest refresh rate
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RequestedMode {
+    width: u16,
+    height: u16,
+    /// in Hz
+    refresh: Option<f64>,
+}
+
+impl RequestedMode {
+    fn parse(mode: &str) -> Option<Self> {
+        let (size, refresh) = match mode.split_once('@') {
+            Some((size, refresh)) => (size, Some(refresh.trim().parse::<f64>().ok()?)),
+            None => (mode, None),
+        };
+        let (width, height) = size.split_once('x')?;
+
+        Some(Self {
+            width: width.trim().parse().ok()?,
+            height: height.trim().parse().ok()?,
+            refresh,
+        })
+    }
+
+    /// Index of the connector mode matching this one the closest, if any has the same size
+    /// and a refresh rate within 1Hz of the requested one
+    fn find_in(&self, modes: &[DrmMode]) -> Option<usize> {
+        self.closest(
+            modes
+                .iter()
+                .map(|mode| (mode.size(), WlMode::from(*mode).refresh)),
+        )
+    }
+
+    /// Like `find_in`, with the modes as their size and refresh rate in mHz
+    fn closest(&self, modes: impl Iterator<Item = ((u16, u16), i32)>) -> Option<usize> {
+        let candidates = modes
+            .enumerate()
+            .filter(|(_, (size, _))| *size == (self.width, self.height))
+            .map(|(index, (_, refresh))| (index, refresh));
+
+        match self.refresh {
+            Some(refresh) => {
+                let refresh = (refresh * 1000.0) as i32;
+                candidates
+                    .map(|(index, mode_refresh)| (index, (mode_refresh - refresh).abs()))
+                    .filter(|(_, difference)| *difference <= 1000)
+                    .min_by_key(|(_, difference)| *difference)
+                    .map(|(index, _)| index)
+            }
+            None => candidates
+                .max_by_key(|(_, mode_refresh)| *mode_refresh)
+                .map(|(index, _)| index),
+        }
+    }
+}
+
+/// Where the pointer goes when an output gets connected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputWarp {
+    Center,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl OutputWarp {
+    fn location(&self, geometry: Rectangle<i32, Logical>) -> Point<f64, Logical> {
+        let geometry = geometry.to_f64();
+        let (left, top) = (geometry.loc.x, geometry.loc.y);
+        // stay one pixel inside so the pointer is still on the output
+        let right = left + geometry.size.w - 1.0;
+        let bottom = top + geometry.size.h - 1.0;
+
+        match self {
+            OutputWarp::Center => {
+                (left + geometry.size.w / 2.0, top + geometry.size.h / 2.0).into()
+            }
+            OutputWarp::TopLeft => (left, top).into(),
+            OutputWarp::TopRight => (right, top).into(),
+            OutputWarp::BottomLeft => (left, bottom).into(),
+            OutputWarp::BottomRight => (right, bottom).into(),
+        }
+    }
+}
+
 pub struct UdevData {
     pub session: LibSeatSession,
     display_handle: DisplayHandle,
@@ -154,6 +249,16 @@ pub struct UdevData {
     debug_flags: DebugFlags,
     pointer_images: Vec<(xcursor::parser::Image, TextureBuffer<MultiTexture>)>,
     pointer_element: PointerElement<MultiTexture>,
+    idle_image: Option<image::RgbaImage>,
+    idle_texture: Option<TextureBuffer<MultiTexture>>,
+    /// digits the clock overlay is drawn with
+    clock_texture: Option<MultiTexture>,
+    /// outputs taking longer than this to render get repainted every other frame,
+    /// defaults to the output's frame time
+    render_budget: Option<Duration>,
+    /// warps the pointer to newly connected outputs
+    output_warp: Option<OutputWarp>,
+    output_power_state: OutputPowerManagementState,
 }
 
 impl DmabufHandler for Buddaraysh<UdevData> {
@@ -167,13 +272,40 @@ impl DmabufHandler for Buddaraysh<UdevData> {
         dmabuf: Dmabuf,
         notifier: ImportNotifier,
     ) {
-        if self
-            .backend_data
-            .gpus
-            .single_renderer(&self.backend_data.primary_gpu)
-            .and_then(|mut renderer| renderer.import_dmabuf(&dmabuf, None))
-            .is_err()
-        {
+        // a buffer rendered on another gpu might only be importable there, so try the gpu
+        // it was allocated on, and then every other one before giving up
+        let mut nodes = vec![self.backend_data.primary_gpu];
+        let origin = dmabuf
+            .node()
+            .and_then(|node| node.node_with_type(NodeType::Render)?.ok());
+        let other_gpus = self.backend_data.backends.values().map(|b| b.render_node);
+        for node in origin.into_iter().chain(other_gpus) {
+            if !nodes.contains(&node) {
+                nodes.push(node);
+            }
+        }
+
+        let imported = nodes.iter().any(|node| {
+            match self
+                .backend_data
+                .gpus
+                .single_renderer(node)
+                .and_then(|mut renderer| renderer.import_dmabuf(&dmabuf, None))
+            {
+                Ok(_) => {
+                    if node != &self.backend_data.primary_gpu {
+                        debug!(?node, "Imported dmabuf on a non-primary gpu");
+                    }
+                    true
+                }
+                Err(err) => {
+                    trace!(?node, ?err, "Failed to import dmabuf");
+                    false
+                }
+            }
+        });
+
+        if !imported {
             notifier.failed();
         }
     }
@@ -206,6 +338,55 @@ impl Backend for UdevData {
             warn!("Early buffer import failed: {}", err);
         }
     }
+
+    fn process_backend_action(state: &mut Buddaraysh<Self>, action: Action) -> Option<Action> {
+        match action {
+            Action::ToggleOutputEnabled(name) => state.toggle_output_enabled(&name),
+            Action::DumpFrame => state.dump_frame(),
+            action => return Some(action),
+        }
+        None
+    }
+
+    fn capture_space(
+        &mut self,
+        space: &Space<WindowElement>,
+        region: Rectangle<i32, Logical>,
+        size: Size<i32, Physical>,
+        path: PathBuf,
+    ) -> Result<PathBuf, String> {
+        if region.is_empty() || size.w <= 0 || size.h <= 0 {
+            return Err(String::from("nothing to capture"));
+        }
+        let scale = f64::min(
+            size.w as f64 / region.size.w as f64,
+            size.h as f64 / region.size.h as f64,
+        );
+
+        let mut renderer = self
+            .gpus
+            .single_renderer(&self.primary_gpu)
+            .map_err(|err| err.to_string())?;
+        let buffer = Offscreen::<GlesTexture>::create_buffer(&mut renderer, Fourcc::Abgr8888, size)
+            .map_err(|err| err.to_string())?;
+        renderer.bind(buffer).map_err(|err| err.to_string())?;
+
+        // a fresh damage tracker, so the whole buffer gets drawn
+        let elements = workspace_capture_elements(&mut renderer, space, region, scale);
+        let mut damage_tracker = OutputDamageTracker::new(size, 1.0, Transform::Normal);
+        damage_tracker
+            .render_output(&mut renderer, 0, &elements, CLEAR_COLOR)
+            .map_err(|err| format!("{err:?}"))?
+            .sync
+            .wait();
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        save_framebuffer(&mut renderer, size, &path)?;
+
+        Ok(path)
+    }
 }
 
 struct DrmSurfaceDmabufFeedback {
@@ -225,6 +406,45 @@ pub struct Surface {
     #[cfg(feature = "debug")]
     fps_element: Option<FpsElement<MultiTexture>>,
     dmabuf_feedback: Option<DrmSurfaceDmabufFeedback>,
+    render_times: VecDeque<Duration>,
+    /// screencopy frames copied out of the next repaint of this surface
+    pending_screencopies: Vec<Screencopy>,
+    /// write the next repaint of this surface to a png
+    dump_frame: bool,
+    /// outputs that are off aren't repainted
+    power: OutputPower,
+    clock_element: Option<ClockElement<MultiTexture>>,
+}
+
+/// Whether an output is powered on, and what turned it off if it isn't
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputPower {
+    On,
+    /// turned off by a client, only a client turns it back on
+    Off,
+    /// turned off by the dpms timer, any input turns it back on
+    IdleOff,
+}
+
+/// How many of the last render times are averaged for the render budget
+const RENDER_TIME_SAMPLES: usize = 8;
+
+impl Surface {
+    fn record_render_time(&mut self, elapsed: Duration) {
+        if self.render_times.len() == RENDER_TIME_SAMPLES {
+            self.render_times.pop_front();
+        }
+        self.render_times.push_back(elapsed);
+    }
+
+    fn over_render_budget(&self, budget: Duration) -> bool {
+        if self.render_times.is_empty() {
+            return false;
+        }
+
+        let total: Duration = self.render_times.iter().sum();
+        total / self.render_times.len() as u32 > budget
+    }
 }
 
 struct BackendData {
@@ -494,7 +714,7 @@ pub type GbmDrmCompositor = DrmCompositor<
     DrmDeviceFd,
 >;
 
-pub fn run_udev() -> Result<(), Box<dyn std::error::Error>> {
+pub fn run_udev(log_filter: LogFilterHandle) -> Result<(), Box<dyn std::error::Error>> {
     let mut event_loop = EventLoop::<CalloopData<UdevData>>::try_new()?;
     let display = Display::new().unwrap();
     let mut display_handle = display.handle();
@@ -528,6 +748,34 @@ pub fn run_udev() -> Result<(), Box<dyn std::error::Error>> {
     let gpus =
         GpuManager::new(GbmGlesBackend::with_context_priority(ContextPriority::High)).unwrap();
 
+    let idle_image =
+        std::env::var("BUD_IDLE_IMAGE")
+            .ok()
+            .and_then(|path| match image::open(&path) {
+                Ok(image) => Some(image.to_rgba8()),
+                Err(err) => {
+                    warn!("Failed to load idle image {}: {}", path, err);
+                    None
+                }
+            });
+
+    let render_budget = env_parse::<u64>("BUD_RENDER_BUDGET").map(Duration::from_millis);
+
+    let output_warp =
+        std::env::var("BUD_OUTPUT_WARP")
+            .ok()
+            .and_then(|x| match x.to_lowercase().as_str() {
+                "center" => Some(OutputWarp::Center),
+                "top-left" => Some(OutputWarp::TopLeft),
+                "top-right" => Some(OutputWarp::TopRight),
+                "bottom-left" => Some(OutputWarp::BottomLeft),
+                "bottom-right" => Some(OutputWarp::BottomRight),
+                _ => None,
+            });
+
+    let output_power_state =
+        OutputPowerManagementState::new::<Buddaraysh<UdevData>>(&display_handle);
+
     let data = UdevData {
         display_handle: display_handle.clone(),
         dmabuf_state: None,
@@ -540,13 +788,40 @@ pub fn run_udev() -> Result<(), Box<dyn std::error::Error>> {
         pointer_image: crate::cursor::Cursor::load(),
         pointer_images: Vec::new(),
         pointer_element: PointerElement::default(),
+        idle_image,
+        idle_texture: None,
+        clock_texture: None,
+        render_budget,
+        output_warp,
+        output_power_state,
     };
-    let mut state = Buddaraysh::new(event_loop.handle(), &mut event_loop, display, data);
+    let mut state = Buddaraysh::new(
+        event_loop.handle(),
+        &mut event_loop,
+        display,
+        data,
+        log_filter,
+    );
 
     let backend = UdevBackend::new(&state.seat_name).unwrap();
 
     ScreencopyManagerState::new::<Buddaraysh<UdevData>>(&display_handle);
 
+    // runs without timeouts too, a reload of the config can add some
+    let first_check = state
+        .config
+        .dpms_timeouts
+        .values()
+        .min()
+        .copied()
+        .unwrap_or(DPMS_CHECK_INTERVAL);
+    event_loop
+        .handle()
+        .insert_source(Timer::from_duration(first_check), |_, _, data| {
+            TimeoutAction::ToDuration(data.state.power_off_idle_outputs())
+        })
+        .expect("failed to schedule dpms timer");
+
     /*
      * Initialize libinput backend
      */
@@ -600,7 +875,7 @@ pub fn run_udev() -> Result<(), Box<dyn std::error::Error>> {
                     if let Some(lease_global) = backend.leasing_global.as_mut() {
                         lease_global.resume::<Buddaraysh<UdevData>>();
                     }
-                    for surface in backend.surfaces.values_mut() {
+                    for (crtc, surface) in backend.surfaces.iter_mut() {
                         if let Err(err) = surface.compositor.surface().reset_state() {
                             warn!("Failed to reset drm surface state: {}", err);
                         }
@@ -609,8 +884,15 @@ pub fn run_udev() -> Result<(), Box<dyn std::error::Error>> {
                         // has no content and damage tracking may prevent a redraw
                         // otherwise
                         surface.compositor.reset_buffers();
+
+                        // outputs that are off don't get redrawn, but they lost their blank frame
+                        if surface.power != OutputPower::On {
+                            let crtc = *crtc;
+                            loop_handle
+                                .insert_idle(move |data| data.state.blank_surface(node, crtc));
+                        }
                     }
-                    loop_handle.insert_idle(move |data| data.state.render(node, None, None));
+                    loop_handle.insert_idle(move |data| data.state.render(node, None));
                 }
             }
         })
@@ -632,14 +914,7 @@ pub fn run_udev() -> Result<(), Box<dyn std::error::Error>> {
             .shm_formats(),
     );
 
-    let skip_vulkan = std::env::var("BUD_NO_VULKAN")
-        .map(|x| {
-            x == "1"
-                || x.to_lowercase() == "true"
-                || x.to_lowercase() == "yes"
-                || x.to_lowercase() == "y"
-        })
-        .unwrap_or(false);
+    let skip_vulkan = env_flag("BUD_NO_VULKAN");
 
     if !skip_vulkan {
         if let Ok(instance) = Instance::new(Version::VERSION_1_2, None) {
@@ -699,7 +974,7 @@ pub fn run_udev() -> Result<(), Box<dyn std::error::Error>> {
     #[cfg(feature = "debug")]
     {
         let fps_image = image::io::Reader::with_format(
-            std::io::Cursor::new(FPS_NUMBERS_PNG),
+            std::io::Cursor::new(NUMBERS_PNG),
             image::ImageFormat::Png,
         )
         .decode()
@@ -813,6 +1088,10 @@ pub fn run_udev() -> Result<(), Box<dyn std::error::Error>> {
                 .space_mut()
                 .refresh();
             state.popups.cleanup();
+            // clients may have changed the scene before the next batch of input events
+            state.pointer_under_cache = None;
+            state.send_ipc_window_events();
+            state.flush_ipc_connections();
             display_handle.flush_clients().unwrap();
         }
     }
@@ -985,11 +1264,39 @@ impl Buddaraysh<UdevData> {
                 );
             }
         } else {
-            let mode_id = connector
-                .modes()
-                .iter()
-                .position(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
-                .unwrap_or(0);
+            let output_config = self.config.outputs.get(&output_name);
+            if output_config.map_or(false, |config| !config.enabled) {
+                info!("Connector {} is disabled in the config", output_name);
+                return;
+            }
+
+            let preferred_mode_id = || {
+                connector
+                    .modes()
+                    .iter()
+                    .position(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
+                    .unwrap_or(0)
+            };
+            let requested_mode = output_config
+                .and_then(|config| config.mode.as_deref())
+                .and_then(|mode| {
+                    let parsed = RequestedMode::parse(mode);
+                    if parsed.is_none() {
+                        warn!(mode, "Invalid mode in the config of {}", output_name);
+                    }
+                    parsed
+                });
+            let mode_id = match requested_mode {
+                Some(requested) => requested.find_in(connector.modes()).unwrap_or_else(|| {
+                    warn!(
+                        ?requested,
+                        "Requested mode isn't available on {}, using the preferred one",
+                        output_name
+                    );
+                    preferred_mode_id()
+                }),
+                None => preferred_mode_id(),
+            };
 
             let drm_mode = connector.modes()[mode_id];
             let wl_mode = WlMode::from(drm_mode);
@@ -1017,23 +1324,34 @@ impl Buddaraysh<UdevData> {
             );
             let global = output.create_global::<Buddaraysh<UdevData>>(&self.display_handle);
 
-            // TODO: make this configurable
-            let x = self.workspaces.outputs().fold(0, |acc, o| {
-                acc + self
-                    .workspaces
-                    .current_workspace()
-                    .output_geometry(o)
-                    .unwrap()
-                    .size
-                    .w
-            });
-            let position = (x, 0).into();
+            let position = match output_config.and_then(|config| config.position) {
+                Some(position) => position.into(),
+                None => {
+                    let x = self.workspaces.outputs().fold(0, |acc, o| {
+                        acc + self
+                            .workspaces
+                            .current_workspace()
+                            .output_geometry(o)
+                            .unwrap()
+                            .size
+                            .w
+                    });
+                    (x, 0).into()
+                }
+            };
+            let transform = output_config.and_then(|config| config.transform);
+            let scale = output_config
+                .and_then(|config| config.scale)
+                .map(OutputScale::Fractional);
 
             output.set_preferred(wl_mode);
-            output.change_current_state(Some(wl_mode), None, None, Some(position));
+            output.change_current_state(Some(wl_mode), transform, scale, Some(position));
+            self.save_output_layout();
             for workspace in self.workspaces.workspaces_mut() {
                 workspace.add_output(&output, position);
             }
+            self.ensure_pointer_on_output();
+            self.restore_output_layout();
 
             output.user_data().insert_if_missing(|| UdevOutputId {
                 crtc,
@@ -1137,12 +1455,28 @@ impl Buddaraysh<UdevData> {
                 #[cfg(feature = "debug")]
                 fps_element,
                 dmabuf_feedback,
-                output,
+                output: output.clone(),
+                render_times: VecDeque::with_capacity(RENDER_TIME_SAMPLES),
+                pending_screencopies: Vec::new(),
+                dump_frame: false,
+                power: OutputPower::On,
+                clock_element: None,
             };
 
             device.surfaces.insert(crtc, surface);
 
             self.schedule_initial_render(node, crtc, self.loop_handle.clone());
+
+            // only warp for outputs plugged in later on, not the ones found on startup
+            if self.workspaces.outputs().nth(1).is_some() {
+                if let Some(warp) = self.backend_data.output_warp {
+                    if let Some(geometry) =
+                        self.workspaces.current_workspace().output_geometry(&output)
+                    {
+                        self.move_pointer(warp.location(geometry));
+                    }
+                }
+            }
         }
     }
 
@@ -1161,11 +1495,377 @@ impl Buddaraysh<UdevData> {
         info!("Disconnected:");
         dbg!(name);
 
-        if let Some(device) = self.backend_data.backends.get_mut(&node) {
-            device.surfaces.remove(&crtc);
+        let removed = self
+            .backend_data
+            .backends
+            .get_mut(&node)
+            .and_then(|device| device.surfaces.remove(&crtc));
+        if let Some(surface) = removed {
+            self.backend_data
+                .output_power_state
+                .output_removed(&surface.output);
+        }
+
+        let output = self
+            .workspaces
+            .outputs()
+            .find(|o| {
+                o.user_data().get::<UdevOutputId>()
+                    == Some(&UdevOutputId {
+                        device_id: node,
+                        crtc,
+                    })
+            })
+            .cloned();
+
+        if let Some(output) = output {
+            let geometry = self.workspaces.current_workspace().output_geometry(&output);
+            self.save_output_layout();
+            for workspace in self.workspaces.workspaces_mut() {
+                workspace.remove_output(&output);
+            }
+            self.relayout_outputs();
+            if let Some(geometry) = geometry {
+                self.evacuate_output(&output, geometry);
+            }
+            self.restore_output_layout();
         }
     }
 
+    /// Writes the next frame of the output under the pointer to a png under the cache dir
+    pub fn dump_frame(&mut self) {
+        let Some(output) = self
+            .workspaces
+            .output_under(self.pointer.current_location())
+            .next()
+            .cloned()
+        else {
+            return;
+        };
+        let Some((node, crtc, surface)) =
+            self.backend_data
+                .backends
+                .iter_mut()
+                .find_map(|(node, device)| {
+                    device
+                        .surfaces
+                        .iter_mut()
+                        .find(|(_, surface)| surface.output == output)
+                        .map(|(crtc, surface)| (*node, *crtc, surface))
+                })
+        else {
+            return;
+        };
+
+        surface.dump_frame = true;
+        self.render(node, Some(crtc));
+    }
+
+    /// Takes the output out of the layout and blanks it, or puts it back if it was disabled.
+    ///
+    /// Windows on the disabled output are moved onto the first remaining output. Outputs
+    /// disabled in the config file were never set up, so they can't be enabled here.
+    pub fn toggle_output_enabled(&mut self, name: &str) {
+        let Some((node, crtc, output)) =
+            self.backend_data
+                .backends
+                .iter()
+                .find_map(|(node, device)| {
+                    device
+                        .surfaces
+                        .iter()
+                        .find(|(_, surface)| surface.output.name() == name)
+                        .map(|(crtc, surface)| (*node, *crtc, surface.output.clone()))
+                })
+        else {
+            warn!(name, "No output to toggle");
+            return;
+        };
+
+        if !self.workspaces.outputs().any(|o| *o == output) {
+            let x = self
+                .workspaces
+                .outputs()
+                .filter_map(|o| self.workspaces.current_workspace().output_geometry(o))
+                .map(|geo| geo.loc.x + geo.size.w)
+                .max()
+                .unwrap_or(0);
+            self.save_output_layout();
+            for workspace in self.workspaces.workspaces_mut() {
+                workspace.add_output(&output, (x, 0));
+            }
+            self.relayout_outputs();
+            self.restore_output_layout();
+            self.schedule_initial_render(node, crtc, self.loop_handle.clone());
+            info!(name, "Enabled output");
+            return;
+        }
+
+        if self.workspaces.outputs().count() == 1 {
+            warn!(name, "Not disabling the only enabled output");
+            return;
+        }
+
+        let Some(geometry) = self.workspaces.current_workspace().output_geometry(&output) else {
+            return;
+        };
+
+        self.save_output_layout();
+        for workspace in self.workspaces.workspaces_mut() {
+            workspace.remove_output(&output);
+        }
+        self.relayout_outputs();
+        self.evacuate_output(&output, geometry);
+        self.restore_output_layout();
+
+        // the output isn't part of the layout anymore, so this is the last frame it gets
+        if let Some(surface) = self
+            .backend_data
+            .backends
+            .get_mut(&node)
+            .and_then(|device| device.surfaces.get_mut(&crtc))
+        {
+            let render_node = surface.render_node;
+            let result = self
+                .backend_data
+                .gpus
+                .single_renderer(&render_node)
+                .map_err(|err| warn!("Failed to get renderer: {}", err))
+                .ok()
+                .map(|mut renderer| blank_render(surface, &mut renderer));
+            if let Some(Err(err)) = result {
+                warn!("Failed to blank output {}: {:?}", name, err);
+            }
+        }
+
+        info!(name, "Disabled output");
+    }
+
+    /// Turns an output off while keeping it in the layout, or back on
+    fn set_output_power_mode(&mut self, output: &Output, power: OutputPower) {
+        let Some((node, crtc, surface)) =
+            self.backend_data
+                .backends
+                .iter_mut()
+                .find_map(|(node, device)| {
+                    device
+                        .surfaces
+                        .iter_mut()
+                        .find(|(_, surface)| surface.output == *output)
+                        .map(|(crtc, surface)| (*node, *crtc, surface))
+                })
+        else {
+            return;
+        };
+        if surface.power == power {
+            return;
+        }
+        let was_on = surface.power == OutputPower::On;
+        surface.power = power;
+        info!(output = output.name(), ?power, "Changed output power");
+
+        if power == OutputPower::On {
+            surface.compositor.reset_buffers();
+            self.render(node, Some(crtc));
+        } else if was_on {
+            self.blank_surface(node, crtc);
+        }
+
+        self.backend_data
+            .output_power_state
+            .mode_changed(output, power == OutputPower::On);
+    }
+
+    /// Shows a black frame on the surface, which stays until it's rendered to again
+    fn blank_surface(&mut self, node: DrmNode, crtc: crtc::Handle) {
+        let Some(surface) = self
+            .backend_data
+            .backends
+            .get_mut(&node)
+            .and_then(|device| device.surfaces.get_mut(&crtc))
+        else {
+            return;
+        };
+
+        let render_node = surface.render_node;
+        let result = self
+            .backend_data
+            .gpus
+            .single_renderer(&render_node)
+            .map_err(|err| warn!("Failed to get renderer: {}", err))
+            .ok()
+            .map(|mut renderer| blank_render(surface, &mut renderer));
+        if let Some(Err(err)) = result {
+            warn!(
+                "Failed to blank output {}: {:?}",
+                surface.output.name(),
+                err
+            );
+        }
+    }
+
+    /// Turns off the outputs that went without input for longer than their dpms timeout,
+    /// returns how long until the next check
+    fn power_off_idle_outputs(&mut self) -> Duration {
+        let idle = self.last_input.elapsed();
+        let outputs: Vec<(Output, Duration)> = self
+            .backend_data
+            .backends
+            .values()
+            .flat_map(|device| device.surfaces.values())
+            .filter(|surface| surface.power == OutputPower::On)
+            .filter_map(|surface| {
+                let timeout = self.config.dpms_timeouts.get(&surface.output.name())?;
+                Some((surface.output.clone(), *timeout))
+            })
+            .collect();
+
+        let mut next = self
+            .config
+            .dpms_timeouts
+            .values()
+            .min()
+            .copied()
+            .unwrap_or(DPMS_CHECK_INTERVAL)
+            .min(DPMS_CHECK_INTERVAL);
+        for (output, timeout) in outputs {
+            if idle >= timeout {
+                self.set_output_power_mode(&output, OutputPower::IdleOff);
+            } else {
+                next = next.min(timeout - idle);
+            }
+        }
+
+        next
+    }
+
+    /// Turns the outputs the dpms timer turned off back on
+    pub fn wake_idle_outputs(&mut self) {
+        let outputs: Vec<Output> = self
+            .backend_data
+            .backends
+            .values()
+            .flat_map(|device| device.surfaces.values())
+            .filter(|surface| surface.power == OutputPower::IdleOff)
+            .map(|surface| surface.output.clone())
+            .collect();
+        for output in outputs {
+            self.set_output_power_mode(&output, OutputPower::On);
+        }
+    }
+
+    /// Puts the outputs back where the config file has them, the others next to each other from
+    /// the left without covering those, so there are no gaps left after an output got removed,
+    /// and keeps the pointer on a valid output
+    fn relayout_outputs(&mut self) {
+        let outputs: Vec<Output> = self.workspaces.outputs().cloned().collect();
+        let size_of = |output: &Output| {
+            self.workspaces
+                .current_workspace()
+                .output_geometry(output)
+                .map(|geo| geo.size)
+                .unwrap_or_else(|| (0, 0).into())
+        };
+        let configured: Vec<(Output, Rectangle<i32, Logical>)> = outputs
+            .iter()
+            .filter_map(|output| {
+                let position = self.config.outputs.get(&output.name())?.position?;
+                Some((
+                    output.clone(),
+                    Rectangle::from_loc_and_size(position, size_of(output)),
+                ))
+            })
+            .collect();
+
+        let mut x = 0;
+        let mut placed = Vec::new();
+        for output in outputs {
+            if let Some((_, geometry)) = configured.iter().find(|(o, _)| *o == output) {
+                placed.push((output, geometry.loc));
+                continue;
+            }
+
+            let size = size_of(&output);
+            while let Some((_, covered)) = configured
+                .iter()
+                .find(|(_, geometry)| geometry.overlaps(Rectangle::from_loc_and_size((x, 0), size)))
+            {
+                x = covered.loc.x + covered.size.w;
+            }
+            placed.push((output, (x, 0).into()));
+            x += size.w;
+        }
+
+        for (output, position) in placed {
+            output.change_current_state(None, None, None, Some(position));
+            for workspace in self.workspaces.workspaces_mut() {
+                workspace.add_output(&output, position);
+            }
+        }
+
+        self.ensure_pointer_on_output();
+    }
+
+    /// Moves the pointer to the closest point on the closest output, if it ended up outside of all
+    /// of them, and sends a motion event so focus follows
+    fn ensure_pointer_on_output(&mut self) {
+        let pointer_location = self.pointer.current_location();
+        let output_geometries: Vec<Rectangle<i32, Logical>> = self
+            .workspaces
+            .outputs()
+            .filter_map(|o| self.workspaces.current_workspace().output_geometry(o))
+            .collect();
+
+        if output_geometries
+            .iter()
+            .any(|geo| geo.to_f64().contains(pointer_location))
+        {
+            return;
+        }
+
+        let Some(new_location) = output_geometries
+            .iter()
+            .map(|geo| {
+                let geo = geo.to_f64();
+                Point::<f64, Logical>::from((
+                    pointer_location
+                        .x
+                        .clamp(geo.loc.x, geo.loc.x + geo.size.w - 1.0),
+                    pointer_location
+                        .y
+                        .clamp(geo.loc.y, geo.loc.y + geo.size.h - 1.0),
+                ))
+            })
+            .min_by(|a, b| {
+                let distance_a =
+                    (pointer_location.x - a.x).powi(2) + (pointer_location.y - a.y).powi(2);
+                let distance_b =
+                    (pointer_location.x - b.x).powi(2) + (pointer_location.y - b.y).powi(2);
+                distance_a.total_cmp(&distance_b)
+            })
+        else {
+            return;
+        };
+
+        self.move_pointer(new_location);
+    }
+
+    /// Moves the pointer, sending a motion event so the focus follows it
+    fn move_pointer(&mut self, location: Point<f64, Logical>) {
+        let pointer = self.pointer.clone();
+        let under = self.surface_under(location);
+        pointer.motion(
+            self,
+            under,
+            &MotionEvent {
+                location,
+                serial: SERIAL_COUNTER.next_serial(),
+                time: Instant::now().duration_since(self.start_time).as_millis() as u32,
+            },
+        );
+        pointer.frame(self);
+    }
+
     fn device_changed(&mut self, node: DrmNode) {
         let device = if let Some(device) = self.backend_data.backends.get_mut(&node) {
             device
@@ -1364,10 +2064,19 @@ impl Buddaraysh<UdevData> {
             //
             // A more complete solution could work on a sliding window analyzing past repaints
             // and do some prediction for the next repaint.
+            let frame_duration =
+                Duration::from_millis((1_000_000f32 / output_refresh as f32) as u64);
             let repaint_delay =
                 Duration::from_millis(((1_000_000f32 / output_refresh as f32) * 0.6f32) as u64);
 
-            let timer = if self.backend_data.primary_gpu != surface.render_node {
+            // an output that can't keep up with its refresh rate only gets every other frame,
+            // so it doesn't starve the other outputs
+            let render_budget = self.backend_data.render_budget.unwrap_or(frame_duration);
+
+            let timer = if surface.over_render_budget(render_budget) {
+                trace!("{:?} is over its render budget, skipping a frame", crtc);
+                Timer::from_duration(repaint_delay + frame_duration)
+            } else if self.backend_data.primary_gpu != surface.render_node {
                 // However, if we need to do a copy, that might not be enough.
                 // (And without actual comparision to previous frames we cannot really know.)
                 // So lets ignore that in those cases to avoid thrashing performance.
@@ -1384,7 +2093,7 @@ impl Buddaraysh<UdevData> {
 
             self.loop_handle
                 .insert_source(timer, move |_, _, data| {
-                    data.state.render(dev_id, Some(crtc), None);
+                    data.state.render(dev_id, Some(crtc));
                     TimeoutAction::Drop
                 })
                 .expect("failed to schedule frame timer");
@@ -1392,12 +2101,7 @@ impl Buddaraysh<UdevData> {
     }
 
     // If crtc is `Some()`, render it, else render all crtcs
-    fn render(
-        &mut self,
-        node: DrmNode,
-        crtc: Option<crtc::Handle>,
-        screencopy: Option<Screencopy>,
-    ) {
+    fn render(&mut self, node: DrmNode, crtc: Option<crtc::Handle>) {
         let device_backend = match self.backend_data.backends.get_mut(&node) {
             Some(backend) => backend,
             None => {
@@ -1407,22 +2111,27 @@ impl Buddaraysh<UdevData> {
         };
 
         if let Some(crtc) = crtc {
-            self.render_surface(node, crtc, screencopy);
+            self.render_surface(node, crtc);
         } else {
             let crtcs: Vec<_> = device_backend.surfaces.keys().copied().collect();
             for crtc in crtcs {
-                self.render_surface(node, crtc, None);
+                self.render_surface(node, crtc);
             }
         };
     }
 
-    fn render_surface(
-        &mut self,
-        node: DrmNode,
-        crtc: crtc::Handle,
-        screencopy: Option<Screencopy>,
-    ) {
+    fn render_surface(&mut self, node: DrmNode, crtc: crtc::Handle) {
         profiling::scope!("render_surface", &format!("{crtc:?}"));
+        let active_output = self.active_output();
+        let grid_output = self.grid_output();
+        let spotlight = self
+            .spotlight_area()
+            .map(|area| (area, self.config.spotlight.alpha));
+        let pointer_output = self
+            .workspaces
+            .output_under(self.pointer.current_location())
+            .next()
+            .cloned();
         let Some(device) = self.backend_data.backends.get_mut(&node) else {
             return;
         };
@@ -1431,13 +2140,36 @@ impl Buddaraysh<UdevData> {
             return;
         };
 
+        // the blank frame stays up until the output is turned on again
+        if surface.power != OutputPower::On {
+            return;
+        }
+
         let start = Instant::now();
 
-        // TODO get scale from the rendersurface when supporting HiDPI
-        let frame = self
-            .backend_data
-            .pointer_image
-            .get_image(1 /*scale*/, self.clock.now().try_into().unwrap());
+        let Some(output) = self.workspaces.outputs().find(|o| {
+            o.user_data().get::<UdevOutputId>()
+                == Some(&UdevOutputId {
+                    device_id: surface.device_id,
+                    crtc,
+                })
+        }) else {
+            // somehow we got called with an invalid output
+            return;
+        };
+
+        // pick the cursor image matching this output's scale, so it doesn't look blurry
+        // or tiny on outputs with a different scale than the others
+        let cursor_scale = output.current_scale().integer_scale().max(1) as u32;
+        let busy = matches!(
+            *self.cursor_status.lock().unwrap(),
+            CursorImageStatus::Named(CursorIcon::Progress)
+        );
+        let frame = self.backend_data.pointer_image.get_image(
+            busy,
+            cursor_scale,
+            self.clock.now().try_into().unwrap(),
+        );
 
         let render_node = surface.render_node;
         let primary_gpu = self.backend_data.primary_gpu;
@@ -1477,7 +2209,7 @@ impl Buddaraysh<UdevData> {
                     Fourcc::Abgr8888,
                     (frame.width as i32, frame.height as i32),
                     false,
-                    1,
+                    cursor_scale as i32,
                     Transform::Normal,
                     None,
                 )
@@ -1486,16 +2218,51 @@ impl Buddaraysh<UdevData> {
                 texture
             });
 
-        let Some(output) = self.workspaces.outputs().find(|o| {
-            o.user_data().get::<UdevOutputId>()
-                == Some(&UdevOutputId {
-                    device_id: surface.device_id,
-                    crtc,
-                })
-        }) else {
-            // somehow we got called with an invalid output
-            return;
-        };
+        if self.backend_data.idle_texture.is_none() {
+            if let Some(idle_image) = self.backend_data.idle_image.as_ref() {
+                match TextureBuffer::from_memory(
+                    &mut renderer,
+                    idle_image.as_raw(),
+                    Fourcc::Abgr8888,
+                    (idle_image.width() as i32, idle_image.height() as i32),
+                    false,
+                    1,
+                    Transform::Normal,
+                    None,
+                ) {
+                    Ok(texture) => self.backend_data.idle_texture = Some(texture),
+                    Err(err) => {
+                        warn!("Failed to import idle image: {:?}", err);
+                        self.backend_data.idle_image = None;
+                    }
+                }
+            }
+        }
+
+        if self.clock_text.is_some() && surface.clock_element.is_none() {
+            if self.backend_data.clock_texture.is_none() {
+                let numbers_image = image::io::Reader::with_format(
+                    std::io::Cursor::new(NUMBERS_PNG),
+                    image::ImageFormat::Png,
+                )
+                .decode()
+                .unwrap();
+                match renderer.import_memory(
+                    &numbers_image.to_rgba8(),
+                    Fourcc::Abgr8888,
+                    (numbers_image.width() as i32, numbers_image.height() as i32).into(),
+                    false,
+                ) {
+                    Ok(texture) => self.backend_data.clock_texture = Some(texture),
+                    Err(err) => warn!("Failed to import clock texture: {:?}", err),
+                }
+            }
+            surface.clock_element = self
+                .backend_data
+                .clock_texture
+                .clone()
+                .map(ClockElement::new);
+        }
 
         let result = render_surface(
             surface,
@@ -1508,9 +2275,22 @@ impl Buddaraysh<UdevData> {
             &self.dnd_icon,
             &mut self.cursor_status.lock().unwrap(),
             &self.clock,
-            // self.show_window_preview,
-            screencopy,
+            self.show_window_preview,
             self.workspaces.current_workspace_index(),
+            self.backend_data.idle_texture.as_ref(),
+            active_output.as_ref() == Some(output),
+            self.snap_grid()
+                .filter(|_| grid_output.as_ref() == Some(output)),
+            self.clock_text
+                .as_deref()
+                .filter(|_| {
+                    self.clock_config.all_outputs || pointer_output.as_ref() == Some(output)
+                })
+                .map(|text| (text, &self.clock_config)),
+            self.zen
+                .as_ref()
+                .and_then(|zen| zen.window_on(output, self.workspaces.current_workspace_index())),
+            spotlight,
         );
 
         let reschedule = match &result {
@@ -1550,13 +2330,21 @@ impl Buddaraysh<UdevData> {
             let timer = Timer::from_duration(reschedule_duration);
             self.loop_handle
                 .insert_source(timer, move |_, _, data| {
-                    data.state.render(node, Some(crtc), None);
+                    data.state.render(node, Some(crtc));
                     TimeoutAction::Drop
                 })
                 .expect("failed to schedule frame timer");
         } else {
             let elapsed = start.elapsed();
             tracing::trace!(?elapsed, "rendered surface");
+            if let Some(surface) = self
+                .backend_data
+                .backends
+                .get_mut(&node)
+                .and_then(|device| device.surfaces.get_mut(&crtc))
+            {
+                surface.record_render_time(elapsed);
+            }
         }
 
         profiling::finish_frame!();
@@ -1679,22 +2467,28 @@ fn render_surface<'a, 'b, 'c>(
     dnd_icon: &Option<wl_surface::WlSurface>,
     cursor_status: &mut CursorImageStatus,
     clock: &Clock<Monotonic>,
-    // show_window_preview: bool,
-    screencopy: Option<Screencopy>,
+    show_window_preview: bool,
     current_workspace_index: usize,
+    idle_texture: Option<&TextureBuffer<MultiTexture>>,
+    active_output_indicator: bool,
+    grid_size: Option<i32>,
+    clock_overlay: Option<(&str, &ClockConfig)>,
+    zen_window: Option<&WindowElement>,
+    spotlight: Option<(Rectangle<i32, Logical>, f32)>,
 ) -> Result<bool, SwapBuffersError> {
     let output_geometry = space.output_geometry(output).unwrap();
     let scale = Scale::from(output.current_scale().fractional_scale());
 
     let mut custom_elements: Vec<CustomRenderElements<_>> = Vec::new();
+    // screencopy frames without the cursor leave these out when copying the repaint
+    let mut cursor_element_ids: Vec<Id> = Vec::new();
 
-    let render_cursor = if let Some(screencopy) = &screencopy {
-        screencopy.overlay_cursor
-    } else {
-        true
-    };
+    let screencopies = std::mem::take(&mut surface.pending_screencopies)
+        .into_iter()
+        .filter(|screencopy| screencopy.is_alive())
+        .collect::<Vec<_>>();
 
-    if render_cursor && output_geometry.to_f64().contains(pointer_location) {
+    if output_geometry.to_f64().contains(pointer_location) {
         let cursor_hotspot = if let CursorImageStatus::Surface(ref surface) = cursor_status {
             compositor::with_states(surface, |states| {
                 states
@@ -1728,12 +2522,10 @@ fn render_surface<'a, 'b, 'c>(
             pointer_element.set_status(cursor_status.clone());
         }
 
-        custom_elements.extend(pointer_element.render_elements(
-            renderer,
-            cursor_pos_scaled,
-            scale,
-            1.0,
-        ));
+        let pointer_elements: Vec<CustomRenderElements<_>> =
+            pointer_element.render_elements(renderer, cursor_pos_scaled, scale, 1.0);
+        cursor_element_ids.extend(pointer_elements.iter().map(|e| e.id().clone()));
+        custom_elements.extend(pointer_elements);
 
         // draw the dnd icon if applicable
         {
@@ -1753,6 +2545,13 @@ fn render_surface<'a, 'b, 'c>(
         }
     }
 
+    if let (Some((text, config)), Some(element)) = (clock_overlay, surface.clock_element.as_mut()) {
+        element.set_text(text);
+        let size = element.size().to_physical_precise_round(scale);
+        element.set_location(config.location(output, size));
+        custom_elements.push(CustomRenderElements::Clock(element.clone()));
+    }
+
     #[cfg(feature = "debug")]
     if let Some(element) = surface.fps_element.as_mut() {
         element.update_fps(surface.fps.avg().round() as u32);
@@ -1766,6 +2565,12 @@ fn render_surface<'a, 'b, 'c>(
         custom_elements,
         renderer,
         current_workspace_index,
+        idle_texture,
+        active_output_indicator,
+        show_window_preview,
+        grid_size,
+        zen_window,
+        spotlight,
     );
     let (res, frame_result) =
         surface
@@ -1773,8 +2578,18 @@ fn render_surface<'a, 'b, 'c>(
             .render_frame::<_, _, GlesTexture>(renderer, &elements, clear_color)?;
 
     // Copy framebuffer for screencopy.
-    if let Some(mut screencopy) = screencopy {
-        if let Some(frame_result) = frame_result {
+    for mut screencopy in screencopies {
+        let damaged = frame_result
+            .as_ref()
+            .map(|frame_result| frame_result.damage.is_some())
+            .unwrap_or(false);
+        // wait for a repaint that actually changed something
+        if screencopy.with_damage() && !damaged {
+            surface.pending_screencopies.push(screencopy);
+            continue;
+        }
+
+        if let Some(frame_result) = frame_result.as_ref() {
             // Mark entire buffer as damaged.
             let region = screencopy.region();
             if let Some(damage) = frame_result.damage.clone() {
@@ -1806,8 +2621,13 @@ fn render_surface<'a, 'b, 'c>(
                 // Calculate drawing area after output transform.
                 let damage = transform.transform_rect_in(region, &output_size);
 
+                let filter = if screencopy.overlay_cursor {
+                    Vec::new()
+                } else {
+                    cursor_element_ids.clone()
+                };
                 frame_result
-                    .blit_frame_result(damage.size, transform, scale, renderer, [damage], [])
+                    .blit_frame_result(damage.size, transform, scale, renderer, [damage], filter)
                     .unwrap()
                     .wait();
 
@@ -1845,6 +2665,50 @@ fn render_surface<'a, 'b, 'c>(
         }
     }
 
+    if std::mem::take(&mut surface.dump_frame) {
+        match frame_result.as_ref() {
+            Some(frame_result) => {
+                let output_size = output.current_mode().unwrap().size;
+                let transform = output.current_transform();
+                let scale = output.current_scale().fractional_scale();
+                let size = transform.transform_size(output_size);
+                let damage = transform
+                    .transform_rect_in(Rectangle::from_loc_and_size((0, 0), size), &output_size);
+
+                // blitting the frame result picks up the planes too, like the cursor
+                let dumped = Offscreen::<GlesTexture>::create_buffer(
+                    renderer,
+                    Fourcc::Abgr8888,
+                    (size.w, size.h).into(),
+                )
+                .and_then(|buffer| renderer.bind(buffer))
+                .map_err(|err| err.to_string())
+                .and_then(|_| {
+                    frame_result
+                        .blit_frame_result(
+                            damage.size,
+                            transform,
+                            scale,
+                            renderer,
+                            [damage],
+                            Vec::new(),
+                        )
+                        .map_err(|err| err.to_string())
+                })
+                .and_then(|sync| {
+                    sync.wait();
+                    write_frame_dump(renderer, &output.name(), size)
+                });
+
+                match dumped {
+                    Ok(path) => info!(?path, "Dumped frame"),
+                    Err(err) => warn!(%err, "Failed to dump frame"),
+                }
+            }
+            None => warn!("Dumping frames needs the drm compositor"),
+        }
+    }
+
     post_repaint(
         output,
         &res.states,
@@ -1870,6 +2734,48 @@ fn render_surface<'a, 'b, 'c>(
     Ok(res.rendered)
 }
 
+fn frame_dumps_dir() -> PathBuf {
+    let home_dir = std::env::var("HOME").expect("HOME should always be set");
+    PathBuf::from(home_dir).join(".cache/buddaraysh/frames/")
+}
+
+/// Copies the bound framebuffer into a png named after the output and the time
+fn write_frame_dump(
+    renderer: &mut UdevRenderer<'_, '_, '_>,
+    output_name: &str,
+    size: Size<i32, Physical>,
+) -> Result<PathBuf, String> {
+    let dir = frame_dumps_dir();
+    std::fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dir.join(format!("{output_name}-{timestamp}.png"));
+    save_framebuffer(renderer, size, &path)?;
+
+    Ok(path)
+}
+
+/// Copies the bound framebuffer into a png at `path`
+fn save_framebuffer(
+    renderer: &mut UdevRenderer<'_, '_, '_>,
+    size: Size<i32, Physical>,
+    path: &Path,
+) -> Result<(), String> {
+    let region = Rectangle::from_loc_and_size((0, 0), (size.w, size.h));
+    let mapping = renderer
+        .copy_framebuffer(region, Fourcc::Abgr8888)
+        .map_err(|err| err.to_string())?;
+    let pixels = renderer
+        .map_texture(&mapping)
+        .map_err(|err| err.to_string())?;
+    let image = image::RgbaImage::from_raw(size.w as u32, size.h as u32, pixels.to_vec())
+        .ok_or("frame doesn't match the output size")?;
+
+    image.save(path).map_err(|err| err.to_string())
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct SurfaceDmabufFeedback<'a> {
     pub render_feedback: &'a DmabufFeedback,
@@ -1904,6 +2810,17 @@ pub fn post_repaint(
             }
         });
 
+        // a window that isn't scanned out on any output is fully occluded or off-screen
+        let visible = window
+            .wl_surface()
+            .map(|surface| {
+                compositor::with_states(&surface, |states| {
+                    surface_primary_scanout_output(&surface, states).is_some()
+                })
+            })
+            .unwrap_or(true);
+        window.set_suspended(!visible);
+
         if space.outputs_for_element(window).contains(output) {
             window.send_frame(output, time, throttle, surface_primary_scanout_output);
             if let Some(dmabuf_feedback) = dmabuf_feedback {
@@ -2004,21 +2921,163 @@ fn initial_render(
     Ok(())
 }
 
+/// Scans out a black frame, for outputs that got disabled
+fn blank_render(
+    surface: &mut Surface,
+    renderer: &mut UdevRenderer<'_, '_, '_>,
+) -> Result<(), SwapBuffersError> {
+    surface
+        .compositor
+        .render_frame::<_, CustomRenderElements<_>, GlesTexture>(
+            renderer,
+            &[],
+            [0.0, 0.0, 0.0, 1.0],
+        )?;
+    surface.compositor.queue_frame(None, None, None)?;
+    surface.compositor.reset_buffers();
+
+    Ok(())
+}
+
 impl ScreencopyHandler for Buddaraysh<UdevData> {
     fn output(&mut self, output: &WlOutput) -> &Output {
-        self.workspaces.outputs().find(|o| o.owns(output)).unwrap()
+        self.workspaces
+            .outputs()
+            .find(|o| o.owns(output))
+            // disabled outputs aren't part of the workspaces, but their global is still around
+            .or_else(|| {
+                self.backend_data
+                    .backends
+                    .values()
+                    .flat_map(|device| device.surfaces.values())
+                    .map(|surface| &surface.output)
+                    .find(|o| o.owns(output))
+            })
+            .unwrap()
     }
 
     fn frame(&mut self, frame: Screencopy) {
-        for (node, device) in &self.backend_data.backends {
-            for (crtc, surface) in &device.surfaces {
-                if surface.output == frame.output {
-                    self.render(*node, Some(*crtc), Some(frame));
-                    return;
-                }
-            }
+        let Some((node, crtc, surface)) =
+            self.backend_data
+                .backends
+                .iter_mut()
+                .find_map(|(node, device)| {
+                    device
+                        .surfaces
+                        .iter_mut()
+                        .find(|(_, surface)| surface.output == frame.output)
+                        .map(|(crtc, surface)| (*node, *crtc, surface))
+                })
+        else {
+            return;
+        };
+
+        // frames waiting for damage are copied out of the regular repaints,
+        // so continuous captures don't cost an extra render each
+        let render_now = !frame.with_damage();
+        surface.pending_screencopies.push(frame);
+        if render_now {
+            self.render(node, Some(crtc));
         }
     }
+
+    fn can_capture(&self, client: &Client) -> bool {
+        client_is_privileged(client, &self.sandbox_allowlist)
+    }
 }
 
 delegate_screencopy_manager!(Buddaraysh<UdevData>);
+
+impl OutputPowerHandler for Buddaraysh<UdevData> {
+    fn output_power_state(&mut self) -> &mut OutputPowerManagementState {
+        &mut self.backend_data.output_power_state
+    }
+
+    fn output_power(&self, output: &Output) -> bool {
+        self.backend_data
+            .backends
+            .values()
+            .flat_map(|device| device.surfaces.values())
+            .find(|surface| surface.output == *output)
+            .map(|surface| surface.power == OutputPower::On)
+            .unwrap_or(false)
+    }
+
+    fn set_output_power(&mut self, output: &Output, on: bool) {
+        let power = if on {
+            OutputPower::On
+        } else {
+            OutputPower::Off
+        };
+        self.set_output_power_mode(output, power);
+    }
+}
+
+delegate_output_power_management!(Buddaraysh<UdevData>);
+
+#[cfg(test)]
+mod tests {
+    use super::RequestedMode;
+
+    const MODES: [((u16, u16), i32); 4] = [
+        ((2560, 1440), 59_951),
+        ((2560, 1440), 143_912),
+        ((2560, 1440), 120_000),
+        ((1920, 1080), 60_000),
+    ];
+
+    #[test]
+    fn requested_mode_parses_size_and_refresh() {
+        assert_eq!(
+            RequestedMode::parse("2560x1440@144"),
+            Some(RequestedMode {
+                width: 2560,
+                height: 1440,
+                refresh: Some(144.0),
+            })
+        );
+        assert_eq!(
+            RequestedMode::parse(" 1920 x 1080 "),
+            Some(RequestedMode {
+                width: 1920,
+                height: 1080,
+                refresh: None,
+            })
+        );
+    }
+
+    #[test]
+    fn requested_mode_rejects_garbage() {
+        assert_eq!(RequestedMode::parse("2560"), None);
+        assert_eq!(RequestedMode::parse("2560x1440@fast"), None);
+        assert_eq!(RequestedMode::parse("widexhigh"), None);
+    }
+
+    #[test]
+    fn requested_mode_picks_the_closest_refresh_rate() {
+        let mode = RequestedMode::parse("2560x1440@144").unwrap();
+
+        assert_eq!(mode.closest(MODES.into_iter()), Some(1));
+    }
+
+    #[test]
+    fn requested_mode_without_refresh_picks_the_highest() {
+        let mode = RequestedMode::parse("2560x1440").unwrap();
+
+        assert_eq!(mode.closest(MODES.into_iter()), Some(1));
+    }
+
+    #[test]
+    fn requested_mode_needs_a_refresh_rate_within_1hz() {
+        let mode = RequestedMode::parse("2560x1440@100").unwrap();
+
+        assert_eq!(mode.closest(MODES.into_iter()), None);
+    }
+
+    #[test]
+    fn requested_mode_needs_the_same_size() {
+        let mode = RequestedMode::parse("3840x2160").unwrap();
+
+        assert_eq!(mode.closest(MODES.into_iter()), None);
+    }
+}
@@ -1,4 +1,8 @@
-use std::time::Duration;
+use std::{
+    cell::RefCell,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
 
 use smithay::{
     backend::{
@@ -6,12 +10,15 @@ use smithay::{
         renderer::{
             element::{
                 solid::SolidColorRenderElement, surface::WaylandSurfaceRenderElement,
-                AsRenderElements,
+                AsRenderElements, Kind,
             },
             ImportAll, ImportMem, Renderer, Texture,
         },
     },
-    desktop::{space::SpaceElement, utils::OutputPresentationFeedback, Window, WindowSurfaceType},
+    desktop::{
+        space::SpaceElement, utils::OutputPresentationFeedback, PopupManager, Space, Window,
+        WindowSurfaceType,
+    },
     input::{
         keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
         pointer::{
@@ -24,13 +31,21 @@ use smithay::{
     },
     output::Output,
     reexports::{
-        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
-        wayland_server::protocol::wl_surface::WlSurface,
+        wayland_protocols::{
+            wp::presentation_time::server::wp_presentation_feedback,
+            xdg::shell::server::xdg_toplevel,
+        },
+        wayland_server::{protocol::wl_surface::WlSurface, Resource},
     },
     render_elements,
-    utils::{user_data::UserDataMap, IsAlive, Logical, Physical, Point, Rectangle, Scale, Serial},
+    utils::{
+        user_data::UserDataMap, IsAlive, Logical, Physical, Point, Rectangle, Scale, Serial, Size,
+    },
     wayland::{
-        compositor::SurfaceData as WlSurfaceData, dmabuf::DmabufFeedback, seat::WaylandFocus,
+        compositor::{with_states, SurfaceData as WlSurfaceData},
+        dmabuf::DmabufFeedback,
+        seat::WaylandFocus,
+        shell::xdg::{SurfaceCachedState, XdgToplevelSurfaceData},
     },
 };
 #[cfg(feature = "xwayland")]
@@ -44,7 +59,56 @@ use smithay::{
 };
 
 use super::ssd::HEADER_BAR_HEIGHT;
-use crate::{Backend, Buddaraysh};
+use crate::{
+    focus::FocusTarget, protocols::single_pixel_buffer, shell::FullscreenSurface, Backend,
+    Buddaraysh,
+};
+
+const DIM_COLOR: [f32; 4] = [0f32, 0f32, 0f32, 1f32];
+
+/// How many closed windows are remembered
+const RECENTLY_CLOSED_CAPACITY: usize = 10;
+
+/// A window that got closed, kept around so an accidental close can be looked up
+#[derive(Debug, Clone)]
+pub struct ClosedWindow {
+    pub app_id: Option<String>,
+    pub title: Option<String>,
+    pub workspace_index: Option<usize>,
+    pub closed_at: Instant,
+}
+
+/// The size the client picked for itself when it was first mapped
+#[derive(Debug, Default)]
+struct PreferredSize(RefCell<Option<Size<i32, Logical>>>);
+
+/// Whether the window is shown on every output, not only the one it's on
+#[derive(Debug, Default)]
+struct StickyAllOutputs(RefCell<bool>);
+
+/// The sticky windows of the space that aren't on the given output, topmost first,
+/// with the location of their copy relative to the output.
+///
+/// The copy is placed at the same offset from the output's origin the window has
+/// from the origin of the output it's actually on.
+pub fn sticky_copies<'a>(
+    space: &'a Space<WindowElement>,
+    output: &'a Output,
+) -> impl Iterator<Item = (&'a WindowElement, Point<i32, Logical>)> + 'a {
+    space
+        .elements()
+        .rev()
+        .filter(|window| window.is_sticky_all_outputs())
+        .filter_map(move |window| {
+            let outputs = space.outputs_for_element(window);
+            if outputs.contains(output) {
+                return None;
+            }
+            let home_geometry = space.output_geometry(outputs.first()?)?;
+            let location = space.element_location(window)?;
+            Some((window, location - home_geometry.loc))
+        })
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum WindowElement {
@@ -60,7 +124,18 @@ impl WindowElement {
         window_type: WindowSurfaceType,
     ) -> Option<(WlSurface, Point<i32, Logical>)> {
         match self {
-            WindowElement::Wayland(w) => w.surface_under(location, window_type),
+            WindowElement::Wayland(w) => w.surface_under(location, window_type).or_else(|| {
+                // single pixel buffers are mostly backgrounds, under the surfaces smithay finds
+                if window_type.contains(WindowSurfaceType::TOPLEVEL) {
+                    single_pixel_buffer::under_from_surface_tree(
+                        w.toplevel().wl_surface(),
+                        location,
+                        (0, 0),
+                    )
+                } else {
+                    None
+                }
+            }),
             #[cfg(feature = "xwayland")]
             WindowElement::X11(w) => w
                 .wl_surface()
@@ -185,6 +260,22 @@ impl WindowElement {
         }
     }
 
+    /// Whether this is a dialog or another transient window of `parent`
+    pub fn is_transient_for(&self, parent: &WindowElement) -> bool {
+        match (self, parent) {
+            (WindowElement::Wayland(w), _) => w
+                .toplevel()
+                .parent()
+                .map_or(false, |surface| Some(surface) == parent.wl_surface()),
+            #[cfg(feature = "xwayland")]
+            (WindowElement::X11(w), WindowElement::X11(p)) => {
+                w.is_transient_for() == Some(p.window_id())
+            }
+            #[cfg(feature = "xwayland")]
+            (WindowElement::X11(_), _) => false,
+        }
+    }
+
     pub fn user_data(&self) -> &UserDataMap {
         match self {
             WindowElement::Wayland(w) => w.user_data(),
@@ -204,6 +295,141 @@ impl WindowElement {
             }
         }
     }
+
+    /// The xdg app id, or the WM_CLASS of X11 windows
+    pub fn app_id(&self) -> Option<String> {
+        match self {
+            WindowElement::Wayland(w) => with_states(w.toplevel().wl_surface(), |states| {
+                states
+                    .data_map
+                    .get::<XdgToplevelSurfaceData>()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .app_id
+                    .clone()
+            }),
+            #[cfg(feature = "xwayland")]
+            WindowElement::X11(w) => Some(w.class()),
+        }
+    }
+
+    /// The xdg title, or the WM_NAME of X11 windows
+    pub fn title(&self) -> Option<String> {
+        match self {
+            WindowElement::Wayland(w) => with_states(w.toplevel().wl_surface(), |states| {
+                states
+                    .data_map
+                    .get::<XdgToplevelSurfaceData>()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .title
+                    .clone()
+            }),
+            #[cfg(feature = "xwayland")]
+            WindowElement::X11(w) => Some(w.title()),
+        }
+    }
+
+    /// Tells the client whether it's hidden, so it can stop rendering
+    pub fn set_suspended(&self, suspended: bool) {
+        if let WindowElement::Wayland(w) = self {
+            let toplevel = w.toplevel();
+            if !toplevel.is_initial_configure_sent() {
+                return;
+            }
+
+            toplevel.with_pending_state(|state| {
+                if suspended {
+                    state.states.set(xdg_toplevel::State::Suspended);
+                } else {
+                    state.states.unset(xdg_toplevel::State::Suspended);
+                }
+            });
+            // only sends a configure if the state actually changed
+            toplevel.send_pending_configure();
+        }
+    }
+
+    /// Saves the given size as the preferred size of the window, if it wasn't saved already
+    pub fn save_preferred_size(&self, size: Size<i32, Logical>) {
+        // the initial configure can already be maximized or fullscreen, that size is the
+        // output's and not one the client picked
+        let maximized_or_fullscreen = match self {
+            WindowElement::Wayland(w) => {
+                let states = w.toplevel().current_state().states;
+                states.contains(xdg_toplevel::State::Maximized)
+                    || states.contains(xdg_toplevel::State::Fullscreen)
+            }
+            #[cfg(feature = "xwayland")]
+            WindowElement::X11(w) => w.is_maximized() || w.is_fullscreen(),
+        };
+        if maximized_or_fullscreen {
+            return;
+        }
+
+        self.user_data().insert_if_missing(PreferredSize::default);
+        let mut preferred_size = self
+            .user_data()
+            .get::<PreferredSize>()
+            .unwrap()
+            .0
+            .borrow_mut();
+        if preferred_size.is_none() && !size.is_empty() {
+            *preferred_size = Some(size);
+        }
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        match self {
+            WindowElement::Wayland(w) => w
+                .toplevel()
+                .current_state()
+                .states
+                .contains(xdg_toplevel::State::Maximized),
+            #[cfg(feature = "xwayland")]
+            WindowElement::X11(w) => w.is_maximized(),
+        }
+    }
+
+    pub fn is_sticky_all_outputs(&self) -> bool {
+        self.user_data()
+            .get::<StickyAllOutputs>()
+            .map(|sticky| *sticky.0.borrow())
+            .unwrap_or(false)
+    }
+
+    pub fn set_sticky_all_outputs(&self, sticky: bool) {
+        self.user_data()
+            .insert_if_missing(StickyAllOutputs::default);
+        *self
+            .user_data()
+            .get::<StickyAllOutputs>()
+            .unwrap()
+            .0
+            .borrow_mut() = sticky;
+    }
+
+    /// Returns the size the client initially picked, or its minimum size if it's unknown
+    pub fn preferred_size(&self) -> Option<Size<i32, Logical>> {
+        if let Some(size) = self
+            .user_data()
+            .get::<PreferredSize>()
+            .and_then(|s| *s.0.borrow())
+        {
+            return Some(size);
+        }
+
+        match self {
+            WindowElement::Wayland(w) => Some(with_states(w.toplevel().wl_surface(), |states| {
+                states.cached_state.current::<SurfaceCachedState>().min_size
+            })),
+            #[cfg(feature = "xwayland")]
+            WindowElement::X11(w) => w.base_size().or_else(|| w.min_size()),
+        }
+        .filter(|size| !size.is_empty())
+    }
 }
 
 impl IsAlive for WindowElement {
@@ -581,7 +807,7 @@ impl<Backend: crate::Backend> KeyboardTarget<Buddaraysh<Backend>> for WindowElem
 impl SpaceElement for WindowElement {
     fn geometry(&self) -> Rectangle<i32, Logical> {
         let mut geo = match self {
-            WindowElement::Wayland(w) => SpaceElement::geometry(w),
+            WindowElement::Wayland(w) => wayland_geometry(w),
             #[cfg(feature = "xwayland")]
             WindowElement::X11(w) => SpaceElement::geometry(w),
         };
@@ -592,7 +818,11 @@ impl SpaceElement for WindowElement {
     }
     fn bbox(&self) -> Rectangle<i32, Logical> {
         let mut bbox = match self {
-            WindowElement::Wayland(w) => SpaceElement::bbox(w),
+            WindowElement::Wayland(w) => {
+                let bbox = SpaceElement::bbox(w);
+                single_pixel_buffer::bbox_from_surface_tree(w.toplevel().wl_surface(), (0, 0))
+                    .map_or(bbox, |single_pixel| bbox.merge(single_pixel))
+            }
             #[cfg(feature = "xwayland")]
             WindowElement::X11(w) => SpaceElement::bbox(w),
         };
@@ -605,9 +835,9 @@ impl SpaceElement for WindowElement {
         if self.decoration_state().is_ssd {
             point.y < HEADER_BAR_HEIGHT as f64
                 || match self {
-                    WindowElement::Wayland(w) => SpaceElement::is_in_input_region(
+                    WindowElement::Wayland(w) => wayland_is_in_input_region(
                         w,
-                        &(*point - Point::from((0.0, HEADER_BAR_HEIGHT as f64))),
+                        *point - Point::from((0.0, HEADER_BAR_HEIGHT as f64)),
                     ),
                     #[cfg(feature = "xwayland")]
                     WindowElement::X11(w) => SpaceElement::is_in_input_region(
@@ -617,7 +847,7 @@ impl SpaceElement for WindowElement {
                 }
         } else {
             match self {
-                WindowElement::Wayland(w) => SpaceElement::is_in_input_region(w, point),
+                WindowElement::Wayland(w) => wayland_is_in_input_region(w, *point),
                 #[cfg(feature = "xwayland")]
                 WindowElement::X11(w) => SpaceElement::is_in_input_region(w, point),
             }
@@ -662,6 +892,36 @@ impl SpaceElement for WindowElement {
     }
 }
 
+/// The window's geometry, falling back to a bounding box that includes single pixel buffers
+/// when the client didn't set one
+fn wayland_geometry(window: &Window) -> Rectangle<i32, Logical> {
+    let geometry = SpaceElement::geometry(window);
+    let surface = window.toplevel().wl_surface();
+    let has_window_geometry = with_states(surface, |states| {
+        states
+            .cached_state
+            .current::<SurfaceCachedState>()
+            .geometry
+            .is_some()
+    });
+    if has_window_geometry {
+        return geometry;
+    }
+
+    single_pixel_buffer::bbox_from_surface_tree(surface, (0, 0))
+        .map_or(geometry, |single_pixel| geometry.merge(single_pixel))
+}
+
+fn wayland_is_in_input_region(window: &Window, point: Point<f64, Logical>) -> bool {
+    SpaceElement::is_in_input_region(window, &point)
+        || single_pixel_buffer::under_from_surface_tree(
+            window.toplevel().wl_surface(),
+            point,
+            (0, 0),
+        )
+        .is_some()
+}
+
 render_elements!(
     pub WindowRenderElement<R> where R: ImportAll + ImportMem;
     Window=WaylandSurfaceRenderElement<R>,
@@ -698,6 +958,33 @@ where
             WindowElement::X11(w) => SpaceElement::bbox(w),
         };
 
+        let dim_element = {
+            let mut state = self.decoration_state();
+            let dim_alpha = state.dim_alpha;
+            match dim_alpha {
+                Some(dim_alpha) if !window_bbox.is_empty() => {
+                    let mut size = window_bbox.size;
+                    if state.is_ssd {
+                        size.h += HEADER_BAR_HEIGHT;
+                    }
+                    state.dim_overlay.update(size, DIM_COLOR);
+                    Some(C::from(WindowRenderElement::<R>::Decoration(
+                        SolidColorRenderElement::from_buffer(
+                            &state.dim_overlay,
+                            location,
+                            scale,
+                            dim_alpha * alpha,
+                            Kind::Unspecified,
+                        ),
+                    )))
+                }
+                _ => None,
+            }
+        };
+
+        // the overlay has to come first so it's drawn on top of the window
+        let mut elements: Vec<C> = dim_element.into_iter().collect();
+
         if self.decoration_state().is_ssd && !window_bbox.is_empty() {
             let window_geo = match self {
                 WindowElement::Wayland(w) => SpaceElement::geometry(w),
@@ -720,9 +1007,7 @@ where
 
             let window_elements = match self {
                 WindowElement::Wayland(xdg) => {
-                    AsRenderElements::<R>::render_elements::<WindowRenderElement<R>>(
-                        xdg, renderer, location, scale, alpha,
-                    )
+                    wayland_window_elements::<R>(xdg, renderer, location, scale, alpha)
                 }
                 #[cfg(feature = "xwayland")]
                 WindowElement::X11(x11) => AsRenderElements::<R>::render_elements::<
@@ -730,26 +1015,66 @@ where
                 >(x11, renderer, location, scale, alpha),
             };
             vec.extend(window_elements);
-            vec.into_iter().map(C::from).collect()
+            elements.extend(vec.into_iter().map(C::from));
+            elements
         } else {
-            match self {
+            let window_elements = match self {
                 WindowElement::Wayland(xdg) => {
-                    AsRenderElements::<R>::render_elements::<WindowRenderElement<R>>(
-                        xdg, renderer, location, scale, alpha,
-                    )
+                    wayland_window_elements::<R>(xdg, renderer, location, scale, alpha)
                 }
                 #[cfg(feature = "xwayland")]
                 WindowElement::X11(x11) => AsRenderElements::<R>::render_elements::<
                     WindowRenderElement<R>,
                 >(x11, renderer, location, scale, alpha),
-            }
-            .into_iter()
-            .map(C::from)
-            .collect()
+            };
+            elements.extend(window_elements.into_iter().map(C::from));
+            elements
         }
     }
 }
 
+/// Draws the window like smithay does, its popups on top of its surface tree, but with single
+/// pixel buffers drawn in their place in the tree
+fn wayland_window_elements<R>(
+    window: &Window,
+    renderer: &mut R,
+    location: Point<i32, Physical>,
+    scale: Scale<f64>,
+    alpha: f32,
+) -> Vec<WindowRenderElement<R>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    <R as Renderer>::TextureId: Texture + 'static,
+{
+    let surface = window.toplevel().wl_surface();
+
+    let mut elements: Vec<WindowRenderElement<R>> = PopupManager::popups_for_surface(surface)
+        .flat_map(|(popup, popup_offset)| {
+            let offset = (window.geometry().loc + popup_offset - popup.geometry().loc)
+                .to_physical_precise_round(scale);
+            single_pixel_buffer::render_elements_from_surface_tree(
+                renderer,
+                popup.wl_surface(),
+                location + offset,
+                scale,
+                alpha,
+                Kind::Unspecified,
+            )
+        })
+        .collect();
+
+    elements.extend(single_pixel_buffer::render_elements_from_surface_tree(
+        renderer,
+        surface,
+        location,
+        scale,
+        alpha,
+        Kind::Unspecified,
+    ));
+
+    elements
+}
+
 impl<BackendData: Backend> Buddaraysh<BackendData> {
     pub fn window_for_surface(&self, surface: &WlSurface) -> Option<WindowElement> {
         self.workspaces
@@ -758,4 +1083,120 @@ impl<BackendData: Backend> Buddaraysh<BackendData> {
             .find(|window| window.wl_surface().map(|s| s == *surface).unwrap_or(false))
             .cloned()
     }
+
+    /// Finds a window on any workspace by its app id, or by a part of its title
+    pub fn find_window(&self, query: &str) -> Option<WindowElement> {
+        let windows = || {
+            self.workspaces
+                .workspaces()
+                .into_iter()
+                .flat_map(|workspace| workspace.windows())
+        };
+        let query = query.to_lowercase();
+
+        windows()
+            .find(|window| window.app_id().map(|x| x.to_lowercase()).as_ref() == Some(&query))
+            .or_else(|| {
+                windows().find(|window| {
+                    window
+                        .title()
+                        .map(|x| x.to_lowercase().contains(&query))
+                        .unwrap_or(false)
+                })
+            })
+            .cloned()
+    }
+
+    /// Updates which windows get the dim overlay, based on the given keyboard focus
+    pub fn update_focus_dim(&self, focused: Option<&FocusTarget>) {
+        let focused_window = match focused {
+            Some(FocusTarget::Window(w)) => Some(w),
+            _ => None,
+        };
+
+        let fullscreen_windows: Vec<WindowElement> = self
+            .workspaces
+            .outputs()
+            .filter_map(|o| o.user_data().get::<FullscreenSurface>())
+            .filter_map(|f| f.get().0)
+            .collect();
+
+        for workspace in self.workspaces.workspaces() {
+            for window in workspace.windows() {
+                let dimmed = self.focus_dim
+                    && Some(window) != focused_window
+                    && (self.dim_fullscreen || !fullscreen_windows.contains(window));
+
+                window.decoration_state().dim_alpha = dimmed.then_some(self.focus_dim_alpha);
+            }
+        }
+    }
+
+    /// [`Self::update_focus_dim`] with the current keyboard focus, for when windows got mapped
+    /// without the focus changing
+    pub fn refresh_focus_dim(&self) {
+        let focus = self.seat.get_keyboard().unwrap().current_focus();
+        self.update_focus_dim(focus.as_ref());
+    }
+
+    /// Process id of the client owning the window
+    pub fn window_pid(&self, window: &WindowElement) -> Option<u32> {
+        match window {
+            WindowElement::Wayland(w) => self
+                .display_handle
+                .get_client(w.toplevel().wl_surface().id())
+                .ok()
+                .and_then(|client| client.get_credentials(&self.display_handle).ok())
+                .map(|credentials| credentials.pid as u32),
+            #[cfg(feature = "xwayland")]
+            WindowElement::X11(w) => w.pid(),
+        }
+    }
+
+    /// Suspends the windows of every workspace but the current one, and resumes the current ones.
+    ///
+    /// Occluded windows of the current workspace get suspended after they're rendered
+    pub fn update_suspended_windows(&self) {
+        let current_workspace_index = self.workspaces.current_workspace_index();
+        for (index, workspace) in self.workspaces.workspaces().into_iter().enumerate() {
+            let suspended = index != current_workspace_index;
+            workspace
+                .windows()
+                .for_each(|window| window.set_suspended(suspended));
+        }
+    }
+
+    /// Remembers the closed window, and quits in kiosk mode when the kiosk app closed
+    /// or there are no windows left
+    pub fn window_closed(&mut self, window: &WindowElement, workspace_index: Option<usize>) {
+        let app_id = window.app_id();
+        if self.recently_closed.len() == RECENTLY_CLOSED_CAPACITY {
+            self.recently_closed.pop_front();
+        }
+        self.recently_closed.push_back(ClosedWindow {
+            app_id: app_id.clone(),
+            title: window.title(),
+            workspace_index,
+            closed_at: Instant::now(),
+        });
+
+        let app_id = app_id.as_deref();
+        let kiosk_app_closed = self.kiosk_app.is_some() && self.kiosk_app.as_deref() == app_id;
+
+        let no_windows_left = self
+            .workspaces
+            .workspaces()
+            .iter()
+            .flat_map(|workspace| workspace.windows())
+            .all(|window| match window {
+                WindowElement::Wayland(_) => false,
+                #[cfg(feature = "xwayland")]
+                WindowElement::X11(surface) => surface.is_override_redirect(),
+            });
+
+        if kiosk_app_closed || (self.quit_on_empty && no_windows_left) {
+            tracing::info!(?app_id, "Last kiosk window closed, quitting.");
+            self.running.store(false, Ordering::SeqCst);
+        }
+    }
 }
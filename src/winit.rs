@@ -2,17 +2,19 @@ use std::{sync::Mutex, time::Duration};
 
 use smithay::{
     backend::{
-        allocator::dmabuf::Dmabuf,
+        allocator::{dmabuf::Dmabuf, Fourcc},
         egl::EGLDevice,
         renderer::{
-            damage::OutputDamageTracker, element::AsRenderElements, gles::GlesRenderer, ImportDma,
-            ImportEgl,
+            damage::OutputDamageTracker,
+            element::{texture::TextureBuffer, AsRenderElements},
+            gles::{GlesRenderer, GlesTexture},
+            ImportDma, ImportEgl, ImportMem,
         },
         winit::{self, WinitEvent, WinitGraphicsBackend},
     },
     delegate_dmabuf,
     desktop::space::SurfaceTree,
-    input::pointer::{CursorImageAttributes, CursorImageStatus},
+    input::pointer::{CursorIcon, CursorImageAttributes, CursorImageStatus},
     output::{Mode, Output, PhysicalProperties, Subpixel},
     reexports::{
         calloop::EventLoop,
@@ -29,11 +31,19 @@ use smithay::{
 };
 use tracing::{error, info, warn};
 
-use crate::{render::CustomRenderElements, Backend, Buddaraysh, CalloopData};
+use crate::{
+    drawing::{ClockElement, PointerElement, NUMBERS_PNG},
+    render::{output_elements, CustomRenderElements},
+    Backend, Buddaraysh, CalloopData, LogFilterHandle,
+};
 
 pub struct WinitData {
     backend: WinitGraphicsBackend<GlesRenderer>,
     damage_tracker: OutputDamageTracker,
+    pointer_image: crate::cursor::Cursor,
+    pointer_images: Vec<(xcursor::parser::Image, TextureBuffer<GlesTexture>)>,
+    pointer_element: PointerElement<GlesTexture>,
+    clock_element: Option<ClockElement<GlesTexture>>,
     dmabuf_state: (DmabufState, DmabufGlobal, Option<DmabufFeedback>),
     full_redraw: u8,
     #[cfg(feature = "debug")]
@@ -76,7 +86,7 @@ delegate_dmabuf!(Buddaraysh<WinitData>);
 
 pub const OUTPUT_NAME: &str = "winit";
 
-pub fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
+pub fn run_winit(log_filter: LogFilterHandle) -> Result<(), Box<dyn std::error::Error>> {
     let mut event_loop: EventLoop<CalloopData<WinitData>> = EventLoop::try_new()?;
     let display: Display<Buddaraysh<WinitData>> = Display::new()?;
     let display_handle = display.handle();
@@ -89,6 +99,8 @@ pub fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
             return Err(format!("Failed to initialize Winit backend: {}", err).into());
         }
     };
+    // the cursor is drawn by us, same as on udev, so the host's one would only get in the way
+    backend.window().set_cursor_visible(false);
     let size = backend.window_size();
 
     let mode = Mode {
@@ -166,10 +178,20 @@ pub fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
     let data = WinitData {
         backend,
         damage_tracker,
+        pointer_image: crate::cursor::Cursor::load(),
+        pointer_images: Vec::new(),
+        pointer_element: PointerElement::default(),
+        clock_element: None,
         dmabuf_state,
         full_redraw: 0,
     };
-    let state = Buddaraysh::new(event_loop.handle(), &mut event_loop, display, data);
+    let state = Buddaraysh::new(
+        event_loop.handle(),
+        &mut event_loop,
+        display,
+        data,
+        log_filter,
+    );
 
     let mut data = CalloopData {
         state,
@@ -207,8 +229,54 @@ pub fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
 
                     state.backend_data.backend.bind().unwrap();
 
+                    let scale = Scale::from(output.current_scale().fractional_scale());
+                    let cursor_scale = output.current_scale().integer_scale().max(1) as u32;
+                    let busy = matches!(
+                        *state.cursor_status.lock().unwrap(),
+                        CursorImageStatus::Named(CursorIcon::Progress)
+                    );
+                    let frame = state.backend_data.pointer_image.get_image(
+                        busy,
+                        cursor_scale,
+                        state.clock.now().try_into().unwrap(),
+                    );
+
+                    let active_output = state.active_output();
+                    let grid_size = state
+                        .snap_grid()
+                        .filter(|_| state.grid_output().as_ref() == Some(&output));
+                    let spotlight = state
+                        .spotlight_area()
+                        .map(|area| (area, state.config.spotlight.alpha));
+
                     let renderer = state.backend_data.backend.renderer();
 
+                    let pointer_images = &mut state.backend_data.pointer_images;
+                    let pointer_image = pointer_images
+                        .iter()
+                        .find_map(|(image, texture)| {
+                            if image == &frame {
+                                Some(texture.clone())
+                            } else {
+                                None
+                            }
+                        })
+                        .unwrap_or_else(|| {
+                            let texture = TextureBuffer::from_memory(
+                                renderer,
+                                &frame.pixels_rgba,
+                                Fourcc::Abgr8888,
+                                (frame.width as i32, frame.height as i32),
+                                false,
+                                cursor_scale as i32,
+                                Transform::Normal,
+                                None,
+                            )
+                            .expect("Failed to import cursor bitmap");
+                            pointer_images.push((frame, texture.clone()));
+                            texture
+                        });
+
                     let mut cursor_guard = state.cursor_status.lock().unwrap();
 
                     // draw the cursor as relevant
@@ -220,11 +288,11 @@ pub fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
                     if reset {
                         *cursor_guard = CursorImageStatus::default_named();
                     }
-                    // let cursor_visible = !matches!(*cursor_guard, CursorImageStatus::Surface(_));
 
-                    // pointer_element.set_status(cursor_guard.clone());
+                    let pointer_element = &mut state.backend_data.pointer_element;
+                    pointer_element.set_texture(pointer_image);
+                    pointer_element.set_status(cursor_guard.clone());
 
-                    let scale = Scale::from(output.current_scale().fractional_scale());
                     let cursor_hotspot =
                         if let CursorImageStatus::Surface(ref surface) = *cursor_guard {
                             compositor::with_states(surface, |states| {
@@ -243,7 +311,8 @@ pub fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
                     let cursor_pos = state.pointer.current_location() - cursor_hotspot.to_f64();
                     let cursor_pos_scaled = cursor_pos.to_physical(scale).to_i32_round();
 
-                    let mut custom_elements = vec![];
+                    let mut custom_elements: Vec<CustomRenderElements<GlesRenderer>> =
+                        pointer_element.render_elements(renderer, cursor_pos_scaled, scale, 1.0);
 
                     let dnd_icon = state.dnd_icon.as_ref();
 
@@ -262,22 +331,58 @@ pub fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
 
-                    smithay::desktop::space::render_output::<
-                        _,
-                        CustomRenderElements<GlesRenderer>,
-                        _,
-                        _,
-                    >(
+                    if let Some(text) = state.clock_text.as_deref() {
+                        if state.backend_data.clock_element.is_none() {
+                            let numbers_image = image::io::Reader::with_format(
+                                std::io::Cursor::new(NUMBERS_PNG),
+                                image::ImageFormat::Png,
+                            )
+                            .decode()
+                            .unwrap();
+                            match renderer.import_memory(
+                                &numbers_image.to_rgba8(),
+                                Fourcc::Abgr8888,
+                                (numbers_image.width() as i32, numbers_image.height() as i32)
+                                    .into(),
+                                false,
+                            ) {
+                                Ok(texture) => {
+                                    state.backend_data.clock_element =
+                                        Some(ClockElement::new(texture))
+                                }
+                                Err(err) => warn!("Failed to import clock texture: {:?}", err),
+                            }
+                        }
+
+                        if let Some(element) = state.backend_data.clock_element.as_mut() {
+                            element.set_text(text);
+                            let size = element.size().to_physical_precise_round(scale);
+                            element.set_location(state.clock_config.location(&output, size));
+                            custom_elements.push(CustomRenderElements::Clock(element.clone()));
+                        }
+                    }
+
+                    // the same elements the udev backend draws, minus the idle image
+                    let (elements, clear_color) = output_elements(
                         &output,
+                        state.workspaces.current_workspace().space(),
+                        custom_elements,
                         renderer,
-                        1.0,
-                        0,
-                        [state.workspaces.current_workspace().space()],
-                        &custom_elements,
-                        &mut state.backend_data.damage_tracker,
-                        [0.1, 0.1, 0.1, 1.0],
-                    )
-                    .unwrap();
+                        state.workspaces.current_workspace_index(),
+                        None,
+                        active_output.as_ref() == Some(&output),
+                        state.show_window_preview,
+                        grid_size,
+                        state.zen.as_ref().and_then(|zen| {
+                            zen.window_on(&output, state.workspaces.current_workspace_index())
+                        }),
+                        spotlight,
+                    );
+                    state
+                        .backend_data
+                        .damage_tracker
+                        .render_output(renderer, 0, &elements, clear_color)
+                        .unwrap();
                     state.backend_data.backend.submit(Some(&[damage])).unwrap();
 
                     state
@@ -324,8 +429,9 @@ pub fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    event_loop.run(None, &mut data, move |_| {
-        // Buddaraysh is running
+    event_loop.run(None, &mut data, move |data| {
+        data.state.send_ipc_window_events();
+        data.state.flush_ipc_connections();
     })?;
 
     Ok(())
@@ -1,37 +1,38 @@
 use smithay::{
-    desktop::Space,
+    desktop::{space::SpaceElement, Space},
     output::Output,
     reexports::wayland_server::protocol::wl_surface::WlSurface,
-    utils::{Logical, Point, Rectangle},
+    utils::{Logical, Point, Rectangle, Size},
 };
 
-use crate::window::WindowElement;
+use crate::{window::WindowElement, Direction};
 
 pub struct Workspaces {
     workspaces: Vec<Workspace>,
     current: usize,
 }
 
+/// Workspaces there are when the config file doesn't say
+pub const DEFAULT_WORKSPACE_AMOUNT: usize = 10;
+
+/// How far apart merged windows that would cover existing ones get stacked
+const CASCADE_STEP: i32 = 32;
+
 impl Default for Workspaces {
     fn default() -> Self {
+        Self::new(DEFAULT_WORKSPACE_AMOUNT)
+    }
+}
+
+impl Workspaces {
+    /// `amount` workspaces, there's always at least one
+    pub fn new(amount: usize) -> Self {
         Self {
-            workspaces: vec![
-                Workspace::default(),
-                Workspace::default(),
-                Workspace::default(),
-                Workspace::default(),
-                Workspace::default(),
-                Workspace::default(),
-                Workspace::default(),
-                Workspace::default(),
-                Workspace::default(),
-            ],
+            workspaces: (0..amount.max(1)).map(|_| Workspace::default()).collect(),
             current: 0,
         }
     }
-}
 
-impl Workspaces {
     pub fn get(&mut self, workspace_index: usize) -> Option<&Workspace> {
         self.workspaces.get(workspace_index)
     }
@@ -74,6 +75,13 @@ impl Workspaces {
             .next()
     }
 
+    /// Index of the workspace the window is mapped on
+    pub fn workspace_index_of(&self, window: &WindowElement) -> Option<usize> {
+        self.workspaces
+            .iter()
+            .position(|w| w.windows().any(|w| w == window))
+    }
+
     pub fn outputs(&self) -> impl Iterator<Item = &Output> {
         // should be fine to only get outputs from 1 workspace
         // since all outputs are added to all workspaces
@@ -91,11 +99,100 @@ impl Workspaces {
     pub fn current_workspace_mut(&mut self) -> &mut Workspace {
         &mut self.workspaces[self.current]
     }
+
+    /// Moves all windows of the current workspace into the target workspace and removes the
+    /// now-empty current workspace, a fresh workspace is appended so the amount stays the same.
+    ///
+    /// Returns the new index of the target workspace, or None if the index doesn't exist
+    pub fn merge_current_into(&mut self, target_index: usize) -> Option<usize> {
+        if target_index == self.current || target_index >= self.workspaces.len() {
+            return None;
+        }
+
+        let source = self.workspaces.remove(self.current);
+        let target_index = if target_index > self.current {
+            target_index - 1
+        } else {
+            target_index
+        };
+
+        let mut new_workspace = Workspace::default();
+        for output in source.outputs() {
+            if let Some(geometry) = source.output_geometry(output) {
+                new_workspace.add_output(output, geometry.loc);
+            }
+        }
+
+        let target = &mut self.workspaces[target_index];
+        let mut cascaded = 0;
+        for window in source.windows() {
+            let Some(mut location) = source.window_location(window) else {
+                continue;
+            };
+
+            let geometry = Rectangle::from_loc_and_size(location, window.geometry().size);
+            let overlaps = target.windows().any(|w| {
+                target
+                    .window_location(w)
+                    .map(|l| Rectangle::from_loc_and_size(l, w.geometry().size))
+                    .map(|g| g.overlaps(geometry))
+                    .unwrap_or(false)
+            });
+
+            // cascade windows that would end up on top of existing ones
+            if overlaps {
+                if let Some(output_geo) = target
+                    .output_under(geometry.loc.to_f64())
+                    .next()
+                    .or_else(|| target.outputs().next())
+                    .and_then(|o| target.output_geometry(o))
+                {
+                    location = cascade_location(output_geo, geometry.size, cascaded);
+                    cascaded += 1;
+                }
+            }
+
+            target.map_window(window.clone(), location, false);
+        }
+
+        self.workspaces.push(new_workspace);
+        self.current = target_index;
+
+        Some(target_index)
+    }
+}
+
+/// Spot of the `n`th cascaded window of `size` on the output at `output_geo`, starting at the
+/// center and going down and right by [`CASCADE_STEP`], starting over before it leaves the output
+fn cascade_location(
+    output_geo: Rectangle<i32, Logical>,
+    size: Size<i32, Logical>,
+    n: usize,
+) -> Point<i32, Logical> {
+    let center = output_geo.loc
+        + Point::from((
+            (output_geo.size.w - size.w) / 2,
+            (output_geo.size.h - size.h) / 2,
+        ));
+    let room = ((output_geo.size.w - size.w) / 2).min((output_geo.size.h - size.h) / 2);
+    let steps = room.max(0) / CASCADE_STEP + 1;
+    let offset = (n as i32 % steps) * CASCADE_STEP;
+
+    center + Point::from((offset, offset))
+}
+
+/// A window that was added to or removed from a workspace, kept until the IPC events go out
+#[derive(Debug)]
+pub enum WindowChange {
+    Mapped(WindowElement),
+    /// with the output it was on, it's not in the space anymore to look it up
+    Unmapped(WindowElement, Option<Output>),
 }
 
 #[derive(Default, Debug)]
 pub struct Workspace {
     space: Space<WindowElement>,
+    changes: Vec<WindowChange>,
 }
 
 impl Workspace {
@@ -121,13 +218,17 @@ impl Workspace {
         location: impl Into<Point<i32, Logical>>,
         activate: bool,
     ) {
-        self.space.map_element(window, location, activate);
+        self.map_window(window, location, activate);
     }
 
     pub fn add_output(&mut self, output: &Output, location: impl Into<Point<i32, Logical>>) {
         self.space.map_output(output, location);
     }
 
+    pub fn remove_output(&mut self, output: &Output) {
+        self.space.unmap_output(output);
+    }
+
     pub fn window_for_surface(&self, surface: &WlSurface) -> Option<WindowElement> {
         self.space
             .elements()
@@ -160,13 +261,27 @@ impl Workspace {
         location: impl Into<Point<i32, Logical>>,
         activate: bool,
     ) {
+        // mapping a window again only moves it
+        if !self.space.elements().any(|w| *w == window) {
+            self.changes.push(WindowChange::Mapped(window.clone()));
+        }
         self.space.map_element(window, location, activate);
     }
 
     pub fn unmap_window(&mut self, window: &WindowElement) {
+        if self.space.elements().any(|w| w == window) {
+            let output = self.space.outputs_for_element(window).into_iter().next();
+            self.changes
+                .push(WindowChange::Unmapped(window.clone(), output));
+        }
         self.space.unmap_elem(window);
     }
 
+    /// Windows mapped and unmapped since the last call
+    pub fn take_changes(&mut self) -> Vec<WindowChange> {
+        std::mem::take(&mut self.changes)
+    }
+
     pub fn window_location(&self, window: &WindowElement) -> Option<Point<i32, Logical>> {
         self.space.element_location(window)
     }
@@ -175,6 +290,68 @@ impl Workspace {
         self.space.raise_element(window, activate);
     }
 
+    /// Transient windows of `window` on this workspace, along with their own transients
+    pub fn transients_of(&self, window: &WindowElement) -> Vec<WindowElement> {
+        let mut transients: Vec<WindowElement> = Vec::new();
+        let mut parents = vec![window.clone()];
+        while let Some(parent) = parents.pop() {
+            let children: Vec<WindowElement> = self
+                .space
+                .elements()
+                .filter(|w| w.is_transient_for(&parent))
+                .filter(|w| *w != window && !transients.contains(*w))
+                .cloned()
+                .collect();
+            parents.extend(children.iter().cloned());
+            transients.extend(children);
+        }
+        transients
+    }
+
+    /// The window closest to `window` in `direction`, only windows overlapping `bounds` are considered
+    pub fn window_in_direction(
+        &self,
+        window: &WindowElement,
+        direction: Direction,
+        bounds: Rectangle<i32, Logical>,
+    ) -> Option<WindowElement> {
+        let center = |geometry: Rectangle<i32, Logical>| -> Point<i32, Logical> {
+            (
+                geometry.loc.x + geometry.size.w / 2,
+                geometry.loc.y + geometry.size.h / 2,
+            )
+                .into()
+        };
+        let from = center(self.space.element_geometry(window)?);
+
+        self.space
+            .elements()
+            .filter(|w| w != &window)
+            .filter_map(|w| {
+                let geometry = self.space.element_geometry(w)?;
+                if !geometry.overlaps(bounds) {
+                    return None;
+                }
+
+                let to = center(geometry);
+                let (along, across) = match direction {
+                    Direction::Left => (from.x - to.x, to.y - from.y),
+                    Direction::Right => (to.x - from.x, to.y - from.y),
+                    Direction::Up => (from.y - to.y, to.x - from.x),
+                    Direction::Down => (to.y - from.y, to.x - from.x),
+                };
+                if along <= 0 {
+                    return None;
+                }
+
+                // windows in line with the focused one win over closer ones off to the side
+                let distance = along as i64 + 2 * across.unsigned_abs() as i64;
+                Some((distance, w))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, w)| w.clone())
+    }
+
     pub fn output_under(
         &self,
         point: impl Into<Point<f64, Logical>>,
@@ -182,3 +359,107 @@ impl Workspace {
         self.space.output_under(point)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use smithay::utils::{Point, Rectangle};
+
+    use super::{cascade_location, Workspaces};
+    use crate::test_util::output;
+
+    #[test]
+    fn merge_into_the_current_workspace_does_nothing() {
+        let mut workspaces = Workspaces::default();
+        let count = workspaces.workspaces().len();
+
+        assert_eq!(workspaces.merge_current_into(0), None);
+        assert_eq!(workspaces.workspaces().len(), count);
+    }
+
+    #[test]
+    fn merge_into_a_missing_workspace_does_nothing() {
+        let mut workspaces = Workspaces::default();
+        let count = workspaces.workspaces().len();
+
+        assert_eq!(workspaces.merge_current_into(count), None);
+        assert_eq!(workspaces.current_workspace_index(), 0);
+    }
+
+    #[test]
+    fn merge_into_a_later_workspace_shifts_its_index() {
+        let mut workspaces = Workspaces::default();
+        let count = workspaces.workspaces().len();
+        workspaces.set_current_workspace(1);
+
+        assert_eq!(workspaces.merge_current_into(3), Some(2));
+        assert_eq!(workspaces.current_workspace_index(), 2);
+        assert_eq!(workspaces.workspaces().len(), count);
+    }
+
+    #[test]
+    fn merge_into_an_earlier_workspace_keeps_its_index() {
+        let mut workspaces = Workspaces::default();
+        let count = workspaces.workspaces().len();
+        workspaces.set_current_workspace(3);
+
+        assert_eq!(workspaces.merge_current_into(0), Some(0));
+        assert_eq!(workspaces.current_workspace_index(), 0);
+        assert_eq!(workspaces.workspaces().len(), count);
+    }
+
+    #[test]
+    fn merge_appends_a_workspace_with_the_same_outputs() {
+        let mut workspaces = Workspaces::default();
+        let count = workspaces.workspaces().len();
+        let output = output("DP-1", (1920, 1080), (0, 0), 1.0);
+        for workspace in workspaces.workspaces_mut() {
+            workspace.add_output(&output, (0, 0));
+        }
+
+        workspaces.merge_current_into(1);
+
+        let appended = workspaces.workspaces()[count - 1];
+        assert_eq!(appended.outputs().collect::<Vec<_>>(), vec![&output]);
+    }
+
+    #[test]
+    fn cascaded_windows_step_down_and_right_from_the_center() {
+        let output_geo = Rectangle::from_loc_and_size((1920, 0), (1920, 1080));
+        let size = (800, 600).into();
+
+        assert_eq!(
+            cascade_location(output_geo, size, 0),
+            Point::from((1920 + 560, 240))
+        );
+        assert_eq!(
+            cascade_location(output_geo, size, 1),
+            Point::from((1920 + 592, 272))
+        );
+        assert_eq!(
+            cascade_location(output_geo, size, 7),
+            Point::from((1920 + 784, 464))
+        );
+    }
+
+    #[test]
+    fn cascade_starts_over_before_leaving_the_output() {
+        let output_geo = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+        let size = (800, 600).into();
+
+        assert_eq!(
+            cascade_location(output_geo, size, 8),
+            cascade_location(output_geo, size, 0)
+        );
+    }
+
+    #[test]
+    fn cascade_of_a_window_bigger_than_the_output_stays_centered() {
+        let output_geo = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+        let size = (2000, 1200).into();
+
+        assert_eq!(
+            cascade_location(output_geo, size, 3),
+            Point::from((-40, -60))
+        );
+    }
+}